@@ -0,0 +1,104 @@
+//! Measures encoding a control packet the way the driver station does every 20ms, and checks
+//! that once the buffers have warmed up it doesn't touch the heap.
+//!
+//! Run with `cargo bench --bench control_packet`.
+
+extern crate chrono;
+extern crate libds;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use chrono::{TimeZone, Utc};
+
+use libds::{
+    joystick::Joystick,
+    messages::ds::udp::{Control, DsUdpPacket, Request},
+    states::Alliance,
+};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ITERATIONS: u32 = 100_000;
+
+fn main() {
+    let mut joysticks = vec![None; 6];
+    for (i, slot) in joysticks.iter_mut().take(4).enumerate() {
+        let mut stick = Joystick::new(12, 6, 1);
+        stick.set_axis(0, i as i8).unwrap();
+        stick.set_button(3, true).unwrap();
+        *slot = Some(stick);
+    }
+    let timezone = String::from("America/New_York");
+    let date = Utc.ymd(2024, 3, 9).and_hms(13, 45, 30);
+
+    let mut packet = DsUdpPacket {
+        sequence_num: 0,
+        comm_version: 0x01,
+        control: Control::empty(),
+        request: Request::empty(),
+        alliance: Alliance::Red(1),
+        joysticks: Vec::new(),
+        countdown: None,
+        date: None,
+        timezone: None,
+        tags: Vec::new(),
+    };
+    let mut buf = Vec::new();
+
+    // Mirrors DriverStationState::encode_udp_packet.
+    let tick = |packet: &mut DsUdpPacket, buf: &mut Vec<u8>, i: u32| {
+        packet.sequence_num = i as u16;
+        packet.control = Control::ENABLED;
+        packet.joysticks.clone_from(&joysticks);
+        packet.date = Some(date);
+        match packet.timezone {
+            Some(ref mut zone) => zone.clone_from(&timezone),
+            None => packet.timezone = Some(timezone.clone()),
+        }
+        packet.encode_into(buf);
+        black_box(&buf);
+    };
+
+    tick(&mut packet, &mut buf, 0);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        tick(&mut packet, &mut buf, i);
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!(
+        "control packet ({} bytes): {} ns/iter, {} allocations in {} iterations",
+        buf.len(),
+        elapsed.as_nanos() / u128::from(ITERATIONS),
+        allocations,
+        ITERATIONS
+    );
+    assert_eq!(allocations, 0, "encoding a control packet allocated");
+}
@@ -0,0 +1,150 @@
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+
+use libds::states::RobotMode;
+
+/// Something the user asked the driver station to do.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+    Enable,
+    Disable,
+    Estop,
+    Mode(RobotMode),
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "keys: e enable, enter/d disable, space e-stop, \
+                        t teleop, a auto, s test, h help, q quit";
+
+/// The help for commands read a line at a time, where enabling has to be spelled out.
+pub const LINE_HELP: &str = "commands: enable, disable (or an empty line), estop, \
+                             teleop, auto, test, help, quit";
+
+impl Command {
+    /// Returns the command for a key press in a terminal, ignoring releases and repeats.
+    pub fn from_key(key: KeyEvent) -> Option<Self> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('c') | KeyCode::Char('d') => Some(Command::Quit),
+                _ => None,
+            };
+        }
+        match key.code {
+            KeyCode::Enter => Some(Command::Disable),
+            KeyCode::Char(' ') => Some(Command::Estop),
+            KeyCode::Char('e') => Some(Command::Enable),
+            KeyCode::Char(c) => Command::from_word(&c.to_string()),
+            KeyCode::Esc => Some(Command::Quit),
+            _ => None,
+        }
+    }
+
+    /// Parses a command typed as a line, which is a key's letter or the command's name. An
+    /// empty line disables, like Enter does, but enabling takes the whole word so that a stray
+    /// line can't enable the robot.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            Some(Command::Disable)
+        } else {
+            Command::from_word(&line.to_lowercase())
+        }
+    }
+
+    fn from_word(word: &str) -> Option<Self> {
+        match word {
+            "enable" => Some(Command::Enable),
+            "d" | "disable" => Some(Command::Disable),
+            "estop" => Some(Command::Estop),
+            "t" | "teleop" => Some(Command::Mode(RobotMode::Teleop)),
+            "a" | "auto" => Some(Command::Mode(RobotMode::Auto)),
+            "s" | "test" => Some(Command::Mode(RobotMode::Test)),
+            "h" | "?" | "help" => Some(Command::Help),
+            "q" | "quit" => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Where commands come from: single keystrokes when run in a terminal, or lines read from
+/// stdin when it isn't one, such as when started by a service on a headless machine.
+pub enum Input {
+    Keys,
+    Lines(mpsc::Receiver<String>),
+}
+
+impl Input {
+    pub fn new() -> io::Result<Self> {
+        if io::stdin().is_terminal() {
+            terminal::enable_raw_mode()?;
+            return Ok(Input::Keys);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let sent = line.map(|line| sender.send(line).is_ok());
+                if sent.ok() != Some(true) {
+                    break;
+                }
+            }
+        });
+        Ok(Input::Lines(receiver))
+    }
+
+    /// Returns the line ending to print with, since a terminal in raw mode doesn't return to
+    /// the start of the line by itself.
+    pub fn line_ending(&self) -> &'static str {
+        match self {
+            Input::Keys => "\r\n",
+            Input::Lines(_) => "\n",
+        }
+    }
+
+    /// Returns the help for the commands this input takes.
+    pub fn help(&self) -> &'static str {
+        match self {
+            Input::Keys => HELP,
+            Input::Lines(_) => LINE_HELP,
+        }
+    }
+
+    /// Waits up to `timeout` for a command.
+    pub fn next(&mut self, timeout: Duration) -> io::Result<Option<Command>> {
+        match self {
+            Input::Keys => {
+                if !event::poll(timeout)? {
+                    return Ok(None);
+                }
+                match event::read()? {
+                    Event::Key(key) => Ok(Command::from_key(key)),
+                    _ => Ok(None),
+                }
+            }
+            Input::Lines(lines) => match lines.recv_timeout(timeout) {
+                Ok(line) => Ok(Command::from_line(&line)),
+                // Once stdin is closed nothing could disable the robot, so quit, which disables
+                // it on the way out.
+                Err(mpsc::RecvTimeoutError::Disconnected) => Ok(Some(Command::Quit)),
+                Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            },
+        }
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if let Input::Keys = self {
+            terminal::disable_raw_mode().unwrap_or(());
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! A driver station for the command line, for driving a robot from a terminal, over SSH, or
+//! from a headless machine such as a Raspberry Pi.
+//!
+//! The `libds` binary only parses its arguments and calls [`run`]; the rest lives in this library
+//! so it can be tested.
+
+extern crate clap;
+extern crate crossterm;
+extern crate libds;
+
+pub mod input;
+pub mod tui;
+
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+
+use libds::{
+    battery::BatteryWarning,
+    console::{ConsoleFilter, ConsoleMessage, ConsoleSubscription, Severity, NETCONSOLE_PORT},
+    messages::ds::tcp::MatchInfo,
+    states::{Alliance, MatchType, RobotMode},
+    ConnectionConfig, DriverStation,
+};
+
+use input::{Command, Input};
+
+/// How often to check for commands and console messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Parser)]
+#[command(
+    name = "libds",
+    version,
+    about = "An FRC driver station for the command line"
+)]
+pub struct Args {
+    /// The team number, or the robot's IP address
+    #[arg(value_parser = parse_target)]
+    robot: ConnectionConfig,
+    #[arg(long, value_enum, default_value_t = Mode::Teleop)]
+    mode: Mode,
+    /// The alliance station, such as red1 or blue3
+    #[arg(long, value_parser = parse_alliance, default_value = "red1")]
+    alliance: Alliance,
+    #[arg(long, default_value = "")]
+    game_data: String,
+    #[arg(long, default_value = "unknown")]
+    competition: String,
+    #[arg(long, value_enum, default_value_t = Match::None)]
+    match_type: Match,
+    #[arg(long, default_value_t = 0)]
+    match_number: u16,
+    #[arg(long, default_value_t = 0)]
+    replay_number: u8,
+    /// Also show what the robot prints over netconsole
+    #[arg(long)]
+    netconsole: bool,
+    /// Record the match to .dslog and .dsevents files in this directory
+    #[arg(long)]
+    log: Option<PathBuf>,
+    /// Seconds between status lines. The status is also printed whenever it changes
+    #[arg(long, default_value_t = 1.0)]
+    status_interval: f64,
+    /// Show a full-screen dashboard instead of printing status lines
+    #[arg(long)]
+    tui: bool,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Mode {
+    Teleop,
+    Auto,
+    Test,
+}
+
+impl From<Mode> for RobotMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Teleop => RobotMode::Teleop,
+            Mode::Auto => RobotMode::Auto,
+            Mode::Test => RobotMode::Test,
+        }
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Match {
+    None,
+    Practice,
+    Qualification,
+    Elimination,
+}
+
+impl From<Match> for MatchType {
+    fn from(match_type: Match) -> Self {
+        match match_type {
+            Match::None => MatchType::None,
+            Match::Practice => MatchType::Practice,
+            Match::Qualification => MatchType::Qualification,
+            Match::Elimination => MatchType::Elimination,
+        }
+    }
+}
+
+/// Parses where to find the robot: `localhost`, an IP address, or a team number.
+pub fn parse_target(target: &str) -> Result<ConnectionConfig, String> {
+    if target == "localhost" {
+        return Ok(ConnectionConfig::new([127, 0, 0, 1].into()));
+    }
+    if let Ok(address) = target.parse::<IpAddr>() {
+        return Ok(ConnectionConfig::new(address));
+    }
+    match target.parse::<u16>() {
+        Ok(team) if team < 25600 => Ok(ConnectionConfig::for_team(team)),
+        _ => Err(format!("{} is not a team number or IP address", target)),
+    }
+}
+
+/// Parses an alliance station such as `red1`, `r1` or `Blue3`.
+pub fn parse_alliance(station: &str) -> Result<Alliance, String> {
+    let station = station.to_lowercase();
+    let (color, position) = station.split_at(station.len().saturating_sub(1));
+    let position = match position.parse::<u8>() {
+        Ok(position @ 1..=3) => position,
+        _ => return Err(format!("{} is not a station from 1 to 3", position)),
+    };
+    match color {
+        "red" | "r" => Ok(Alliance::Red(position)),
+        "blue" | "b" => Ok(Alliance::Blue(position)),
+        _ => Err(format!("{} is not red or blue", color)),
+    }
+}
+
+fn mode_name(mode: RobotMode) -> &'static str {
+    match mode {
+        RobotMode::Teleop => "teleop",
+        RobotMode::Auto => "auto",
+        RobotMode::Test => "test",
+    }
+}
+
+/// Switches the robot to `mode`, disabling it first as the official driver station does. Does
+/// nothing if it's already in that mode.
+fn change_mode(ds: &DriverStation, mode: RobotMode) {
+    if mode == ds.mode() {
+        return;
+    }
+    ds.set_enabled(false).unwrap_or(());
+    ds.set_mode(mode);
+}
+
+fn warning_text(warning: BatteryWarning) -> String {
+    match warning {
+        BatteryWarning::LowVoltage { voltage, .. } => format!("battery low: {:.2}V", voltage),
+        BatteryWarning::Brownout { voltage } => format!("brownout at {:.2}V", voltage),
+    }
+}
+
+/// Formats a console message, which can run over several lines.
+fn console_text(message: &ConsoleMessage) -> String {
+    let mut text = match message.severity {
+        Severity::Print => message.text.clone(),
+        Severity::Warning | Severity::Error => {
+            let kind = if message.severity == Severity::Error {
+                "ERROR"
+            } else {
+                "WARNING"
+            };
+            let code = message
+                .error_code
+                .map_or(String::new(), |code| format!(" {}", code));
+            format!("{}{}: {} ({})", kind, code, message.text, message.location)
+        }
+    };
+    if message.count > 1 {
+        text.push_str(&format!(" (x{})", message.count));
+    }
+    text
+}
+
+/// Reports connection errors once each, rather than every time the connection retries.
+#[derive(Default)]
+struct ConnectionErrors {
+    last: Option<String>,
+}
+
+impl ConnectionErrors {
+    /// Returns the text of a new connection error, if there is one.
+    fn check(&mut self, ds: &DriverStation) -> Option<String> {
+        if ds.has_robot_tcp() {
+            // Once connected, the same error is worth reporting again if it comes back.
+            self.last = None;
+        }
+        let error = ds.connection_error()?.to_string();
+        if self.last.as_ref() == Some(&error) {
+            return None;
+        }
+        self.last = Some(error.clone());
+        Some(format!("connection: {}", error))
+    }
+}
+
+/// The parts of the status that are printed as soon as they change.
+#[derive(Clone, PartialEq)]
+struct Status {
+    comms: bool,
+    tcp: bool,
+    code: bool,
+    mode: RobotMode,
+    enabled: bool,
+    estop: bool,
+}
+
+impl Status {
+    fn read(ds: &DriverStation) -> Self {
+        Status {
+            comms: ds.has_robot_comms(),
+            tcp: ds.has_robot_tcp(),
+            code: ds.has_robot_code(),
+            mode: ds.mode(),
+            enabled: ds.is_enabled(),
+            estop: ds.is_estopped(),
+        }
+    }
+}
+
+/// Prints lines, with line endings that suit the terminal's mode.
+struct Output {
+    line_ending: &'static str,
+}
+
+impl Output {
+    fn line(&self, line: &str) {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "{}{}", line, self.line_ending).unwrap_or(());
+        stdout.flush().unwrap_or(());
+    }
+
+    fn status(&self, ds: &DriverStation, status: &Status) {
+        let state = if status.estop {
+            "E-STOPPED"
+        } else if status.enabled {
+            "ENABLED"
+        } else {
+            "disabled"
+        };
+        let link = ds.link_stats();
+        let trip = link.trip_time.map_or("-".to_owned(), |trip| {
+            format!("{:.1}ms", trip.as_secs_f32() * 1000.0)
+        });
+        self.line(&format!(
+            "comms {}  tcp {}  code {}  {} {}  {:.2}V  trip {}  loss {:.0}%",
+            if status.comms { "ok" } else { "--" },
+            if status.tcp { "ok" } else { "--" },
+            if status.code { "ok" } else { "--" },
+            mode_name(status.mode),
+            state,
+            ds.battery_voltage(),
+            trip,
+            link.packet_loss * 100.0
+        ));
+    }
+
+    fn console(&self, message: &ConsoleMessage) {
+        for text in console_text(message).lines() {
+            self.line(&format!("> {}", text));
+        }
+    }
+}
+
+/// Runs the driver station until the user quits.
+pub fn run(args: Args) -> io::Result<()> {
+    let mut ds = DriverStation::new();
+    ds.set_mode(args.mode.into());
+    ds.set_alliance(args.alliance);
+    ds.set_game_data(args.game_data);
+    ds.set_match_info(MatchInfo {
+        competition: args.competition,
+        match_type: args.match_type.into(),
+        match_number: args.match_number,
+        replay_number: args.replay_number,
+    });
+
+    let console = ds.console(ConsoleFilter::default());
+    let warnings = ds.battery_warnings();
+    let address = args.robot.address;
+    ds.connect_with(args.robot)?;
+
+    let mut notices = Vec::new();
+    if args.netconsole {
+        if let Err(e) = ds.listen_netconsole(NETCONSOLE_PORT) {
+            notices.push(format!("couldn't listen for netconsole: {}", e));
+        }
+    }
+    if let Some(ref dir) = args.log {
+        ds.start_logging(dir)?;
+    }
+
+    let result = if args.tui {
+        tui::run(&ds, address, &console, &warnings, notices)
+    } else {
+        let interval = Duration::from_secs_f64(args.status_interval.max(0.1));
+        print_status(&ds, address, &console, &warnings, notices, interval)
+    };
+
+    // Give the connection time to send that the robot is disabled before closing it.
+    ds.set_enabled(false).unwrap_or(());
+    thread::sleep(POLL_INTERVAL);
+    if args.log.is_some() {
+        ds.stop_logging()?;
+    }
+    result
+}
+
+/// Prints the status and console as lines of text, taking commands as keystrokes or lines.
+fn print_status(
+    ds: &DriverStation,
+    address: IpAddr,
+    console: &ConsoleSubscription,
+    warnings: &mpsc::Receiver<BatteryWarning>,
+    notices: Vec<String>,
+    interval: Duration,
+) -> io::Result<()> {
+    let mut input = Input::new()?;
+    let out = Output {
+        line_ending: input.line_ending(),
+    };
+    out.line(&format!("connecting to {}", address));
+    for notice in notices {
+        out.line(&notice);
+    }
+    out.line(input.help());
+
+    let mut last_status = None;
+    let mut last_printed = Instant::now();
+    let mut last_trip = None;
+    let mut errors = ConnectionErrors::default();
+    loop {
+        match input.next(POLL_INTERVAL)? {
+            Some(Command::Enable) => {
+                if let Err(reason) = ds.set_enabled(true) {
+                    out.line(&format!("can't enable: {}", reason));
+                }
+            }
+            Some(Command::Disable) => ds.set_enabled(false).unwrap_or(()),
+            Some(Command::Estop) => ds.estop(),
+            Some(Command::Mode(mode)) => change_mode(ds, mode),
+            Some(Command::Help) => out.line(input.help()),
+            Some(Command::Quit) => return Ok(()),
+            None => {}
+        }
+
+        for message in console.try_iter() {
+            out.console(&message);
+        }
+        for warning in warnings.try_iter() {
+            out.line(&warning_text(warning));
+        }
+        let trip = ds.interlock_trip();
+        if trip != last_trip {
+            if let Some(ref reason) = trip {
+                out.line(&format!("disabled: {}", reason));
+            }
+            last_trip = trip;
+        }
+        if let Some(error) = errors.check(ds) {
+            out.line(&error);
+        }
+
+        let status = Status::read(ds);
+        if last_status.as_ref() != Some(&status) || last_printed.elapsed() >= interval {
+            out.status(ds, &status);
+            last_status = Some(status);
+            last_printed = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,15 @@
+extern crate clap;
+extern crate libds_cli;
+
+use std::process;
+
+use clap::Parser;
+
+use libds_cli::Args;
+
+fn main() {
+    if let Err(e) = libds_cli::run(Args::parse()) {
+        eprintln!("libds: {}", e);
+        process::exit(1);
+    }
+}
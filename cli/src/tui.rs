@@ -0,0 +1,469 @@
+//! A full-screen dashboard, laid out like the official driver station's.
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::IpAddr;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph};
+use ratatui::Frame;
+
+use libds::{
+    battery::BatteryWarning,
+    console::{ConsoleMessage, ConsoleSubscription, Severity},
+    interlock::EnableRefusal,
+    joystick::Joystick,
+    states::RobotMode,
+    DriverStation,
+};
+
+use super::{change_mode, console_text, mode_name, warning_text, ConnectionErrors, POLL_INTERVAL};
+
+/// How often the battery voltage is sampled for the graph, and how much of it is shown.
+const VOLTAGE_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+const VOLTAGE_HISTORY: Duration = Duration::from_secs(60);
+
+/// The range of the battery graph, in volts.
+const VOLTAGE_RANGE: [f64; 2] = [6.0, 14.0];
+const VOLTAGE_LABELS: [f64; 3] = [6.0, 10.0, 14.0];
+
+/// How many console lines are kept for scrolling back through.
+const CONSOLE_HISTORY: usize = 1000;
+
+/// How long a notice replaces the key help at the bottom of the screen.
+const NOTICE_TIME: Duration = Duration::from_secs(5);
+
+/// The keys that enable the robot when pressed together, as on the official driver station.
+/// Terminals report them one at a time, so they only have to arrive within this long.
+const ENABLE_CHORD: [char; 3] = ['[', ']', '\\'];
+const CHORD_WINDOW: Duration = Duration::from_millis(500);
+
+const MODES: [RobotMode; 3] = [RobotMode::Teleop, RobotMode::Auto, RobotMode::Test];
+
+const HELP: &str = "[ ] \\ together: enable   Enter: disable   Space: E-STOP   \
+                    Left/Right: mode   PgUp/PgDn: scroll   c: clear   q: quit";
+
+struct ConsoleLine {
+    severity: Severity,
+    text: String,
+    /// Whether the line is a message from the robot, rather than a warning from the dashboard.
+    from_robot: bool,
+}
+
+/// The dashboard's state between frames, which follows the driver station it was made for.
+pub struct Dashboard<'a> {
+    ds: &'a DriverStation,
+    address: IpAddr,
+    console: VecDeque<ConsoleLine>,
+    /// How many lines the console is scrolled up from the latest.
+    scroll: usize,
+    voltages: VecDeque<(Instant, f32)>,
+    last_sample: Option<Instant>,
+    /// When each key of the enable chord was last pressed.
+    chord: [Option<Instant>; 3],
+    notice: Option<(String, Instant)>,
+    last_trip: Option<EnableRefusal>,
+    errors: ConnectionErrors,
+    quit: bool,
+}
+
+impl<'a> Dashboard<'a> {
+    pub fn new(ds: &'a DriverStation, address: IpAddr) -> Self {
+        Dashboard {
+            ds,
+            address,
+            console: VecDeque::new(),
+            scroll: 0,
+            voltages: VecDeque::new(),
+            last_sample: None,
+            chord: [None; 3],
+            notice: None,
+            last_trip: None,
+            errors: ConnectionErrors::default(),
+            quit: false,
+        }
+    }
+
+    /// Returns the notice shown in place of the key help, if one is still showing.
+    pub fn notice(&self) -> Option<&str> {
+        match self.notice {
+            Some((ref notice, at)) if at.elapsed() < NOTICE_TIME => Some(notice),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the user has asked to quit.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn notify(&mut self, notice: String) {
+        self.notice = Some((notice, Instant::now()));
+    }
+
+    fn push_console(&mut self, line: ConsoleLine) {
+        if self.console.len() == CONSOLE_HISTORY {
+            self.console.pop_front();
+        }
+        self.console.push_back(line);
+    }
+
+    fn receive(&mut self, message: ConsoleMessage) {
+        // A repeat updates the count on the line it repeats rather than adding another.
+        if message.count > 1 && self.console.back().is_some_and(|line| line.from_robot) {
+            self.console.pop_back();
+        }
+        self.push_console(ConsoleLine {
+            severity: message.severity,
+            text: console_text(&message),
+            from_robot: true,
+        });
+    }
+
+    fn cycle_mode(&mut self, step: usize) {
+        let current = MODES.iter().position(|mode| *mode == self.ds.mode());
+        let next = current.map_or(0, |i| (i + step) % MODES.len());
+        change_mode(self.ds, MODES[next]);
+    }
+
+    fn press_chord(&mut self, index: usize, now: Instant) {
+        self.chord[index] = Some(now);
+        let held = self.chord.iter().all(|pressed| {
+            pressed.is_some_and(|pressed| now.duration_since(pressed) <= CHORD_WINDOW)
+        });
+        if held {
+            self.chord = [None; 3];
+            if let Err(reason) = self.ds.set_enabled(true) {
+                self.notify(format!("Can't enable: {}", reason));
+            }
+        }
+    }
+
+    pub fn key(&mut self, key: KeyEvent) {
+        self.key_at(key, Instant::now());
+    }
+
+    /// Handles a key pressed at `now`, which decides whether the keys of the enable chord came
+    /// close enough together.
+    pub fn key_at(&mut self, key: KeyEvent, now: Instant) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        match key.code {
+            KeyCode::Enter => self.ds.set_enabled(false).unwrap_or(()),
+            KeyCode::Char(' ') => self.ds.estop(),
+            KeyCode::Char(c) if ENABLE_CHORD.contains(&c) => {
+                let index = ENABLE_CHORD.iter().position(|key| *key == c).unwrap();
+                self.press_chord(index, now);
+            }
+            KeyCode::Right | KeyCode::Tab => self.cycle_mode(1),
+            KeyCode::Left | KeyCode::BackTab => self.cycle_mode(MODES.len() - 1),
+            KeyCode::Char('t') => change_mode(self.ds, RobotMode::Teleop),
+            KeyCode::Char('a') => change_mode(self.ds, RobotMode::Auto),
+            KeyCode::Char('s') => change_mode(self.ds, RobotMode::Test),
+            KeyCode::PageUp => self.scroll = (self.scroll + 10).min(self.console.len()),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::End => self.scroll = 0,
+            KeyCode::Char('c') => {
+                self.console.clear();
+                self.scroll = 0;
+            }
+            KeyCode::Char('q') => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Takes what has arrived since the last update.
+    fn update(&mut self, console: &ConsoleSubscription, warnings: &mpsc::Receiver<BatteryWarning>) {
+        for message in console.try_iter() {
+            self.receive(message);
+        }
+        for warning in warnings.try_iter() {
+            self.push_console(ConsoleLine {
+                severity: Severity::Warning,
+                text: warning_text(warning),
+                from_robot: false,
+            });
+        }
+
+        let trip = self.ds.interlock_trip();
+        if trip != self.last_trip {
+            if let Some(ref reason) = trip {
+                self.notify(format!("Disabled: {}", reason));
+            }
+            self.last_trip = trip;
+        }
+        if let Some(error) = self.errors.check(self.ds) {
+            self.notify(error);
+        }
+
+        let now = Instant::now();
+        if self.last_sample.map_or(true, |last| {
+            now.duration_since(last) >= VOLTAGE_SAMPLE_INTERVAL
+        }) {
+            self.last_sample = Some(now);
+            if self.ds.has_robot_comms() {
+                self.voltages.push_back((now, self.ds.battery_voltage()));
+            }
+            while self
+                .voltages
+                .front()
+                .is_some_and(|(time, _)| now.duration_since(*time) > VOLTAGE_HISTORY)
+            {
+                self.voltages.pop_front();
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status, middle, console, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(10),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [battery, joysticks] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(middle);
+
+        self.draw_status(frame, status);
+        self.draw_battery(frame, battery);
+        self.draw_joysticks(frame, joysticks);
+        self.draw_console(frame, console);
+
+        let footer_line = match self.notice() {
+            Some(notice) => Line::styled(notice, Style::new().fg(Color::Yellow)),
+            None => Line::styled(HELP, Style::new().fg(Color::DarkGray)),
+        };
+        frame.render_widget(Paragraph::new(footer_line), footer);
+    }
+
+    fn draw_status(&self, frame: &mut Frame, area: Rect) {
+        let ds = self.ds;
+        let has_joystick = ds.joysticks().iter().any(Option::is_some);
+        let mut spans = vec![
+            indicator("Comms", ds.has_robot_comms()),
+            indicator("TCP", ds.has_robot_tcp()),
+            indicator("Code", ds.has_robot_code()),
+            indicator("Joysticks", has_joystick),
+            Span::raw("  "),
+        ];
+
+        let mode = ds.mode();
+        for option in MODES.iter() {
+            let style = if *option == mode {
+                Style::new().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+            } else {
+                Style::new().fg(Color::DarkGray)
+            };
+            spans.push(Span::styled(format!(" {} ", mode_name(*option)), style));
+        }
+        spans.push(Span::raw("  "));
+
+        spans.push(if ds.is_estopped() {
+            Span::styled(
+                " E-STOPPED ",
+                Style::new()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else if ds.is_enabled() {
+            Span::styled(
+                " ENABLED ",
+                Style::new()
+                    .fg(Color::Black)
+                    .bg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(
+                " DISABLED ",
+                Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )
+        });
+
+        let link = ds.link_stats();
+        let trip = link.trip_time.map_or("-".to_owned(), |trip| {
+            format!("{:.1}ms", trip.as_secs_f32() * 1000.0)
+        });
+        spans.push(Span::raw(format!(
+            "  {:.2}V  trip {}  loss {:.0}%",
+            ds.battery_voltage(),
+            trip,
+            link.packet_loss * 100.0
+        )));
+
+        let block = Block::bordered().title(format!(" libds \u{2014} {} ", self.address));
+        frame.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+    }
+
+    fn draw_battery(&self, frame: &mut Frame, area: Rect) {
+        let report = self.ds.battery();
+        let min = report
+            .phases
+            .iter()
+            .flatten()
+            .map(|phase| phase.min)
+            .fold(None, |min: Option<f32>, voltage| {
+                Some(min.map_or(voltage, |min| min.min(voltage)))
+            });
+        let mut title = format!(" Battery {:.2}V ", self.ds.battery_voltage());
+        if let Some(min) = min {
+            title.push_str(&format!("min {:.2}V ", min));
+        }
+        if !report.brownouts.is_empty() {
+            title.push_str(&format!("brownouts {} ", report.brownouts.len()));
+        }
+
+        let now = Instant::now();
+        let points: Vec<(f64, f64)> = self
+            .voltages
+            .iter()
+            .map(|(time, voltage)| {
+                let age = now.duration_since(*time).as_secs_f64();
+                let voltage = f64::from(*voltage).clamp(VOLTAGE_RANGE[0], VOLTAGE_RANGE[1]);
+                (-age, voltage)
+            })
+            .collect();
+        let dataset = Dataset::default()
+            .marker(Marker::Dot)
+            .graph_type(GraphType::Line)
+            .style(Style::new().fg(Color::Yellow))
+            .data(&points);
+        let history = VOLTAGE_HISTORY.as_secs_f64();
+        let chart = Chart::new(vec![dataset])
+            .block(Block::bordered().title(title))
+            .x_axis(
+                Axis::default()
+                    .bounds([-history, 0.0])
+                    .labels([format!("-{}s", history), "now".to_owned()]),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds(VOLTAGE_RANGE)
+                    .labels(VOLTAGE_LABELS.iter().map(|volts| format!("{}V", volts))),
+            );
+        frame.render_widget(chart, area);
+    }
+
+    fn draw_joysticks(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self
+            .ds
+            .joysticks()
+            .iter()
+            .enumerate()
+            .map(|(slot, stick)| match stick {
+                Some(stick) => Line::raw(format!("{} {}", slot, joystick_text(stick))),
+                None => Line::styled(format!("{} -", slot), Style::new().fg(Color::DarkGray)),
+            })
+            .collect();
+        let block = Block::bordered().title(" Joysticks ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_console(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let end = self.console.len() - self.scroll.min(self.console.len());
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = self
+            .console
+            .range(start..end)
+            .flat_map(|line| {
+                let style = match line.severity {
+                    Severity::Print => Style::new(),
+                    Severity::Warning => Style::new().fg(Color::Yellow),
+                    Severity::Error => Style::new().fg(Color::Red),
+                };
+                line.text
+                    .lines()
+                    .map(move |text| Line::styled(text.to_owned(), style))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        // Lines that wrap onto several are cut from the top, so the latest stays in view.
+        let skip = lines.len().saturating_sub(height);
+
+        let mut title = " Console ".to_owned();
+        if self.scroll > 0 {
+            title = format!(" Console (scrolled back {}) ", self.scroll);
+        }
+        let block = Block::bordered().title(title);
+        frame.render_widget(
+            Paragraph::new(lines.into_iter().skip(skip).collect::<Vec<_>>()).block(block),
+            area,
+        );
+    }
+}
+
+fn indicator(name: &str, ok: bool) -> Span<'static> {
+    let (mark, color) = if ok {
+        ("OK", Color::Green)
+    } else {
+        ("--", Color::Red)
+    };
+    Span::styled(format!("[{}] {}  ", mark, name), Style::new().fg(color))
+}
+
+fn joystick_text(stick: &Joystick) -> String {
+    let axes: Vec<String> = (0..stick.num_axes())
+        .filter_map(|i| stick.axis(i))
+        .map(|value| value.to_string())
+        .collect();
+    let buttons: String = (0..stick.num_buttons())
+        .filter_map(|i| stick.button(i))
+        .map(|pressed| if pressed { '#' } else { '.' })
+        .collect();
+    let povs: Vec<String> = (0..stick.num_povs())
+        .filter_map(|i| stick.pov(i))
+        .map(|value| value.to_string())
+        .collect();
+    format!(
+        "{:?}  axes [{}]  buttons [{}]  pov [{}]",
+        stick.stick_type(),
+        axes.join(" "),
+        buttons,
+        povs.join(" ")
+    )
+}
+
+/// Runs the dashboard until the user quits.
+pub fn run(
+    ds: &DriverStation,
+    address: IpAddr,
+    console: &ConsoleSubscription,
+    warnings: &mpsc::Receiver<BatteryWarning>,
+    notices: Vec<String>,
+) -> io::Result<()> {
+    let mut dashboard = Dashboard::new(ds, address);
+    for notice in notices {
+        dashboard.notify(notice);
+    }
+
+    let mut terminal = ratatui::try_init()?;
+    let result = (|| {
+        while !dashboard.quit {
+            dashboard.update(console, warnings);
+            terminal.draw(|frame| dashboard.draw(frame))?;
+            if event::poll(POLL_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    dashboard.key(key);
+                }
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
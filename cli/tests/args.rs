@@ -0,0 +1,44 @@
+extern crate libds;
+extern crate libds_cli;
+
+use std::net::IpAddr;
+
+use libds::states::Alliance;
+use libds_cli::{parse_alliance, parse_target};
+
+fn address(target: &str) -> IpAddr {
+    parse_target(target).unwrap().address
+}
+
+#[test]
+fn targets() {
+    assert_eq!(address("localhost"), IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(address("172.22.11.2"), IpAddr::from([172, 22, 11, 2]));
+    assert_eq!(address("::1"), "::1".parse::<IpAddr>().unwrap());
+    assert_eq!(address("1234"), IpAddr::from([10, 12, 34, 2]));
+    assert_eq!(address("254"), IpAddr::from([10, 2, 54, 2]));
+    assert_eq!(address("25599"), IpAddr::from([10, 255, 99, 2]));
+
+    let config = parse_target("1234").unwrap();
+    assert_eq!(config.robot_udp_port, 1110);
+    assert_eq!(config.robot_tcp_port, 1740);
+
+    assert!(parse_target("25600").is_err());
+    assert!(parse_target("-1").is_err());
+    assert!(parse_target("robot").is_err());
+    assert!(parse_target("").is_err());
+}
+
+#[test]
+fn alliances() {
+    assert_eq!(parse_alliance("red1"), Ok(Alliance::Red(1)));
+    assert_eq!(parse_alliance("r2"), Ok(Alliance::Red(2)));
+    assert_eq!(parse_alliance("Blue3"), Ok(Alliance::Blue(3)));
+    assert_eq!(parse_alliance("B1"), Ok(Alliance::Blue(1)));
+
+    assert!(parse_alliance("red0").is_err());
+    assert!(parse_alliance("red4").is_err());
+    assert!(parse_alliance("green1").is_err());
+    assert!(parse_alliance("red").is_err());
+    assert!(parse_alliance("").is_err());
+}
@@ -0,0 +1,211 @@
+extern crate crossterm;
+extern crate libds;
+extern crate libds_cli;
+
+use std::net::IpAddr;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+use libds::{states::RobotMode, DriverStation};
+use libds_cli::input::{Command, Input};
+use libds_cli::tui::Dashboard;
+
+fn press(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn ctrl(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn dashboard(ds: &DriverStation) -> Dashboard<'_> {
+    Dashboard::new(ds, IpAddr::from([127, 0, 0, 1]))
+}
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn chord_enables_within_the_window() {
+    let ds = DriverStation::new();
+    let mut dashboard = dashboard(&ds);
+    let start = Instant::now();
+
+    dashboard.key_at(press(KeyCode::Char('[')), start);
+    dashboard.key_at(press(KeyCode::Char(']')), start + ms(200));
+    assert!(!ds.is_enabled());
+    dashboard.key_at(press(KeyCode::Char('\\')), start + ms(500));
+    assert!(ds.is_enabled());
+}
+
+#[test]
+fn chord_keys_too_far_apart_do_not_enable() {
+    let ds = DriverStation::new();
+    let mut dashboard = dashboard(&ds);
+    let start = Instant::now();
+
+    dashboard.key_at(press(KeyCode::Char('[')), start);
+    dashboard.key_at(press(KeyCode::Char(']')), start + ms(300));
+    dashboard.key_at(press(KeyCode::Char('\\')), start + ms(501));
+    assert!(!ds.is_enabled());
+
+    // Pressing the stale key again brings all three back within the window.
+    dashboard.key_at(press(KeyCode::Char('[')), start + ms(700));
+    assert!(ds.is_enabled());
+
+    // The chord starts over once it has enabled.
+    ds.set_enabled(false).unwrap();
+    dashboard.key_at(press(KeyCode::Char(']')), start + ms(750));
+    assert!(!ds.is_enabled());
+}
+
+#[test]
+fn refused_chord_shows_why() {
+    let ds = DriverStation::new();
+    ds.estop();
+    let mut dashboard = dashboard(&ds);
+    let start = Instant::now();
+
+    assert_eq!(dashboard.notice(), None);
+    for (i, c) in ['[', ']', '\\'].iter().enumerate() {
+        dashboard.key_at(press(KeyCode::Char(*c)), start + ms(i as u64));
+    }
+    assert!(!ds.is_enabled());
+    assert!(dashboard
+        .notice()
+        .is_some_and(|notice| notice.starts_with("Can't enable")));
+}
+
+#[test]
+fn enter_disables_and_space_estops() {
+    let ds = DriverStation::new();
+    let mut dashboard = dashboard(&ds);
+
+    ds.set_enabled(true).unwrap();
+    dashboard.key(press(KeyCode::Enter));
+    assert!(!ds.is_enabled());
+    assert!(!ds.is_estopped());
+
+    ds.set_enabled(true).unwrap();
+    dashboard.key(press(KeyCode::Char(' ')));
+    assert!(!ds.is_enabled());
+    assert!(ds.is_estopped());
+
+    // Releases are ignored.
+    let release = KeyEvent::new_with_kind(
+        KeyCode::Char('q'),
+        KeyModifiers::NONE,
+        KeyEventKind::Release,
+    );
+    dashboard.key(release);
+    assert!(!dashboard.has_quit());
+}
+
+#[test]
+fn changing_mode_disables() {
+    let ds = DriverStation::new();
+    let mut dashboard = dashboard(&ds);
+
+    ds.set_enabled(true).unwrap();
+    dashboard.key(press(KeyCode::Char('a')));
+    assert_eq!(ds.mode(), RobotMode::Auto);
+    assert!(!ds.is_enabled());
+
+    // Choosing the mode it's already in changes nothing.
+    ds.set_enabled(true).unwrap();
+    dashboard.key(press(KeyCode::Char('a')));
+    assert!(ds.is_enabled());
+
+    dashboard.key(press(KeyCode::Right));
+    assert_eq!(ds.mode(), RobotMode::Test);
+    assert!(!ds.is_enabled());
+    dashboard.key(press(KeyCode::Right));
+    assert_eq!(ds.mode(), RobotMode::Teleop);
+    dashboard.key(press(KeyCode::Left));
+    assert_eq!(ds.mode(), RobotMode::Test);
+    dashboard.key(press(KeyCode::Char('t')));
+    assert_eq!(ds.mode(), RobotMode::Teleop);
+}
+
+#[test]
+fn dashboard_quits() {
+    let ds = DriverStation::new();
+    let mut dashboard = dashboard(&ds);
+    dashboard.key(press(KeyCode::Char('q')));
+    assert!(dashboard.has_quit());
+
+    let mut dashboard = self::dashboard(&ds);
+    dashboard.key(ctrl('c'));
+    assert!(dashboard.has_quit());
+}
+
+#[test]
+fn commands_from_keys() {
+    let key = |code| Command::from_key(press(code));
+    assert_eq!(key(KeyCode::Enter), Some(Command::Disable));
+    assert_eq!(key(KeyCode::Char(' ')), Some(Command::Estop));
+    assert_eq!(key(KeyCode::Char('e')), Some(Command::Enable));
+    assert_eq!(key(KeyCode::Char('d')), Some(Command::Disable));
+    assert_eq!(
+        key(KeyCode::Char('t')),
+        Some(Command::Mode(RobotMode::Teleop))
+    );
+    assert_eq!(
+        key(KeyCode::Char('a')),
+        Some(Command::Mode(RobotMode::Auto))
+    );
+    assert_eq!(
+        key(KeyCode::Char('s')),
+        Some(Command::Mode(RobotMode::Test))
+    );
+    assert_eq!(key(KeyCode::Char('h')), Some(Command::Help));
+    assert_eq!(key(KeyCode::Char('q')), Some(Command::Quit));
+    assert_eq!(key(KeyCode::Esc), Some(Command::Quit));
+    assert_eq!(key(KeyCode::Char('x')), None);
+    assert_eq!(key(KeyCode::Left), None);
+
+    assert_eq!(Command::from_key(ctrl('c')), Some(Command::Quit));
+    assert_eq!(Command::from_key(ctrl('d')), Some(Command::Quit));
+    assert_eq!(Command::from_key(ctrl('e')), None);
+    assert_eq!(
+        Command::from_key(KeyEvent::new_with_kind(
+            KeyCode::Char('e'),
+            KeyModifiers::NONE,
+            KeyEventKind::Release
+        )),
+        None
+    );
+}
+
+#[test]
+fn commands_from_lines() {
+    assert_eq!(Command::from_line(""), Some(Command::Disable));
+    // Unlike the Space key, a line of spaces disables rather than E-stopping.
+    assert_eq!(Command::from_line("  \n"), Some(Command::Disable));
+    assert_eq!(Command::from_line("ENABLE"), Some(Command::Enable));
+    // Enabling has to be spelled out, unlike the other commands.
+    assert_eq!(Command::from_line("e"), None);
+    assert_eq!(Command::from_line("d"), Some(Command::Disable));
+    assert_eq!(
+        Command::from_line(" auto \n"),
+        Some(Command::Mode(RobotMode::Auto))
+    );
+    assert_eq!(Command::from_line("estop"), Some(Command::Estop));
+    assert_eq!(Command::from_line("e-stop"), None);
+}
+
+#[test]
+fn closing_stdin_quits() {
+    let (sender, receiver) = mpsc::channel();
+    let mut input = Input::Lines(receiver);
+    sender.send("teleop".to_owned()).unwrap();
+    drop(sender);
+    assert_eq!(
+        input.next(ms(10)).unwrap(),
+        Some(Command::Mode(RobotMode::Teleop))
+    );
+    assert_eq!(input.next(ms(10)).unwrap(), Some(Command::Quit));
+}
@@ -19,15 +19,19 @@ fn main() {
     ds.set_match_info(MatchInfo {
         competition: "jack's super fun and definitely real frc competition".to_owned(),
         match_type: MatchType::Elimination,
+        match_number: 1,
+        replay_number: 0,
     });
 
     println!("we connected");
     thread::sleep(time::Duration::from_millis(20000));
-    ds.set_enabled(true);
-    println!("we enabled");
+    match ds.set_enabled(true) {
+        Ok(()) => println!("we enabled"),
+        Err(reason) => println!("couldn't enable: {}", reason),
+    }
     ds.set_game_data("RRR".to_string());
     thread::sleep(time::Duration::from_millis(20000));
-    ds.set_enabled(false);
+    ds.set_enabled(false).unwrap();
     println!("we disabled");
     thread::sleep(time::Duration::from_millis(20000));
     println!("we done");
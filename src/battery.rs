@@ -0,0 +1,254 @@
+//! Battery health: voltage through each phase of a match, brownouts, and the battery's internal
+//! resistance.
+//!
+//! A [BatteryMonitor] is fed the same telemetry that's recorded to `.dslog` files, so it works
+//! live from [DriverStation::battery](crate::DriverStation::battery) or offline over a
+//! [DsLog](crate::log::DsLog).
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::log::DsLogRecord;
+use crate::messages::ds::udp::Control;
+use crate::messages::rio::Status;
+use crate::states::RobotMode;
+
+/// How far the voltage has to recover above a threshold before crossing it again warns again.
+const HYSTERESIS: f32 = 0.5;
+
+/// The minimum variance of the total current, in amps squared, before there's enough spread to
+/// estimate the resistance from.
+const MIN_CURRENT_VARIANCE: f64 = 4.0;
+
+/// A phase of a match, by what was being sent to the robot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Disabled,
+    Auto,
+    Teleop,
+    Test,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [Phase::Disabled, Phase::Auto, Phase::Teleop, Phase::Test];
+
+    pub fn from_control(control: Control) -> Self {
+        if !control.contains(Control::ENABLED) {
+            return Phase::Disabled;
+        }
+        match control.robot_mode() {
+            Some(RobotMode::Auto) => Phase::Auto,
+            Some(RobotMode::Teleop) => Phase::Teleop,
+            Some(RobotMode::Test) | None => Phase::Test,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The battery voltage through one phase.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhaseVoltage {
+    pub min: f32,
+    pub average: f32,
+    pub samples: u32,
+}
+
+/// A period the robot reported being browned out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Brownout {
+    pub start: DateTime<Utc>,
+    /// How long it lasted, or [None] if it hasn't ended.
+    pub duration: Option<Duration>,
+    /// The lowest voltage during it.
+    pub min_voltage: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BatteryWarning {
+    /// The voltage dropped below one of the configured thresholds.
+    LowVoltage { threshold: f32, voltage: f32 },
+    /// The robot browned out, disabling its outputs to keep the roboRIO running.
+    Brownout { voltage: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatteryConfig {
+    /// Voltages that warn when the battery drops below them. Each warns once until the voltage
+    /// recovers by half a volt.
+    pub thresholds: Vec<f32>,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        BatteryConfig {
+            thresholds: vec![9.0, 7.5],
+        }
+    }
+}
+
+/// What a [BatteryMonitor] has seen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatteryReport {
+    /// The voltage through each phase, in the order of [Phase::ALL], if the phase was seen.
+    pub phases: [Option<PhaseVoltage>; 4],
+    pub brownouts: Vec<Brownout>,
+    /// The estimated internal resistance of the battery and its wiring in ohms, if the current
+    /// drawn from the power distribution panel has varied enough to estimate it.
+    pub resistance: Option<f32>,
+}
+
+impl BatteryReport {
+    pub fn phase(&self, phase: Phase) -> Option<PhaseVoltage> {
+        self.phases[phase.index()]
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct PhaseTotals {
+    min: f32,
+    sum: f64,
+    samples: u32,
+}
+
+/// Running sums for a least squares fit of voltage against current.
+#[derive(Copy, Clone, Default)]
+struct Regression {
+    n: f64,
+    current: f64,
+    voltage: f64,
+    current_squared: f64,
+    current_voltage: f64,
+}
+
+impl Regression {
+    fn add(&mut self, current: f64, voltage: f64) {
+        self.n += 1.0;
+        self.current += current;
+        self.voltage += voltage;
+        self.current_squared += current * current;
+        self.current_voltage += current * voltage;
+    }
+
+    /// Returns the resistance, which is how much the voltage sags per amp drawn.
+    fn resistance(&self) -> Option<f32> {
+        if self.n < 2.0 {
+            return None;
+        }
+        let mean_current = self.current / self.n;
+        let variance = self.current_squared / self.n - mean_current * mean_current;
+        if variance < MIN_CURRENT_VARIANCE {
+            return None;
+        }
+        let covariance = self.current_voltage / self.n - mean_current * self.voltage / self.n;
+        Some((-covariance / variance) as f32)
+    }
+}
+
+/// Tracks the battery over a match from telemetry taken every 20ms.
+///
+/// Samples with no battery voltage, which is what's recorded while there's no robot, are
+/// ignored.
+#[derive(Clone, Default)]
+pub struct BatteryMonitor {
+    config: BatteryConfig,
+    phases: [Option<PhaseTotals>; 4],
+    brownouts: Vec<Brownout>,
+    regression: Regression,
+    /// Whether each threshold has warned and not yet been re-armed.
+    tripped: Vec<bool>,
+}
+
+impl BatteryMonitor {
+    pub fn new(config: BatteryConfig) -> Self {
+        BatteryMonitor {
+            tripped: vec![false; config.thresholds.len()],
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &BatteryConfig {
+        &self.config
+    }
+
+    /// Adds a sample taken at `time`, returning any warnings it causes.
+    pub fn update(&mut self, time: DateTime<Utc>, record: &DsLogRecord) -> Vec<BatteryWarning> {
+        let voltage = record.battery_voltage;
+        let mut warnings = Vec::new();
+        if voltage <= 0.0 {
+            return warnings;
+        }
+
+        let totals =
+            self.phases[Phase::from_control(record.control).index()].get_or_insert(PhaseTotals {
+                min: voltage,
+                ..Default::default()
+            });
+        totals.min = totals.min.min(voltage);
+        totals.sum += f64::from(voltage);
+        totals.samples += 1;
+
+        let browned_out = record.status.contains(Status::BROWNOUT);
+        match self.brownouts.last_mut() {
+            Some(brownout) if brownout.duration.is_none() => {
+                brownout.min_voltage = brownout.min_voltage.min(voltage);
+                if !browned_out {
+                    brownout.duration = Some((time - brownout.start).to_std().unwrap_or_default());
+                }
+            }
+            _ if browned_out => {
+                self.brownouts.push(Brownout {
+                    start: time,
+                    duration: None,
+                    min_voltage: voltage,
+                });
+                warnings.push(BatteryWarning::Brownout { voltage });
+            }
+            _ => {}
+        }
+
+        let current = record.total_current();
+        if current > 0.0 {
+            self.regression.add(f64::from(current), f64::from(voltage));
+        }
+
+        self.tripped.resize(self.config.thresholds.len(), false);
+        for (threshold, tripped) in self.config.thresholds.iter().zip(&mut self.tripped) {
+            if !*tripped && voltage < *threshold {
+                *tripped = true;
+                warnings.push(BatteryWarning::LowVoltage {
+                    threshold: *threshold,
+                    voltage,
+                });
+            } else if *tripped && voltage >= threshold + HYSTERESIS {
+                *tripped = false;
+            }
+        }
+        warnings
+    }
+
+    /// Forgets everything seen, keeping the configuration, such as at the start of a match.
+    pub fn reset(&mut self) {
+        *self = BatteryMonitor::new(self.config.clone());
+    }
+
+    pub fn report(&self) -> BatteryReport {
+        let mut phases = [None; 4];
+        for (phase, totals) in phases.iter_mut().zip(&self.phases) {
+            *phase = totals.map(|totals| PhaseVoltage {
+                min: totals.min,
+                average: (totals.sum / f64::from(totals.samples)) as f32,
+                samples: totals.samples,
+            });
+        }
+        BatteryReport {
+            phases,
+            brownouts: self.brownouts.clone(),
+            resistance: self.regression.resistance(),
+        }
+    }
+}
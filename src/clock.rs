@@ -0,0 +1,36 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+
+/// A source of time, so that anything driven by the clock can be tested without waiting.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// A source of the current date and time, which is sent to the robot when it asks for it.
+pub trait WallClock {
+    fn now_utc(&self) -> DateTime<Utc>;
+}
+
+/// A [Clock] that reads the system's monotonic clock, and a [WallClock] that reads the system
+/// time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+impl WallClock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Returns the host's IANA timezone name, such as `America/New_York`, or `UTC` if it can't be
+/// determined.
+pub fn host_timezone() -> String {
+    iana_time_zone::get_timezone().unwrap_or_else(|_| String::from("UTC"))
+}
@@ -0,0 +1,157 @@
+//! Framing for the TCP side of the protocol, where every message is prefixed with its length.
+//!
+//! The driver station, robot and FMS all send frames of a [u16] length followed by that many
+//! bytes, usually a tag id and its contents. [FramedStream] wraps a non-blocking [TcpStream]
+//! and buffers in both directions, so a frame split across reads is kept until the rest of it
+//! arrives, several frames arriving in one read are all returned, and a frame the socket could
+//! only partly accept is finished on a later [flush](FramedStream::flush).
+
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// How many bytes can be waiting to be written before [send](FramedStream::send) refuses more.
+pub const DEFAULT_MAX_PENDING: usize = 64 * 1024;
+
+/// A non-blocking TCP stream that sends and receives length-prefixed frames.
+#[derive(Debug)]
+pub struct FramedStream {
+    stream: TcpStream,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    max_pending: usize,
+}
+
+impl FramedStream {
+    /// Wraps `stream`, making it non-blocking.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        Self::with_max_pending(stream, DEFAULT_MAX_PENDING)
+    }
+
+    /// Wraps `stream`, allowing at most `max_pending` bytes to be waiting to be written.
+    pub fn with_max_pending(stream: TcpStream, max_pending: usize) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(FramedStream {
+            stream,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            max_pending,
+        })
+    }
+
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Returns the number of bytes waiting to be written.
+    pub fn pending(&self) -> usize {
+        self.write_buf.len()
+    }
+
+    /// Queues already framed bytes, such as one or more tags from `to_packet`, and writes as
+    /// much as the socket will take.
+    ///
+    /// If the peer isn't keeping up and the bytes would take the queue past its limit, nothing
+    /// is queued and an error of kind [WouldBlock](io::ErrorKind::WouldBlock) is returned, so the
+    /// caller can hold on to them and try again later.
+    pub fn send(&mut self, frames: &[u8]) -> io::Result<()> {
+        self.flush()?;
+        if !self.write_buf.is_empty() && self.write_buf.len() + frames.len() > self.max_pending {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.write_buf.extend_from_slice(frames);
+        self.flush()
+    }
+
+    /// Sends frames from the front of `queue` until it's empty or the stream pushes back,
+    /// leaving the rest for a later call.
+    pub fn send_queued(&mut self, queue: &mut VecDeque<Vec<u8>>) -> io::Result<()> {
+        while let Some(frames) = queue.front() {
+            match self.send(frames) {
+                Ok(()) => {
+                    queue.pop_front();
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a length prefix to `contents` and sends it as one frame.
+    ///
+    /// Returns an error of kind [InvalidInput](io::ErrorKind::InvalidInput) if `contents` is
+    /// longer than a [u16] length can describe.
+    pub fn send_frame(&mut self, contents: &[u8]) -> io::Result<()> {
+        if contents.len() > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame is longer than 65535 bytes",
+            ));
+        }
+        let mut frame = Vec::with_capacity(contents.len() + 2);
+        frame.extend_from_slice(&(contents.len() as u16).to_be_bytes());
+        frame.extend_from_slice(contents);
+        self.send(&frame)
+    }
+
+    /// Writes as much of the queue as the socket will take without blocking.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.write_buf.len() {
+                break Ok(());
+            }
+            match self.stream.write(&self.write_buf[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.write_buf.drain(..written);
+        result
+    }
+
+    /// Reads whatever is available and returns every complete frame received, without their
+    /// lengths. Returns an error once the stream is closed.
+    pub fn receive(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        let mut chunk = [0u8; 512];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(split_frames(&mut self.read_buf))
+    }
+
+    /// Flushes the queue and then receives, which is what a connection does each tick.
+    pub fn poll(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        self.flush()?;
+        self.receive()
+    }
+}
+
+/// Removes every complete frame from the start of `buf`, leaving any partial frame behind.
+fn split_frames(buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while buf.len() - start >= 2 {
+        let size = NetworkEndian::read_u16(&buf[start..]) as usize;
+        if buf.len() - start < size + 2 {
+            break;
+        }
+        frames.push(buf[start + 2..start + 2 + size].to_vec());
+        start += size + 2;
+    }
+    buf.drain(..start);
+    frames
+}
@@ -1,124 +1,169 @@
+use std::collections::VecDeque;
 use std::io;
-use std::io::{Read, Write};
 use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-use byteorder::{ByteOrder, NetworkEndian};
-
+use crate::codec::FramedStream;
 use crate::ds::DriverStationState;
 use crate::messages::{ds::tcp::*, rio::*};
 
+/// How long to wait for the robot to accept the TCP connection.
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long to wait before trying the TCP connection again, which doubles after each failure
+/// up to the maximum.
+const TCP_RETRY_MIN: Duration = Duration::from_millis(250);
+const TCP_RETRY_MAX: Duration = Duration::from_secs(4);
+
+/// How long each pass of the connection loop waits for a status packet, which bounds how late
+/// control packets, tags and TCP frames are handled without spinning while nothing arrives.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Where to find the robot, and the local port to receive its status packets on.
+#[derive(Clone, Debug)]
+pub struct ConnectionConfig {
+    pub address: IpAddr,
+    pub robot_udp_port: u16,
+    pub robot_tcp_port: u16,
+    /// The local UDP port. Control packets are sent from it and status packets are received on it.
+    pub ds_udp_port: u16,
+}
+
+impl ConnectionConfig {
+    pub fn new(address: IpAddr) -> Self {
+        ConnectionConfig {
+            address,
+            robot_udp_port: 1110,
+            robot_tcp_port: 1740,
+            ds_udp_port: 1150,
+        }
+    }
+
+    /// Creates a config for the robot of team `team` on its radio's network, at `10.TE.AM.2`.
+    pub fn for_team(team: u16) -> Self {
+        ConnectionConfig::new([10, (team / 100) as u8, (team % 100) as u8, 2].into())
+    }
+}
+
 pub struct DSConnection {
-    thread: JoinHandle<()>,
+    thread: Option<JoinHandle<()>>,
     sender: mpsc::Sender<Signal>,
     errors: mpsc::Receiver<io::Result<()>>,
-    err: Option<io::Error>,
 }
 
 impl DSConnection {
-    pub fn new(addr: IpAddr, state: Arc<Mutex<DriverStationState>>) -> io::Result<Self> {
+    pub fn new(
+        config: ConnectionConfig,
+        state: Arc<Mutex<DriverStationState>>,
+    ) -> io::Result<Self> {
+        let udp = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), config.ds_udp_port))?;
+        udp.set_read_timeout(Some(POLL_INTERVAL))?;
+        let robot_udp = SocketAddr::new(config.address, config.robot_udp_port);
+
         let (sender_signal, receiver_signal) = mpsc::channel::<Signal>();
+        {
+            let mut state = state.lock().unwrap();
+            state.robot_tcp = Some(sender_signal.clone());
+            state.robot_tcp_connected = false;
+            state.link.reset();
+        }
 
         let (sender_res, receiver_res) = mpsc::channel::<io::Result<()>>();
 
-        // TODO: Create sockets within other thread and add mechanism to detect status of connections
-        // (connecting, failed, connected, error, etc)
-
         let mut last = Instant::now();
 
         let t = thread::spawn(move || {
-            println!("udp start");
-            let udp = UdpSocket::bind("0.0.0.0:1149").unwrap();
-
-            udp.connect(SocketAddr::new(addr.clone(), 1110)).unwrap();
-            println!("udp 2");
-            let udp_recv = UdpSocket::bind("0.0.0.0:1150").unwrap();
-            udp_recv.set_nonblocking(true).unwrap();
-            println!("udp started");
-
-            udp.send(state.lock().unwrap().udp_packet().as_ref())
-                .unwrap();
-
-            println!("tcp start");
-            let mut tcp = TcpStream::connect(SocketAddr::new(addr.clone(), 1740)).unwrap();
-            tcp.set_nonblocking(true).unwrap();
-
-            println!(
-                "{:?}",
-                GameData::new(state.lock().unwrap().game_data.clone()).to_packet()
-            );
-            tcp.write(
-                GameData::new(state.lock().unwrap().game_data.clone())
-                    .to_packet()
-                    .as_slice(),
-            )
-            .unwrap();
-            tcp.write(
-                state
-                    .lock()
-                    .unwrap()
-                    .match_info
-                    .clone()
-                    .to_packet()
-                    .as_slice(),
-            )
-            .unwrap();
+            // Reused every tick so the steady state doesn't allocate.
+            let mut control_buf = Vec::new();
+            let mut udp_buf = [0u8; 100];
+            // The last error sending a control packet, which is only reported once until
+            // sending works again rather than every 20ms.
+            let mut udp_error = None;
+
+            {
+                let mut state = state.lock().unwrap();
+                let sequence_num = state.encode_udp_packet(&mut control_buf);
+                state.link.on_sent(sequence_num, last);
+            }
+            udp.send_to(&control_buf, robot_udp).unwrap_or(0);
+
+            let mut tcp = RobotTcp::new(SocketAddr::new(config.address, config.robot_tcp_port));
+            let mut tcp_queue = VecDeque::new();
 
             loop {
                 match receiver_signal.try_recv() {
                     Ok(Signal::Disconnect) | Err(mpsc::TryRecvError::Disconnected) => break,
-                    Ok(Signal::Tcp(tag)) => {
-                        match tcp.write(tag.to_packet().as_slice()) {
-                            Ok(n) => println!("wrote {}", n),
-                            Err(e) => {} //TODO
-                        }
+                    Ok(Signal::Tcp(tag)) if tcp.stream.is_some() => {
+                        let version = state.lock().unwrap().protocol_version();
+                        tcp_queue.push_back(tag.to_packet_for(version));
                     }
                     _ => {}
                 }
 
-                let mut udp_buf = vec![0u8; 100];
-                match udp_recv.recv_from(&mut udp_buf) {
-                    Ok((n, f)) => {
-                        if let Some(packet) = RioUdpPacket::from_bytes(Vec::from(&udp_buf[0..n])) {
-                            state.lock().unwrap().update_from_udp(packet);
-                        }
+                match tcp.connect(Instant::now()) {
+                    Ok(true) => {
+                        // Whatever was queued for the last connection is superseded by the
+                        // current game data and match info.
+                        tcp_queue.clear();
+                        let mut state = state.lock().unwrap();
+                        let version = state.protocol_version();
+                        tcp_queue.push_back(
+                            GameData::new(state.game_data.clone()).to_packet_for(version),
+                        );
+                        tcp_queue.push_back(state.match_info.to_packet_for(version));
+                        state.robot_tcp_connected = true;
                     }
+                    Ok(false) => {}
                     Err(e) => {
-                        if e.kind() != io::ErrorKind::WouldBlock {
-                            if let Err(e) = sender_res.send(Err(e)) {
-                                break;
-                            }
+                        if sender_res.send(Err(e)).is_err() {
+                            break;
                         }
                     }
                 }
 
-                let mut size_buf = vec![0u8; 2];
-                match tcp.read_exact(&mut size_buf) {
-                    Ok(_) => {
-                        let size = NetworkEndian::read_u16(&size_buf);
-                        println!("tcp size: {}", size);
-                        let mut buf = vec![0u8; size as usize];
-                        match tcp.read_exact(&mut buf) {
-                            Ok(_) => {
-                                if let Some(packet) = RioTcpPacket::from_bytes(buf) {
-                                    state.lock().unwrap().update_from_tcp(packet);
-                                }
-                            }
-                            Err(e) => {
-                                if e.kind() != io::ErrorKind::WouldBlock {
-                                    if let Err(e) = sender_res.send(Err(e)) {
-                                        break;
-                                    }
-                                }
-                            }
+                match udp.recv_from(&mut udp_buf) {
+                    Ok((n, _)) => {
+                        if let Ok(packet) = RioUdpPacket::from_bytes(&udp_buf[0..n]) {
+                            let mut state = state.lock().unwrap();
+                            state.link.on_received(packet.sequence_num, Instant::now());
+                            state.update_from_udp(packet);
                         }
                     }
+                    // Timing out is reported as either kind, depending on the platform.
+                    Err(ref e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut => {}
                     Err(e) => {
-                        if e.kind() != io::ErrorKind::WouldBlock {
-                            if let Err(e) = sender_res.send(Err(e)) {
+                        if sender_res.send(Err(e)).is_err() {
+                            break;
+                        }
+                    }
+                }
+
+                if let Some(ref mut stream) = tcp.stream {
+                    let frames = stream
+                        .send_queued(&mut tcp_queue)
+                        .and_then(|_| stream.receive());
+                    match frames {
+                        Ok(frames) => {
+                            if !frames.is_empty() {
+                                let packets = frames
+                                    .iter()
+                                    .filter_map(|frame| RioTcpPacket::from_bytes(frame).ok())
+                                    .collect();
+                                state.lock().unwrap().update_from_tcp(packets);
+                            }
+                        }
+                        Err(e) => {
+                            // The control packets keep going, and the connection is made again
+                            // once the robot accepts it.
+                            tcp.disconnect(Instant::now());
+                            tcp_queue.clear();
+                            state.lock().unwrap().robot_tcp_connected = false;
+                            if sender_res.send(Err(e)).is_err() {
                                 break;
                             }
                         }
@@ -127,12 +172,21 @@ impl DSConnection {
 
                 if last.elapsed() >= Duration::from_millis(20) {
                     last = Instant::now();
-                    let packet = state.lock().unwrap().udp_packet();
-                    match udp.send(packet.as_ref()) {
-                        Ok(s) => {} // println!("udp sent {:?}", packet),
+                    {
+                        let mut state = state.lock().unwrap();
+                        state.enforce_interlocks(last);
+                        let sequence_num = state.encode_udp_packet(&mut control_buf);
+                        state.link.on_sent(sequence_num, last);
+                        state.record_telemetry(last);
+                        state.console.deliver_repeats(last);
+                    }
+                    match udp.send_to(&control_buf, robot_udp) {
+                        Ok(_) => udp_error = None,
                         Err(e) => {
-                            if e.kind() != io::ErrorKind::WouldBlock {
-                                if let Err(e) = sender_res.send(Err(e)) {
+                            let kind = e.kind();
+                            if kind != io::ErrorKind::WouldBlock && udp_error != Some(kind) {
+                                udp_error = Some(kind);
+                                if sender_res.send(Err(e)).is_err() {
                                     break;
                                 }
                             }
@@ -140,13 +194,14 @@ impl DSConnection {
                     }
                 }
             }
+
+            state.lock().unwrap().robot_tcp_connected = false;
         });
 
         Ok(DSConnection {
-            thread: t,
+            thread: Some(t),
             sender: sender_signal,
             errors: receiver_res,
-            err: None,
         })
     }
 
@@ -160,73 +215,95 @@ impl DSConnection {
             Ok(ior) => ior,
         }
     }
-
-    pub fn send_tcp(&self, tag: TcpTag) {
-        self.sender.send(Signal::Tcp(tag)).unwrap();
-    }
 }
 
 impl Drop for DSConnection {
     fn drop(&mut self) {
         self.sender.send(Signal::Disconnect).unwrap_or(());
+        // Waiting for the thread frees the port before the next connection binds it.
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
     }
 }
 
-pub enum Signal {
-    Tcp(TcpTag),
-    Disconnect,
-}
-
-struct TcpConnection{
-    stream: TcpStream,
-    queue: Vec<u8>,
-    received: Vec<RioTcpPacket>,
-    next_tag_len: Option<u16>,
+/// The TCP connection to the robot. Connecting happens on its own thread, so a robot that's
+/// still booting or isn't reachable doesn't hold up the control packets.
+struct RobotTcp {
+    addr: SocketAddr,
+    stream: Option<FramedStream>,
+    connecting: Option<mpsc::Receiver<io::Result<TcpStream>>>,
+    next_attempt: Instant,
+    backoff: Duration,
 }
 
-impl TcpConnection {
-    fn new(addr: SocketAddr) -> io::Result<Self> {
-        let stream = TcpStream::connect(addr)?;
-        stream.set_nonblocking(true)?;
-        Ok(Self {
-            stream,
-            queue: Vec::new(),
-            received: Vec::new(),
-            next_tag_len: None,
-        })
+impl RobotTcp {
+    fn new(addr: SocketAddr) -> Self {
+        RobotTcp {
+            addr,
+            stream: None,
+            connecting: None,
+            next_attempt: Instant::now(),
+            backoff: TCP_RETRY_MIN,
+        }
     }
 
-    fn send_tag(&mut self, tag: TcpTag) {
-        self.queue.append(&mut tag.to_packet());
-    }
+    /// Starts connecting if it's time to try again, or finishes connecting. Returns whether the
+    /// connection was just made, or why an attempt failed.
+    fn connect(&mut self, now: Instant) -> io::Result<bool> {
+        if self.stream.is_some() {
+            return Ok(false);
+        }
 
-    fn tick(&mut self) {
-        if !self.queue.is_empty() {
-            match self.stream.write(&self.queue) {
-                Ok(len) => self.queue = self.queue.split_off(len),
-                Err(e) => panic!("aah"),
-            }
+        if let Some(ref connecting) = self.connecting {
+            let result = match connecting.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return Ok(false),
+                Err(mpsc::TryRecvError::Disconnected) => Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "connecting thread stopped",
+                )),
+            };
+            self.connecting = None;
+            return match result.and_then(FramedStream::new) {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    self.backoff = TCP_RETRY_MIN;
+                    Ok(true)
+                }
+                Err(e) => {
+                    self.retry_later(now);
+                    Err(e)
+                }
+            };
         }
 
-        if let Some(ref len) = self.next_tag_len {
-            let mut buf = vec![0u8; usize::from(*len)];
-            match self.stream.read_exact(&mut buf) {
-                Ok(_) => {
-                    self.next_tag_len = None;
-                    if let Some(tag) = RioTcpPacket::from_bytes(buf) {
-                        self.received.push(tag)
-                    }
-                },
-                Err(e) => {},
-            }
-        } else {
-            let mut buf = vec![0u8; 2];
-            match self.stream.read_exact(&mut buf) {
-                Ok(_) => {
-                    self.next_tag_len = Some(NetworkEndian::read_u16(&buf));
-                },
-                Err(e) => {},
-            }
+        if now >= self.next_attempt {
+            let (sender, receiver) = mpsc::channel();
+            let addr = self.addr;
+            thread::spawn(move || {
+                sender
+                    .send(TcpStream::connect_timeout(&addr, TCP_CONNECT_TIMEOUT))
+                    .unwrap_or(());
+            });
+            self.connecting = Some(receiver);
         }
+        Ok(false)
+    }
+
+    /// Drops the connection after an error, to be made again after the backoff.
+    fn disconnect(&mut self, now: Instant) {
+        self.stream = None;
+        self.retry_later(now);
     }
+
+    fn retry_later(&mut self, now: Instant) {
+        self.next_attempt = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(TCP_RETRY_MAX);
+    }
+}
+
+pub enum Signal {
+    Tcp(TcpTag),
+    Disconnect,
 }
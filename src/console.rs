@@ -0,0 +1,290 @@
+//! The robot's console: what its code prints, and the errors and warnings it reports.
+//!
+//! Subscribe with [DriverStation::console](crate::DriverStation::console) to receive the
+//! messages that match a [ConsoleFilter]. Messages arrive over the TCP connection to the robot,
+//! and over netconsole once [DriverStation::listen_netconsole] has been called for robot code
+//! that prints there instead.
+//!
+//! [DriverStation::listen_netconsole]: crate::DriverStation::listen_netconsole
+
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::messages::rio::RioTcpPacket;
+
+/// The UDP port robot code prints to with netconsole.
+pub const NETCONSOLE_PORT: u16 = 6666;
+
+/// How long repeats of a message are held back before their count is delivered, when no
+/// different message arrives first.
+pub const REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many recent messages are remembered to recognize ones the robot sends again after
+/// reconnecting.
+const SEEN_HISTORY: usize = 256;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Printed to standard output.
+    #[default]
+    Print,
+    Warning,
+    Error,
+}
+
+/// How a message arrived.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConsoleSource {
+    /// Over the TCP connection to the robot.
+    Tcp,
+    /// As a line printed over netconsole. These only have text, so their robot time and sequence
+    /// number are 0.
+    NetConsole,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsoleMessage {
+    pub severity: Severity,
+    pub source: ConsoleSource,
+    /// When the driver station received it.
+    pub time: DateTime<Utc>,
+    /// The robot's timestamp, in seconds since its code started.
+    pub robot_time: f32,
+    pub sequence_number: u16,
+    /// The error code of an error or warning.
+    pub error_code: Option<u16>,
+    /// The printed text, or an error's details.
+    pub text: String,
+    /// Where an error or warning was reported from.
+    pub location: String,
+    pub call_stack: String,
+    /// How many times in a row the message has been received. See [ConsoleSubscription].
+    pub count: u32,
+}
+
+impl ConsoleMessage {
+    /// Converts a message from the robot, if it's one that's printed to the console.
+    pub fn from_packet(packet: &RioTcpPacket, time: DateTime<Utc>) -> Option<Self> {
+        match packet {
+            RioTcpPacket::StandardOutput {
+                timestamp,
+                sequence_number,
+                message,
+            } => Some(ConsoleMessage {
+                severity: Severity::Print,
+                source: ConsoleSource::Tcp,
+                time,
+                robot_time: *timestamp,
+                sequence_number: *sequence_number,
+                error_code: None,
+                text: message.clone(),
+                location: String::new(),
+                call_stack: String::new(),
+                count: 1,
+            }),
+            RioTcpPacket::ErrorMessage {
+                timestamp,
+                sequence_number,
+                error_code,
+                is_error,
+                details,
+                location,
+                call_stack,
+                ..
+            } => Some(ConsoleMessage {
+                severity: if *is_error {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                },
+                source: ConsoleSource::Tcp,
+                time,
+                robot_time: *timestamp,
+                sequence_number: *sequence_number,
+                error_code: Some(*error_code),
+                text: details.clone(),
+                location: location.clone(),
+                call_stack: call_stack.clone(),
+                count: 1,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `other` says the same thing, regardless of when it was sent.
+    pub fn is_repeat_of(&self, other: &ConsoleMessage) -> bool {
+        self.severity == other.severity
+            && self.error_code == other.error_code
+            && self.text == other.text
+            && self.location == other.location
+            && self.call_stack == other.call_stack
+    }
+}
+
+/// Which messages a subscription receives. The default lets everything through.
+#[derive(Clone, Debug, Default)]
+pub struct ConsoleFilter {
+    /// The least severe messages received.
+    pub min_severity: Severity,
+    /// If not empty, only errors and warnings with these codes are received.
+    pub error_codes: Vec<u16>,
+    /// If set, only messages whose text matches are received.
+    pub pattern: Option<Regex>,
+    /// If set, only messages whose location contains this are received.
+    pub location: Option<String>,
+}
+
+impl ConsoleFilter {
+    pub fn matches(&self, message: &ConsoleMessage) -> bool {
+        message.severity >= self.min_severity
+            && (self.error_codes.is_empty()
+                || message
+                    .error_code
+                    .is_some_and(|code| self.error_codes.contains(&code)))
+            && self
+                .pattern
+                .as_ref()
+                .map_or(true, |pattern| pattern.is_match(&message.text))
+            && self.location.as_ref().map_or(true, |location| {
+                message.location.contains(location.as_str())
+            })
+    }
+}
+
+/// Receives the console messages that match a filter, in the order the robot sent them.
+///
+/// A message that repeats the one delivered before it isn't delivered itself. Repeats are counted
+/// until a different message arrives, or for up to [REPEAT_INTERVAL], and then the last of them
+/// is delivered with its [count](ConsoleMessage::count) so far, so a display can update its last
+/// line rather than add another.
+pub struct ConsoleSubscription {
+    receiver: mpsc::Receiver<ConsoleMessage>,
+}
+
+impl ConsoleSubscription {
+    /// Returns the next message if there is one, without waiting.
+    pub fn try_recv(&self) -> Option<ConsoleMessage> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next message.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<ConsoleMessage> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Returns every message waiting, without waiting for more.
+    pub fn try_iter(&self) -> impl Iterator<Item = ConsoleMessage> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+struct Subscriber {
+    filter: ConsoleFilter,
+    sender: mpsc::Sender<ConsoleMessage>,
+    /// The last message delivered, for counting repeats.
+    last: Option<ConsoleMessage>,
+    /// The latest repeat of `last` that hasn't been delivered, with its count, and when the
+    /// first repeat held back arrived.
+    repeat: Option<(ConsoleMessage, Instant)>,
+}
+
+impl Subscriber {
+    /// Sends `message`, returning false once the subscription has been dropped.
+    fn send(&mut self, message: ConsoleMessage) -> bool {
+        self.last = Some(message.clone());
+        self.sender.send(message).is_ok()
+    }
+
+    /// Sends the repeat being held back, if there is one.
+    fn send_repeat(&mut self) -> bool {
+        match self.repeat.take() {
+            Some((repeat, _)) => self.send(repeat),
+            None => true,
+        }
+    }
+}
+
+/// Delivers console messages to subscribers.
+#[derive(Default)]
+pub(crate) struct Console {
+    subscribers: Vec<Subscriber>,
+    /// The sequence numbers and robot timestamps of recent messages. A message is only sent
+    /// again if both match, since restarted robot code numbers its messages from the start.
+    seen: VecDeque<(u16, u32)>,
+}
+
+impl Console {
+    pub fn subscribe(&mut self, filter: ConsoleFilter) -> ConsoleSubscription {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(Subscriber {
+            filter,
+            sender,
+            last: None,
+            repeat: None,
+        });
+        ConsoleSubscription { receiver }
+    }
+
+    /// Delivers messages received together, putting them in the order the robot sent them and
+    /// dropping any that were already delivered before a reconnect.
+    pub fn deliver(&mut self, mut messages: Vec<ConsoleMessage>) {
+        if let Some(first) = messages.first().map(|message| message.sequence_number) {
+            // Sequence numbers wrap, so they're ordered by their distance from the first.
+            messages.sort_by_key(|message| message.sequence_number.wrapping_sub(first) as i16);
+        }
+        for message in messages {
+            let key = (message.sequence_number, message.robot_time.to_bits());
+            if self.seen.contains(&key) {
+                continue;
+            }
+            if self.seen.len() == SEEN_HISTORY {
+                self.seen.pop_front();
+            }
+            self.seen.push_back(key);
+            self.deliver_one(message);
+        }
+    }
+
+    /// Delivers messages that have no sequence numbers, in the order given.
+    pub fn deliver_unsequenced<I: IntoIterator<Item = ConsoleMessage>>(&mut self, messages: I) {
+        for message in messages {
+            self.deliver_one(message);
+        }
+    }
+
+    /// Delivers the repeats that have been held back for [REPEAT_INTERVAL] by `now`.
+    pub fn deliver_repeats(&mut self, now: Instant) {
+        self.subscribers
+            .retain_mut(|subscriber| match subscriber.repeat {
+                Some((_, since)) if now.saturating_duration_since(since) >= REPEAT_INTERVAL => {
+                    subscriber.send_repeat()
+                }
+                _ => true,
+            });
+    }
+
+    fn deliver_one(&mut self, message: ConsoleMessage) {
+        let now = Instant::now();
+        // Subscriptions that have been dropped are forgotten.
+        self.subscribers.retain_mut(|subscriber| {
+            if !subscriber.filter.matches(&message) {
+                return true;
+            }
+            let last = subscriber.repeat.as_ref().map(|(repeat, _)| repeat);
+            match last.or(subscriber.last.as_ref()) {
+                Some(last) if message.is_repeat_of(last) => {
+                    let since = subscriber.repeat.as_ref().map_or(now, |(_, since)| *since);
+                    let mut message = message.clone();
+                    message.count = last.count + 1;
+                    subscriber.repeat = Some((message, since));
+                    true
+                }
+                _ => subscriber.send_repeat() && subscriber.send(message.clone()),
+            }
+        });
+    }
+}
@@ -1,13 +1,33 @@
+use std::collections::VecDeque;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use crate::battery::{BatteryMonitor, BatteryWarning};
+use crate::clock::{host_timezone, SystemClock, WallClock};
+use crate::connection::Signal;
+use crate::console::{Console, ConsoleMessage};
+use crate::fms::FmsControl;
+use crate::interlock::{EnableRefusal, Interlocks};
 use crate::joystick::Joystick;
-use crate::messages::{ds::tcp::MatchInfo, rio::*};
-use crate::packet::PacketWriter;
+use crate::link::LinkMonitor;
+use crate::log::{DsLogRecord, Logger};
+use crate::messages::{
+    ds::tcp::{MatchInfo, TcpTag},
+    ds::udp::{Control, DsUdpPacket, Request},
+    fms::StationStatus,
+    rio::*,
+};
+use crate::pdp::{PdpHistory, PdpSample};
 use crate::states::{Alliance, MatchType, RobotMode};
+use crate::version::ProtocolVersion;
 
-use chrono::prelude::*;
+/// The number of brownout start times kept for the brownout storm interlock, unless its count
+/// needs more.
+const BROWNOUT_HISTORY: usize = 32;
 
-const TIMEZONE: &'static str = "UTC";
+/// How long the robot can go without sending a status packet before comms are considered lost.
+const COMMS_TIMEOUT: Duration = Duration::from_secs(1);
 
-#[derive(Clone)]
 pub struct DriverStationState {
     pub joysticks: Vec<Option<Joystick>>,
     pub estop: bool,
@@ -16,8 +36,40 @@ pub struct DriverStationState {
     pub alliance: Alliance,
     pub game_data: String,
     pub match_info: MatchInfo,
+    pub fms_attached: bool,
+    pub fms_station: Option<StationStatus>,
+    pub interlocks: Interlocks,
+    pub interlock_trip: Option<EnableRefusal>,
+    /// The IANA timezone sent to the robot along with the date.
+    pub timezone: String,
+    pub(crate) wall_clock: Arc<dyn WallClock + Send + Sync>,
+    pub(crate) robot_status: Status,
+    pub(crate) robot_trace: Trace,
+    pub(crate) last_robot_packet: Option<Instant>,
+    pub(crate) brownouts: VecDeque<Instant>,
+    pub(crate) battery_voltage: f32,
+    /// The roboRIO's CPU usage and CAN utilization from 0 to 1, if it has reported them.
+    pub(crate) robot_cpu: f32,
+    pub(crate) robot_can: f32,
+    /// Where the match is being recorded, if it is.
+    pub(crate) logger: Option<Logger>,
+    pub(crate) console: Console,
+    pub(crate) link: LinkMonitor,
+    pub(crate) battery: BatteryMonitor,
+    pub(crate) battery_warnings: Vec<mpsc::Sender<BatteryWarning>>,
+    pub(crate) pdp: PdpHistory,
+    pub(crate) pdp_samples: Vec<mpsc::Sender<PdpSample>>,
+    pub(crate) robot_tcp: Option<mpsc::Sender<Signal>>,
+    /// Whether the TCP connection to the robot is up.
+    pub(crate) robot_tcp_connected: bool,
+    /// The protocol version to use regardless of what the robot reports.
+    pub(crate) forced_version: Option<ProtocolVersion>,
+    /// The protocol version the robot last reported.
+    pub(crate) robot_version: Option<ProtocolVersion>,
     sequence_num: u16,
     request_time: bool,
+    /// The last control packet, updated in place so that sending one doesn't allocate.
+    control_packet: DsUdpPacket,
 }
 
 impl DriverStationState {
@@ -25,44 +77,105 @@ impl DriverStationState {
         Default::default()
     }
 
-    pub fn udp_packet(&mut self) -> Vec<u8> {
-        let mut packet = PacketWriter::new();
-
-        // Packet number in case they arrive out of order
-        packet.write_u16(self.sequence_num);
-        self.sequence_num += 1;
-
-        packet.write_u8(0x01); // comm version
-        packet.write_u8(self.control_byte().bits()); // control byte
-        packet.write_u8(Request::empty().bits()); // TODO: actually restart code or rio with this byte.
-        packet.write_u8(self.alliance.to_position_u8()); // alliance
-
-        // joystick tags
-        for stick in &self.joysticks {
-            if let Some(stick) = stick {
-                let mut tag = stick.udp_tag();
-                packet.write_u8(tag.len() as u8 + 1); // size
-                packet.write_u8(0x0c); // id
-                packet.write_vec(tag); // joystick tag info
-            } else {
-                // Empty joystick tag
-                packet.write_u8(0x01); // size
-                packet.write_u8(0x0c); // id
+    /// Encodes the next control packet to send to the robot into `buf`, without allocating once
+    /// `buf` is large enough, and returns its sequence number.
+    pub fn encode_udp_packet(&mut self, buf: &mut Vec<u8>) -> u16 {
+        let sequence_num = self.sequence_num;
+        let control = self.control_byte();
+        let comm_version = self.protocol_version().comm_version();
+        let packet = &mut self.control_packet;
+        packet.sequence_num = sequence_num;
+        packet.comm_version = comm_version;
+        packet.control = control;
+        packet.alliance = self.alliance.clone();
+        packet.joysticks.clone_from(&self.joysticks);
+        if self.request_time {
+            packet.date = Some(self.wall_clock.now_utc());
+            match packet.timezone {
+                Some(ref mut timezone) => timezone.clone_from(&self.timezone),
+                None => packet.timezone = Some(self.timezone.clone()),
             }
+        } else {
+            packet.date = None;
+            packet.timezone = None;
         }
+        packet.encode_into(buf);
+        // Packet number in case they arrive out of order
+        self.sequence_num = self.sequence_num.wrapping_add(1);
+        sequence_num
+    }
 
-        // datetime and timezone
-        if self.request_time {
-            // timezone
-            packet.write_u8(TIMEZONE.len() as u8 + 1); // size
-            packet.write_u8(0x10); // id
-            packet.write_slice(TIMEZONE.as_bytes());
+    /// Returns the protocol version being spoken: the forced one if there is one, otherwise the
+    /// one the robot reported, or the newest before the robot has said.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.forced_version
+            .or(self.robot_version)
+            .unwrap_or_default()
+    }
 
-            // date and time
-            packet.write_vec(date_packet());
+    /// Forces a protocol version, or with [None] goes back to following the robot.
+    pub fn force_protocol_version(&mut self, version: Option<ProtocolVersion>) {
+        let before = self.protocol_version();
+        self.forced_version = version;
+        self.resend_if_version_changed(before);
+    }
+
+    /// The match info tag's layout depends on the version, so the robot needs a fresh copy
+    /// whenever the version changes.
+    fn resend_if_version_changed(&mut self, before: ProtocolVersion) {
+        if self.protocol_version() != before {
+            self.send_tcp(TcpTag::MatchInfo(self.match_info.clone()));
         }
+    }
+
+    /// Returns the number of control packets sent to the robot, wrapping at [u16::MAX].
+    pub fn sent_packets(&self) -> u16 {
+        self.sequence_num
+    }
 
-        packet.into_vec()
+    pub fn has_robot_comms(&self, now: Instant) -> bool {
+        match self.last_robot_packet {
+            Some(last) => now.saturating_duration_since(last) <= COMMS_TIMEOUT,
+            None => false,
+        }
+    }
+
+    /// Sends a tag to the robot if it is connected.
+    pub fn send_tcp(&self, tag: TcpTag) {
+        if let Some(ref sender) = self.robot_tcp {
+            sender.send(Signal::Tcp(tag)).unwrap_or(());
+        }
+    }
+
+    pub fn check_interlocks(&self, now: Instant) -> Result<(), EnableRefusal> {
+        self.interlocks.check(self, now)
+    }
+
+    /// Applies control from an FMS, enabling the robot only if the interlocks allow it.
+    pub fn apply_fms_control(&mut self, control: &FmsControl) -> Result<(), EnableRefusal> {
+        self.estop |= control.estop;
+        self.mode = control.mode;
+        self.alliance = control.alliance.clone();
+
+        if control.enabled {
+            if let Err(reason) = self.check_interlocks(Instant::now()) {
+                self.enabled = false;
+                self.interlock_trip = Some(reason.clone());
+                return Err(reason);
+            }
+        }
+        self.enabled = control.enabled;
+        Ok(())
+    }
+
+    /// Disables the robot if it is enabled and no longer satisfies the interlocks.
+    pub fn enforce_interlocks(&mut self, now: Instant) {
+        if self.enabled {
+            if let Err(reason) = self.check_interlocks(now) {
+                self.enabled = false;
+                self.interlock_trip = Some(reason);
+            }
+        }
     }
 
     fn control_byte(&self) -> Control {
@@ -70,7 +183,9 @@ impl DriverStationState {
         if self.estop {
             byte |= Control::ESTOP;
         }
-        // fms is never connected, but if it were that would go here
+        if self.fms_attached {
+            byte |= Control::FMS_CONNECTED;
+        }
         if self.enabled {
             byte |= Control::ENABLED;
         }
@@ -80,33 +195,114 @@ impl DriverStationState {
         byte
     }
 
-    pub fn update_from_tcp(&mut self, packet: RioTcpPacket) {
-        // TODO: implement
+    /// Returns the current telemetry, as it would be recorded in a `.dslog` file.
+    pub fn telemetry(&self, now: Instant) -> DsLogRecord {
+        let link = self.link.stats(now);
+        DsLogRecord {
+            trip_time_ms: link
+                .trip_time
+                .map_or(0.0, |trip| trip.as_secs_f32() * 1000.0),
+            packet_loss: link.packet_loss,
+            battery_voltage: self.battery_voltage,
+            rio_cpu: self.robot_cpu,
+            control: self.control_byte(),
+            status: self.robot_status,
+            trace: self.robot_trace,
+            watchdog: !self.has_robot_comms(now),
+            can_utilization: self.robot_can,
+            wifi_db: 0.0,
+            bandwidth_mb: 0.0,
+            pdp: self
+                .pdp
+                .latest()
+                .map(|sample| sample.pdp.clone())
+                .unwrap_or_default(),
+            pdh: None,
+        }
+    }
+
+    /// Takes the current telemetry, writing it to the log if logging and passing it to the
+    /// battery monitor. Called every 20ms.
+    pub fn record_telemetry(&mut self, now: Instant) {
+        let record = self.telemetry(now);
+        self.write_log(|logger| logger.log(&record));
+
+        // Without comms the battery voltage is the last one the robot reported, which would
+        // look like a battery holding steady at it.
+        if !self.has_robot_comms(now) {
+            return;
+        }
+        let warnings = self.battery.update(self.wall_clock.now_utc(), &record);
+        if !warnings.is_empty() {
+            self.battery_warnings
+                .retain(|sender| warnings.iter().all(|warning| sender.send(*warning).is_ok()));
+        }
+    }
+
+    /// Runs `write` on the logger, and stops logging if it fails so a full disk doesn't stop
+    /// the robot being driven.
+    fn write_log<F: FnOnce(&mut Logger) -> std::io::Result<()>>(&mut self, write: F) {
+        if let Some(ref mut logger) = self.logger {
+            if write(logger).is_err() {
+                self.logger = None;
+            }
+        }
+    }
+
+    /// Handles the messages received from the robot in one read.
+    pub fn update_from_tcp(&mut self, packets: Vec<RioTcpPacket>) {
+        let now = self.wall_clock.now_utc();
+        let mut console = Vec::new();
+        for packet in &packets {
+            self.write_log(|logger| logger.log_message(now, packet));
+            console.extend(ConsoleMessage::from_packet(packet, now));
+        }
+        self.console.deliver(console);
+        // TODO: handle the other tags
     }
 
     pub fn update_from_udp(&mut self, packet: RioUdpPacket) {
+        let now = Instant::now();
         self.request_time = packet.request_date;
+        self.last_robot_packet = Some(now);
+
+        let before = self.protocol_version();
+        self.robot_version = Some(ProtocolVersion::from_comm_version(packet.comm_version));
+        self.resend_if_version_changed(before);
+
+        if packet.status.contains(Status::BROWNOUT) && !self.robot_status.contains(Status::BROWNOUT)
+        {
+            let history = self
+                .interlocks
+                .brownout_storm
+                .map_or(BROWNOUT_HISTORY, |storm| storm.count.max(BROWNOUT_HISTORY));
+            while self.brownouts.len() >= history {
+                self.brownouts.pop_front();
+            }
+            self.brownouts.push_back(now);
+        }
+        self.robot_status = packet.status;
+        self.robot_trace = packet.trace;
+        self.battery_voltage = packet.battery_voltage;
+        if let Ok(Some(cpu)) = packet.cpu_usage() {
+            self.robot_cpu = cpu;
+        }
+        if let Some(can) = packet.can_utilization() {
+            self.robot_can = can;
+        }
+        if let Some(pdp) = packet.pdp() {
+            let sample = PdpSample {
+                time: self.wall_clock.now_utc(),
+                pdp,
+            };
+            self.pdp_samples
+                .retain(|sender| sender.send(sample.clone()).is_ok());
+            self.pdp.push(sample);
+        }
         // TODO: Finish implementing this
     }
 }
 
-fn date_packet() -> Vec<u8> {
-    let mut packet = PacketWriter::new();
-    let now = Utc::now();
-    packet.write_u8(11); // size
-    packet.write_u8(0x0f); // id
-    let nanos = now.nanosecond();
-    let micros = nanos / 1000;
-    packet.write_u32(micros);
-    packet.write_u8(now.second() as u8);
-    packet.write_u8(now.minute() as u8);
-    packet.write_u8(now.hour() as u8);
-    packet.write_u8(now.day() as u8); // should this be day0?
-    packet.write_u8(now.month0() as u8);
-    packet.write_u8((now.year() - 1900) as u8);
-    packet.into_vec()
-}
-
 impl Default for DriverStationState {
     fn default() -> Self {
         DriverStationState {
@@ -119,42 +315,47 @@ impl Default for DriverStationState {
             match_info: MatchInfo {
                 competition: String::from("unknown"),
                 match_type: MatchType::None,
+                match_number: 0,
+                replay_number: 0,
             },
+            fms_attached: false,
+            fms_station: None,
+            interlocks: Interlocks::default(),
+            interlock_trip: None,
+            timezone: host_timezone(),
+            wall_clock: Arc::new(SystemClock),
+            robot_status: Status::empty(),
+            robot_trace: Trace::empty(),
+            last_robot_packet: None,
+            brownouts: VecDeque::new(),
+            battery_voltage: 0.0,
+            robot_cpu: 0.0,
+            robot_can: 0.0,
+            logger: None,
+            console: Console::default(),
+            link: LinkMonitor::new(),
+            battery: BatteryMonitor::default(),
+            battery_warnings: Vec::new(),
+            pdp: PdpHistory::default(),
+            pdp_samples: Vec::new(),
+            robot_tcp: None,
+            robot_tcp_connected: false,
+            forced_version: None,
+            robot_version: None,
             sequence_num: 0,
             request_time: false,
+            control_packet: DsUdpPacket {
+                sequence_num: 0,
+                comm_version: 0x01,
+                control: Control::empty(),
+                request: Request::empty(), // TODO: actually restart code or rio with this byte.
+                alliance: Alliance::Red(1),
+                joysticks: Vec::new(),
+                countdown: None,
+                date: None,
+                timezone: None,
+                tags: Vec::new(),
+            },
         }
     }
 }
-
-bitflags! {
-    pub struct Control: u8 {
-        const ESTOP = 0b1000_0000;
-        const FMS_CONNECTED = 0b0000_1000;
-        const ENABLED = 0b0000_0100;
-
-        const TELEOP = 0b00;
-        const TEST = 0b01;
-        const AUTO = 0b10;
-    }
-}
-
-impl Control {
-    pub fn robot_mode(&self) -> Option<RobotMode> {
-        return if self.contains(Control::AUTO) {
-            Some(RobotMode::Auto)
-        } else if self.contains(Control::TELEOP) {
-            Some(RobotMode::Teleop)
-        } else if self.contains(Control::TEST) {
-            Some(RobotMode::Test)
-        } else {
-            None
-        };
-    }
-}
-
-bitflags! {
-    pub struct Request: u8 {
-        const REBOOT_ROBORIO = 0b1000;
-        const RESTART_ROBOT_CODE = 0b0100;
-    }
-}
@@ -0,0 +1,299 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::codec::FramedStream;
+use crate::ds::DriverStationState;
+use crate::fms::FmsControl;
+use crate::messages::ds::tcp::{GameData, MatchInfo, TcpTag};
+use crate::messages::fms::*;
+use crate::states::MatchType;
+
+const STATUS_PERIOD: Duration = Duration::from_millis(250);
+const TCP_RETRY_PERIOD: Duration = Duration::from_secs(1);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Where to find the FMS and how to identify to it.
+#[derive(Clone, Debug)]
+pub struct FmsConfig {
+    pub team: u16,
+    pub address: IpAddr,
+    /// The local UDP port control packets are received on. Status packets are sent from it too.
+    pub control_port: u16,
+    pub status_port: u16,
+    pub tcp_port: u16,
+    /// How long to go without a control packet before the robot is disabled.
+    pub timeout: Duration,
+}
+
+impl FmsConfig {
+    pub fn new(team: u16, address: IpAddr) -> Self {
+        FmsConfig {
+            team,
+            address,
+            control_port: DS_CONTROL_PORT,
+            status_port: FMS_STATUS_PORT,
+            tcp_port: FMS_TCP_PORT,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+pub struct FmsConnection {
+    thread: Option<JoinHandle<()>>,
+    stop: mpsc::Sender<()>,
+}
+
+impl FmsConnection {
+    pub fn new(config: FmsConfig, state: Arc<Mutex<DriverStationState>>) -> io::Result<Self> {
+        let udp = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), config.control_port))?;
+        udp.set_nonblocking(true)?;
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            let mut client = FmsClient::new(config, udp, state);
+            while let Err(mpsc::TryRecvError::Empty) = stopped.try_recv() {
+                client.tick(Instant::now());
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            let mut state = client.state.lock().unwrap();
+            state.fms_attached = false;
+            state.fms_station = None;
+            state.enabled = false;
+        });
+
+        Ok(FmsConnection {
+            thread: Some(t),
+            stop,
+        })
+    }
+}
+
+impl Drop for FmsConnection {
+    fn drop(&mut self) {
+        self.stop.send(()).unwrap_or(());
+        // Waiting for the thread frees the ports before anything else binds them.
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
+
+struct FmsClient {
+    config: FmsConfig,
+    udp: UdpSocket,
+    state: Arc<Mutex<DriverStationState>>,
+    tcp: Option<FramedStream>,
+    /// The result of the connection being made on its own thread, so an FMS that doesn't
+    /// answer doesn't hold up the control and status packets.
+    connecting: Option<mpsc::Receiver<io::Result<TcpStream>>>,
+    last_tcp_attempt: Option<Instant>,
+    last_control: Option<Instant>,
+    last_status: Option<Instant>,
+    sequence_num: u16,
+    event_code: String,
+    match_id: (MatchType, u16, u8),
+}
+
+impl FmsClient {
+    fn new(config: FmsConfig, udp: UdpSocket, state: Arc<Mutex<DriverStationState>>) -> Self {
+        FmsClient {
+            config,
+            udp,
+            state,
+            tcp: None,
+            connecting: None,
+            last_tcp_attempt: None,
+            last_control: None,
+            last_status: None,
+            sequence_num: 0,
+            event_code: String::new(),
+            match_id: (MatchType::None, 0, 0),
+        }
+    }
+
+    fn tick(&mut self, now: Instant) {
+        self.connect_tcp(now);
+        self.receive_tcp();
+        self.receive_udp(now);
+
+        if let Some(last) = self.last_control {
+            if now.saturating_duration_since(last) > self.config.timeout {
+                self.state.lock().unwrap().enabled = false;
+            }
+        }
+
+        let status_due = match self.last_status {
+            Some(last) => now.saturating_duration_since(last) >= STATUS_PERIOD,
+            None => true,
+        };
+        if status_due {
+            self.last_status = Some(now);
+            self.send_status(now);
+        }
+    }
+
+    fn connect_tcp(&mut self, now: Instant) {
+        if self.tcp.is_some() {
+            return;
+        }
+
+        if let Some(ref connecting) = self.connecting {
+            let stream = match connecting.try_recv() {
+                Ok(stream) => stream,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "connecting thread stopped",
+                )),
+            };
+            self.connecting = None;
+            let hello = DsFmsTcpTag::TeamNumber(self.config.team).to_packet();
+            let stream = stream
+                .and_then(FramedStream::new)
+                .and_then(|mut stream| stream.send(&hello).map(|_| stream));
+            self.tcp = stream.ok();
+            return;
+        }
+
+        if let Some(last) = self.last_tcp_attempt {
+            if now.saturating_duration_since(last) < TCP_RETRY_PERIOD {
+                return;
+            }
+        }
+        self.last_tcp_attempt = Some(now);
+
+        let (sender, receiver) = mpsc::channel();
+        let addr = SocketAddr::new(self.config.address, self.config.tcp_port);
+        thread::spawn(move || {
+            sender
+                .send(TcpStream::connect_timeout(&addr, TCP_CONNECT_TIMEOUT))
+                .unwrap_or(());
+        });
+        self.connecting = Some(receiver);
+    }
+
+    fn receive_tcp(&mut self) {
+        let frames = match self.tcp {
+            Some(ref mut stream) => stream.poll(),
+            None => return,
+        };
+        match frames {
+            Ok(frames) => {
+                for tag in frames
+                    .into_iter()
+                    .filter_map(|frame| FmsTcpTag::from_bytes(&frame).ok())
+                {
+                    self.handle_tag(tag);
+                }
+            }
+            Err(_) => self.tcp = None,
+        }
+    }
+
+    fn handle_tag(&mut self, tag: FmsTcpTag) {
+        match tag {
+            FmsTcpTag::EventCode(code) => {
+                self.event_code = code;
+                self.update_match_info();
+            }
+            FmsTcpTag::StationInfo { status, .. } => {
+                self.state.lock().unwrap().fms_station = Some(status);
+            }
+            FmsTcpTag::GameData(data) => {
+                let mut state = self.state.lock().unwrap();
+                if state.game_data != data {
+                    state.game_data = data.clone();
+                    state.send_tcp(TcpTag::GameData(GameData::new(data)));
+                }
+            }
+        }
+    }
+
+    fn receive_udp(&mut self, now: Instant) {
+        let mut buf = [0u8; 64];
+        while let Ok((n, _)) = self.udp.recv_from(&mut buf) {
+            let packet = match FmsControlPacket::from_bytes(&buf[..n]) {
+                Ok(packet) => packet,
+                Err(_) => continue,
+            };
+            self.last_control = Some(now);
+
+            {
+                let mut state = self.state.lock().unwrap();
+                state.fms_attached = true;
+                // A refusal is recorded as the interlock trip, so there's nothing else to do here.
+                let _ = state.apply_fms_control(&FmsControl {
+                    enabled: packet.enabled,
+                    estop: packet.estop,
+                    mode: packet.mode,
+                    alliance: packet.alliance,
+                });
+            }
+
+            let match_id = (packet.match_type, packet.match_number, packet.replay_number);
+            if match_id != self.match_id {
+                self.match_id = match_id;
+                self.update_match_info();
+            }
+        }
+    }
+
+    fn update_match_info(&self) {
+        let (match_type, match_number, replay_number) = self.match_id;
+        let info = MatchInfo {
+            competition: self.event_code.clone(),
+            match_type,
+            match_number,
+            replay_number,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.fms_attached && state.match_info != info {
+            state.match_info = info.clone();
+            state.send_tcp(TcpTag::MatchInfo(info));
+        }
+    }
+
+    fn send_status(&mut self, now: Instant) {
+        let packet = {
+            let state = self.state.lock().unwrap();
+            let link = state.link.stats(now);
+
+            let mut status = DsStatus::from_bits_truncate(state.mode as u8);
+            if state.estop {
+                status |= DsStatus::ESTOP;
+            }
+            if state.enabled {
+                status |= DsStatus::ENABLED;
+            }
+            // The radio and roboRIO aren't pinged, so only comms are reported and the ping bits
+            // are left clear rather than guessed from them.
+            if state.has_robot_comms(now) {
+                status |= DsStatus::ROBOT_COMMS;
+            }
+
+            DsStatusPacket {
+                sequence_num: self.sequence_num,
+                status,
+                team: self.config.team,
+                battery_voltage: state.battery_voltage,
+                comms: Some(CommsMetrics {
+                    lost_packets: link.lost.min(u64::from(u16::MAX)) as u16,
+                    sent_packets: state.sent_packets(),
+                    trip_time_ms: link
+                        .average_trip_time
+                        .map_or(0, |trip| trip.as_millis().min(u128::from(u8::MAX)) as u8),
+                }),
+            }
+        };
+        self.sequence_num = self.sequence_num.wrapping_add(1);
+
+        let addr = SocketAddr::new(self.config.address, self.config.status_port);
+        self.udp.send_to(&packet.to_bytes(), addr).unwrap_or(0);
+    }
+}
@@ -0,0 +1,18 @@
+mod client;
+mod server;
+
+pub use self::client::FmsConfig;
+pub(crate) use self::client::FmsConnection;
+pub use self::server::{FmsServer, FmsServerConfig, StationReport};
+
+use crate::states::{Alliance, RobotMode};
+
+/// The control state an FMS commands a driver station to send to its robot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FmsControl {
+    pub enabled: bool,
+    /// E-stops are latched, so clearing this does not clear an e-stop that already happened.
+    pub estop: bool,
+    pub mode: RobotMode,
+    pub alliance: Alliance,
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::codec::FramedStream;
+use crate::match_timer::{MatchConfig, MatchPhase};
+use crate::messages::fms::*;
+use crate::states::{Alliance, MatchType, RobotMode};
+
+const CONTROL_PERIOD: Duration = Duration::from_millis(100);
+/// How long a driver station can go without sending a status packet before it's disconnected.
+const DS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The ports an [FmsServer] listens and sends on.
+#[derive(Clone, Debug)]
+pub struct FmsServerConfig {
+    pub status_port: u16,
+    pub tcp_port: u16,
+    /// The port control packets are sent to on each driver station, or [None] to reply to the
+    /// port its status packets come from.
+    pub ds_control_port: Option<u16>,
+}
+
+impl Default for FmsServerConfig {
+    fn default() -> Self {
+        FmsServerConfig {
+            status_port: FMS_STATUS_PORT,
+            tcp_port: FMS_TCP_PORT,
+            ds_control_port: Some(DS_CONTROL_PORT),
+        }
+    }
+}
+
+/// The state of one of the six driver station positions on the field.
+#[derive(Clone, Debug)]
+pub struct StationReport {
+    pub alliance: Alliance,
+    pub team: Option<u16>,
+    pub connected: bool,
+    pub estop: bool,
+    /// The last status packet received from the driver station in this station.
+    pub status: Option<DsStatusPacket>,
+}
+
+/// A minimal FMS that runs matches for up to six driver stations.
+///
+/// Teams are assigned to stations with [assign](FmsServer::assign), and any driver station that
+/// connects with an assigned team number is controlled as part of the match.
+pub struct FmsServer {
+    field: Arc<Mutex<Field>>,
+    thread: Option<JoinHandle<()>>,
+    stop: mpsc::Sender<()>,
+    status_port: u16,
+    tcp_port: u16,
+}
+
+impl FmsServer {
+    pub fn start(config: FmsServerConfig) -> io::Result<Self> {
+        let udp = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), config.status_port))?;
+        udp.set_nonblocking(true)?;
+        let listener = TcpListener::bind(SocketAddr::new([0, 0, 0, 0].into(), config.tcp_port))?;
+        listener.set_nonblocking(true)?;
+
+        let status_port = udp.local_addr()?.port();
+        let tcp_port = listener.local_addr()?.port();
+
+        let field = Arc::new(Mutex::new(Field::new(config.ds_control_port)));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let t = {
+            let field = field.clone();
+            thread::spawn(move || {
+                let mut pending = Vec::new();
+                while let Err(mpsc::TryRecvError::Empty) = stopped.try_recv() {
+                    while let Ok((stream, _)) = listener.accept() {
+                        if let Ok(stream) = FramedStream::new(stream) {
+                            pending.push(stream);
+                        }
+                    }
+                    field
+                        .lock()
+                        .unwrap()
+                        .tick(Instant::now(), &udp, &mut pending);
+                    thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+
+        Ok(FmsServer {
+            field,
+            thread: Some(t),
+            stop,
+            status_port,
+            tcp_port,
+        })
+    }
+
+    /// Returns the port status packets are received on, which is useful if it was chosen by the OS.
+    pub fn status_port(&self) -> u16 {
+        self.status_port
+    }
+
+    /// Returns the port driver stations connect to over TCP.
+    pub fn tcp_port(&self) -> u16 {
+        self.tcp_port
+    }
+
+    /// Puts a team in a station, or clears the station with [None].
+    /// Stations outside of 1-3 are ignored.
+    pub fn assign(&self, station: Alliance, team: Option<u16>) {
+        if let Some(index) = station_index(&station) {
+            let mut field = self.field.lock().unwrap();
+            if let Some(old) = field.stations[index].team {
+                field.send_tcp(
+                    old,
+                    FmsTcpTag::StationInfo {
+                        alliance: station.clone(),
+                        status: StationStatus::Waiting,
+                    },
+                );
+            }
+            field.stations[index] = Station { team, estop: false };
+            if let Some(team) = team {
+                field.send_tcp(
+                    team,
+                    FmsTcpTag::StationInfo {
+                        alliance: station,
+                        status: StationStatus::Good,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Starts the countdown to a new match, clearing any e-stops from the last one.
+    pub fn start_match(&self, config: MatchConfig) {
+        let mut field = self.field.lock().unwrap();
+        for station in field.stations.iter_mut() {
+            station.estop = false;
+        }
+        if let Some(ref info) = config.match_info {
+            field.broadcast(FmsTcpTag::EventCode(info.competition.clone()));
+        }
+        field.config = config;
+        field.started = Some(Instant::now());
+        field.phase = MatchPhase::Countdown;
+    }
+
+    /// Ends the match early, disabling every robot.
+    pub fn abort_match(&self) {
+        let mut field = self.field.lock().unwrap();
+        field.started = None;
+        field.phase = MatchPhase::Finished;
+    }
+
+    /// E-stops the robot in a station until the next match starts.
+    pub fn estop(&self, station: Alliance) {
+        if let Some(index) = station_index(&station) {
+            self.field.lock().unwrap().stations[index].estop = true;
+        }
+    }
+
+    pub fn phase(&self) -> MatchPhase {
+        self.field.lock().unwrap().phase
+    }
+
+    /// Returns the time left in the current match phase.
+    pub fn remaining(&self) -> Duration {
+        self.field.lock().unwrap().remaining(Instant::now())
+    }
+
+    pub fn stations(&self) -> Vec<StationReport> {
+        let field = self.field.lock().unwrap();
+        field
+            .stations
+            .iter()
+            .enumerate()
+            .map(|(index, station)| {
+                let link = station.team.and_then(|team| field.links.get(&team));
+                StationReport {
+                    alliance: Alliance::from_position_u8(index as u8),
+                    team: station.team,
+                    connected: link.is_some_and(|link| link.addr.is_some()),
+                    estop: station.estop,
+                    status: link.and_then(|link| link.status.clone()),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for FmsServer {
+    fn drop(&mut self) {
+        self.stop.send(()).unwrap_or(());
+        // Waiting for the thread frees the ports before anything else binds them.
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
+
+fn station_index(station: &Alliance) -> Option<usize> {
+    match station {
+        Alliance::Red(1..=3) | Alliance::Blue(1..=3) => Some(station.to_position_u8() as usize),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Default)]
+struct Station {
+    team: Option<u16>,
+    estop: bool,
+}
+
+/// A driver station that has connected to the field.
+#[derive(Default)]
+struct Link {
+    tcp: Option<FramedStream>,
+    addr: Option<SocketAddr>,
+    status: Option<DsStatusPacket>,
+    last_status: Option<Instant>,
+}
+
+struct Field {
+    ds_control_port: Option<u16>,
+    stations: Vec<Station>,
+    links: HashMap<u16, Link>,
+    config: MatchConfig,
+    started: Option<Instant>,
+    phase: MatchPhase,
+    last_control: Option<Instant>,
+    sequence_num: u16,
+}
+
+impl Field {
+    fn new(ds_control_port: Option<u16>) -> Self {
+        Field {
+            ds_control_port,
+            stations: vec![Station::default(); 6],
+            links: HashMap::new(),
+            config: MatchConfig::default(),
+            started: None,
+            phase: MatchPhase::Idle,
+            last_control: None,
+            sequence_num: 0,
+        }
+    }
+
+    fn tick(&mut self, now: Instant, udp: &UdpSocket, pending: &mut Vec<FramedStream>) {
+        self.identify(pending);
+        self.receive_tcp();
+        self.receive_status(now, udp);
+        self.update_match(now);
+
+        let control_due = match self.last_control {
+            Some(last) => now.saturating_duration_since(last) >= CONTROL_PERIOD,
+            None => true,
+        };
+        if control_due {
+            self.last_control = Some(now);
+            self.send_control(now, udp);
+        }
+    }
+
+    /// Waits for new connections to say which team they are.
+    fn identify(&mut self, pending: &mut Vec<FramedStream>) {
+        let mut index = 0;
+        while index < pending.len() {
+            let team = match pending[index].receive() {
+                Ok(frames) => frames
+                    .into_iter()
+                    .filter_map(|frame| DsFmsTcpTag::from_bytes(&frame).ok())
+                    .map(|tag| match tag {
+                        DsFmsTcpTag::TeamNumber(team) => team,
+                    })
+                    .next(),
+                Err(_) => {
+                    pending.remove(index);
+                    continue;
+                }
+            };
+
+            match team {
+                Some(team) => {
+                    let stream = pending.remove(index);
+                    self.links.entry(team).or_default().tcp = Some(stream);
+                    self.greet(team);
+                }
+                None => index += 1,
+            }
+        }
+    }
+
+    /// Tells a newly connected driver station where it is and what event it's at.
+    fn greet(&mut self, team: u16) {
+        let station = self
+            .stations
+            .iter()
+            .position(|station| station.team == Some(team));
+        let (alliance, status) = match station {
+            Some(index) => (Alliance::from_position_u8(index as u8), StationStatus::Good),
+            None => (Alliance::Red(1), StationStatus::Waiting),
+        };
+        self.send_tcp(team, FmsTcpTag::StationInfo { alliance, status });
+
+        if self.started.is_some() {
+            if let Some(ref info) = self.config.match_info {
+                let code = FmsTcpTag::EventCode(info.competition.clone());
+                self.send_tcp(team, code);
+            }
+        }
+    }
+
+    fn receive_tcp(&mut self) {
+        for link in self.links.values_mut() {
+            if let Some(ref mut stream) = link.tcp {
+                // Nothing the driver station sends after its team number is needed.
+                if stream.poll().is_err() {
+                    link.tcp = None;
+                }
+            }
+        }
+    }
+
+    fn receive_status(&mut self, now: Instant, udp: &UdpSocket) {
+        let mut buf = [0u8; 256];
+        while let Ok((n, from)) = udp.recv_from(&mut buf) {
+            if let Ok(packet) = DsStatusPacket::from_bytes(&buf[..n]) {
+                let link = self.links.entry(packet.team).or_default();
+                link.addr = Some(match self.ds_control_port {
+                    Some(port) => SocketAddr::new(from.ip(), port),
+                    None => from,
+                });
+                link.status = Some(packet);
+                link.last_status = Some(now);
+            }
+        }
+
+        for link in self.links.values_mut() {
+            if let Some(last) = link.last_status {
+                if now.saturating_duration_since(last) > DS_TIMEOUT {
+                    link.addr = None;
+                    link.status = None;
+                    link.last_status = None;
+                }
+            }
+        }
+    }
+
+    fn update_match(&mut self, now: Instant) {
+        let started = match self.started {
+            Some(started) => started,
+            None => return,
+        };
+        let phase = self.config.phase_at(now.saturating_duration_since(started));
+        if phase == self.phase {
+            return;
+        }
+
+        // Game data is sent at the start of autonomous, even if a slow tick skipped past it.
+        let before_auto = matches!(self.phase, MatchPhase::Idle | MatchPhase::Countdown);
+        if before_auto && phase != MatchPhase::Countdown {
+            if let Some(data) = self.config.game_data.clone() {
+                self.broadcast(FmsTcpTag::GameData(data));
+            }
+        }
+        self.phase = phase;
+        if phase == MatchPhase::Finished {
+            self.started = None;
+        }
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        match self.started {
+            Some(started) => self
+                .config
+                .phase_end(self.phase)
+                .saturating_sub(now.saturating_duration_since(started)),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    fn send_control(&mut self, now: Instant, udp: &UdpSocket) {
+        let (match_type, match_number, replay_number) = match self.config.match_info {
+            Some(ref info) => (info.match_type, info.match_number, info.replay_number),
+            None => (MatchType::None, 0, 0),
+        };
+        let running = self.phase == MatchPhase::Auto || self.phase == MatchPhase::Teleop;
+        let mode = match self.phase {
+            MatchPhase::Idle | MatchPhase::Countdown | MatchPhase::Auto => RobotMode::Auto,
+            _ => RobotMode::Teleop,
+        };
+        let remaining_seconds = self.remaining(now).as_secs() as u16;
+
+        for (index, station) in self.stations.iter().enumerate() {
+            let addr = match station.team.and_then(|team| self.links.get(&team)) {
+                Some(Link {
+                    addr: Some(addr), ..
+                }) => *addr,
+                _ => continue,
+            };
+            let packet = FmsControlPacket {
+                sequence_num: self.sequence_num,
+                estop: station.estop,
+                enabled: running && !station.estop,
+                mode,
+                alliance: Alliance::from_position_u8(index as u8),
+                match_type,
+                match_number,
+                replay_number,
+                time: Utc::now(),
+                remaining_seconds,
+            };
+            udp.send_to(&packet.to_bytes(), addr).unwrap_or(0);
+        }
+        self.sequence_num = self.sequence_num.wrapping_add(1);
+    }
+
+    fn send_tcp(&mut self, team: u16, tag: FmsTcpTag) {
+        if let Some(link) = self.links.get_mut(&team) {
+            if let Some(ref mut stream) = link.tcp {
+                // Tags are tiny, so a driver station that has let the queue fill up is gone.
+                if stream.send(&tag.to_packet()).is_err() {
+                    link.tcp = None;
+                }
+            }
+        }
+    }
+
+    /// Sends a tag to every driver station in a station.
+    fn broadcast(&mut self, tag: FmsTcpTag) {
+        let teams: Vec<u16> = self.stations.iter().filter_map(|s| s.team).collect();
+        for team in teams {
+            self.send_tcp(team, tag.clone());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::ds::DriverStationState;
+use crate::messages::rio::{Status, Trace};
+
+/// Conditions that must hold for the robot to be enabled.
+///
+/// These are checked when enabling through [DriverStation::set_enabled](crate::DriverStation::set_enabled)
+/// and again before every control packet, so a robot that stops meeting them while enabled is
+/// disabled automatically. An emergency stop from the driver station or the robot always refuses
+/// to enable and can't be configured away.
+#[derive(Clone, Debug, Default)]
+pub struct Interlocks {
+    /// Refuse to enable if no status packet has been received from the robot within this time.
+    pub comms_timeout: Option<Duration>,
+    /// Refuse to enable unless the robot reports that user code is running.
+    pub require_robot_code: bool,
+    /// Refuse to enable after too many brownouts in a short period.
+    pub brownout_storm: Option<BrownoutStorm>,
+    /// Joystick slots (0-5) that must have a joystick attached.
+    pub required_joysticks: Vec<usize>,
+}
+
+/// Trips when at least `count` brownouts start within `window` of each other.
+///
+/// Enough brownouts are remembered to reach any `count`.
+#[derive(Clone, Copy, Debug)]
+pub struct BrownoutStorm {
+    pub count: usize,
+    pub window: Duration,
+}
+
+/// The reason the robot could not be enabled, or was disabled by an interlock.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnableRefusal {
+    EStopped,
+    FmsAttached,
+    NoComms,
+    NoRobotCode,
+    BrownoutStorm { count: usize, window: Duration },
+    MissingJoystick(usize),
+}
+
+impl fmt::Display for EnableRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnableRefusal::EStopped => write!(f, "robot is emergency stopped"),
+            EnableRefusal::FmsAttached => write!(f, "the robot is being controlled by an FMS"),
+            EnableRefusal::NoComms => write!(f, "no communication with the robot"),
+            EnableRefusal::NoRobotCode => write!(f, "no robot code is running"),
+            EnableRefusal::BrownoutStorm { count, window } => write!(
+                f,
+                "{} brownouts in the last {:.1}s",
+                count,
+                window.as_secs_f32()
+            ),
+            EnableRefusal::MissingJoystick(slot) => {
+                write!(f, "no joystick in required slot {}", slot)
+            }
+        }
+    }
+}
+
+impl Error for EnableRefusal {}
+
+impl Interlocks {
+    pub(crate) fn check(
+        &self,
+        state: &DriverStationState,
+        now: Instant,
+    ) -> Result<(), EnableRefusal> {
+        if state.estop || state.robot_status.contains(Status::ESTOP) {
+            return Err(EnableRefusal::EStopped);
+        }
+
+        if let Some(timeout) = self.comms_timeout {
+            match state.last_robot_packet {
+                Some(last) if now.saturating_duration_since(last) <= timeout => {}
+                _ => return Err(EnableRefusal::NoComms),
+            }
+        }
+
+        if self.require_robot_code && !state.robot_trace.contains(Trace::ROBOT_CODE) {
+            return Err(EnableRefusal::NoRobotCode);
+        }
+
+        if let Some(storm) = self.brownout_storm {
+            let count = state
+                .brownouts
+                .iter()
+                .filter(|&&start| now.saturating_duration_since(start) <= storm.window)
+                .count();
+            if storm.count > 0 && count >= storm.count {
+                return Err(EnableRefusal::BrownoutStorm {
+                    count,
+                    window: storm.window,
+                });
+            }
+        }
+
+        for &slot in &self.required_joysticks {
+            match state.joysticks.get(slot) {
+                Some(Some(_)) => {}
+                _ => return Err(EnableRefusal::MissingJoystick(slot)),
+            }
+        }
+
+        Ok(())
+    }
+}
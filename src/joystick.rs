@@ -1,5 +1,10 @@
-use crate::packet::PacketWriter;
+use std::error::Error;
+use std::fmt;
 
+use crate::packet::{PacketReader, PacketWriter, ReadError};
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum AxisType {
     X = 0,
     Y = 1,
@@ -8,12 +13,73 @@ pub enum AxisType {
     Throttle = 4,
 }
 
-#[derive(Clone)]
+impl AxisType {
+    pub fn from(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(AxisType::X),
+            1 => Some(AxisType::Y),
+            2 => Some(AxisType::Z),
+            3 => Some(AxisType::Twist),
+            4 => Some(AxisType::Throttle),
+            _ => None,
+        }
+    }
+}
+
+#[repr(i8)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum JoystickType {
     Unknown = -1,
+    XInputUnknown = 0,
+    XInputGamepad = 1,
+    XInputWheel = 2,
+    XInputArcadeStick = 3,
+    XInputFlightStick = 4,
+    XInputDancePad = 5,
+    XInputGuitar = 6,
+    XInputGuitar2 = 7,
+    XInputDrumKit = 8,
+    XInputGuitar3 = 11,
+    XInputArcadePad = 19,
+    HIDJoystick = 20,
+    HIDGamepad = 21,
+    HIDDriving = 22,
+    HIDFlight = 23,
+    HIDFirstPerson = 24,
+}
+
+impl JoystickType {
+    /// Whether this is one of the XInput types, which are all xbox style controllers.
+    pub fn is_xinput(self) -> bool {
+        (0..JoystickType::HIDJoystick as i8).contains(&(self as i8))
+    }
+
+    pub fn from(val: i8) -> Option<Self> {
+        use self::JoystickType::*;
+        match val {
+            -1 => Some(Unknown),
+            0 => Some(XInputUnknown),
+            1 => Some(XInputGamepad),
+            2 => Some(XInputWheel),
+            3 => Some(XInputArcadeStick),
+            4 => Some(XInputFlightStick),
+            5 => Some(XInputDancePad),
+            6 => Some(XInputGuitar),
+            7 => Some(XInputGuitar2),
+            8 => Some(XInputDrumKit),
+            11 => Some(XInputGuitar3),
+            19 => Some(XInputArcadePad),
+            20 => Some(HIDJoystick),
+            21 => Some(HIDGamepad),
+            22 => Some(HIDDriving),
+            23 => Some(HIDFlight),
+            24 => Some(HIDFirstPerson),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Debug, PartialEq)]
 pub struct Joystick {
     stick_type: JoystickType,
     buttons: Vec<bool>,
@@ -21,6 +87,45 @@ pub struct Joystick {
     povs: Vec<i16>,
 }
 
+impl Clone for Joystick {
+    fn clone(&self) -> Self {
+        Joystick {
+            stick_type: self.stick_type,
+            buttons: self.buttons.clone(),
+            axes: self.axes.clone(),
+            povs: self.povs.clone(),
+        }
+    }
+
+    // Reuses the existing buffers, so copying joysticks into each control packet doesn't allocate.
+    fn clone_from(&mut self, source: &Self) {
+        self.stick_type = source.stick_type;
+        self.buttons.clone_from(&source.buttons);
+        self.axes.clone_from(&source.axes);
+        self.povs.clone_from(&source.povs);
+    }
+}
+
+/// An index past the end of a joystick's buttons, axes or POVs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutOfRange {
+    pub index: u8,
+    /// How many of them the joystick has.
+    pub count: u8,
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "index {} is out of range for {} inputs",
+            self.index, self.count
+        )
+    }
+}
+
+impl Error for OutOfRange {}
+
 impl Joystick {
     pub fn new(num_buttons: u8, num_axes: u8, num_povs: u8) -> Self {
         Joystick {
@@ -31,39 +136,64 @@ impl Joystick {
         }
     }
 
-    fn num_buttons(&self) -> u8 {
+    pub fn stick_type(&self) -> JoystickType {
+        self.stick_type
+    }
+
+    pub fn num_buttons(&self) -> u8 {
         self.buttons.len() as u8
     }
 
-    fn num_axes(&self) -> u8 {
+    pub fn num_axes(&self) -> u8 {
         self.axes.len() as u8
     }
 
-    fn num_povs(&self) -> u8 {
+    pub fn num_povs(&self) -> u8 {
         self.povs.len() as u8
     }
 
-    fn set_button(&mut self, index: u8, pressed: bool) -> Result<(), ()> {
-        if self.buttons.len() as u8 >= index {
-            Err(())
+    pub fn button(&self, index: u8) -> Option<bool> {
+        self.buttons.get(index as usize).cloned()
+    }
+
+    pub fn axis(&self, index: u8) -> Option<i8> {
+        self.axes.get(index as usize).cloned()
+    }
+
+    pub fn pov(&self, index: u8) -> Option<i16> {
+        self.povs.get(index as usize).cloned()
+    }
+
+    pub fn set_button(&mut self, index: u8, pressed: bool) -> Result<(), OutOfRange> {
+        if index as usize >= self.buttons.len() {
+            Err(OutOfRange {
+                index,
+                count: self.buttons.len() as u8,
+            })
         } else {
             self.buttons[index as usize] = pressed;
             Ok(())
         }
     }
 
-    fn set_axis(&mut self, index: u8, value: i8) -> Result<(), ()> {
-        if self.axes.len() as u8 >= index {
-            Err(())
+    pub fn set_axis(&mut self, index: u8, value: i8) -> Result<(), OutOfRange> {
+        if index as usize >= self.axes.len() {
+            Err(OutOfRange {
+                index,
+                count: self.axes.len() as u8,
+            })
         } else {
             self.axes[index as usize] = value;
             Ok(())
         }
     }
 
-    fn set_pov(&mut self, index: u8, value: i16) -> Result<(), ()> {
-        if self.povs.len() as u8 >= index {
-            Err(())
+    pub fn set_pov(&mut self, index: u8, value: i16) -> Result<(), OutOfRange> {
+        if index as usize >= self.povs.len() {
+            Err(OutOfRange {
+                index,
+                count: self.povs.len() as u8,
+            })
         } else {
             self.povs[index as usize] = value;
             Ok(())
@@ -72,7 +202,12 @@ impl Joystick {
 
     pub fn udp_tag(&self) -> Vec<u8> {
         let mut tag = PacketWriter::new();
+        self.write_udp_tag(&mut tag);
+        tag.into_vec()
+    }
 
+    /// Writes the contents of a joystick tag onto the end of `tag`.
+    pub(crate) fn write_udp_tag(&self, tag: &mut PacketWriter) {
         tag.write_u8(self.axes.len() as u8);
         for axis in &self.axes {
             tag.write_u8(*axis as u8); // this might work
@@ -98,10 +233,44 @@ impl Joystick {
 
         tag.write_u8(self.povs.len() as u8);
         for pov in &self.povs {
-            tag.write_u8(((pov >> 8) & 0xff as i16) as u8);
+            tag.write_u8(((pov >> 8) & 0xff) as u8);
             tag.write_u8((pov & 0xff) as u8);
         }
+    }
 
-        tag.into_vec()
+    /// Parses the contents of a joystick tag, as written by [udp_tag](Joystick::udp_tag).
+    pub fn from_udp_tag(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut tag = PacketReader::new(bytes);
+        let joystick = Joystick::read_udp_tag(&mut tag)?;
+        tag.finish()?;
+        Ok(joystick)
+    }
+
+    pub(crate) fn read_udp_tag(tag: &mut PacketReader) -> Result<Self, ReadError> {
+        let num_axes = tag.next_u8()?;
+        let axes = tag
+            .next_bytes(num_axes as usize)?
+            .iter()
+            .map(|axis| *axis as i8)
+            .collect();
+
+        let num_buttons = tag.next_u8()?;
+        let bytes = tag.next_bytes((num_buttons as usize + 7) / 8)?;
+        let buttons = (0..num_buttons as usize)
+            .map(|i| bytes[i / 8] & (1 << (7 - i % 8)) != 0)
+            .collect();
+
+        let num_povs = tag.next_u8()?;
+        let mut povs = Vec::with_capacity(num_povs as usize);
+        for _ in 0..num_povs {
+            povs.push(tag.next_i16()?);
+        }
+
+        Ok(Joystick {
+            stick_type: JoystickType::Unknown,
+            buttons,
+            axes,
+            povs,
+        })
     }
 }
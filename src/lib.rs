@@ -1,89 +1,416 @@
 extern crate byteorder;
 extern crate chrono;
+extern crate iana_time_zone;
 #[macro_use]
 extern crate bitflags;
 
-use byteorder::{ByteOrder, NetworkEndian};
-use chrono::prelude::*;
-
 use std::default::Default;
 use std::io;
-use std::io::Read;
-use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
-use std::sync::{mpsc, Arc, Mutex, MutexGuard};
-use std::thread;
-use std::thread::JoinHandle;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 
+pub mod battery;
+pub mod clock;
+pub mod codec;
 mod connection;
+pub mod console;
 mod ds;
-mod joystick;
+pub mod fms;
+pub mod interlock;
+pub mod joystick;
+pub mod link;
+pub mod log;
+pub mod match_timer;
 pub mod messages; // change to just re-export
+pub mod mock;
+mod netconsole;
 mod packet;
+pub mod pdp;
+pub mod robot;
+mod server;
 pub mod states;
+pub mod version;
 
+use battery::{BatteryConfig, BatteryMonitor, BatteryReport, BatteryWarning};
+use clock::WallClock;
+pub use connection::ConnectionConfig;
 use connection::DSConnection;
+use console::{ConsoleFilter, ConsoleSubscription};
 use ds::DriverStationState;
+use fms::{FmsConfig, FmsConnection, FmsControl};
+use interlock::{EnableRefusal, Interlocks};
 use joystick::Joystick;
-use messages::*;
-use packet::PacketWriter;
+use link::LinkStats;
+use log::{DsLogRecord, Logger};
+use messages::fms::StationStatus;
+use messages::rio::Trace;
+use netconsole::NetConsole;
+use pdp::{PdpHistory, PdpSample};
 use states::{Alliance, RobotMode};
+use version::ProtocolVersion;
 
 use messages::ds::tcp::*;
 
 pub struct DriverStation {
     state: Arc<Mutex<DriverStationState>>,
     connection: Option<DSConnection>,
+    fms: Option<FmsConnection>,
+    netconsole: Option<NetConsole>,
 }
 
 impl DriverStation {
     pub fn new() -> Self {
         DriverStation {
-            state: Arc::new(Mutex::new(Default::default())),
+            state: Arc::new(Mutex::new(DriverStationState::new())),
             connection: None,
+            fms: None,
+            netconsole: None,
         }
     }
 
     pub fn connect(&mut self, addr: IpAddr) -> io::Result<()> {
+        self.connect_with(ConnectionConfig::new(addr))
+    }
+
+    /// Connects to a robot that isn't using the standard ports.
+    pub fn connect_with(&mut self, config: ConnectionConfig) -> io::Result<()> {
         if let Some(conn) = self.connection.take() {
             drop(conn);
         }
-        self.connection = Some(DSConnection::new(addr, self.state.clone())?);
+        self.connection = Some(DSConnection::new(config, self.state.clone())?);
         Ok(())
     }
 
     pub fn is_connected(&self) -> bool {
         match self.connection {
             None => false,
-            Some(ref conn) => match conn.status() {
-                Ok(_) => true,
-                Err(_) => false,
-            },
+            Some(ref conn) => conn.status().is_ok(),
+        }
+    }
+
+    /// Returns the next error the connection has run into since the last call, if any.
+    ///
+    /// Errors aren't fatal: control packets keep being sent, and the TCP connection is tried
+    /// again until the robot accepts it, such as once it has finished booting.
+    pub fn connection_error(&self) -> Option<io::Error> {
+        self.connection.as_ref()?.status().err()
+    }
+
+    /// Returns whether the TCP connection to the robot, which carries its console messages and
+    /// the match info, is up.
+    pub fn has_robot_tcp(&self) -> bool {
+        self.state.lock().unwrap().robot_tcp_connected
+    }
+
+    /// Returns whether a status packet has been received from the robot recently.
+    pub fn has_robot_comms(&self) -> bool {
+        self.state.lock().unwrap().has_robot_comms(Instant::now())
+    }
+
+    /// Returns whether the robot last reported that its code is running.
+    pub fn has_robot_code(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.has_robot_comms(Instant::now()) && state.robot_trace.contains(Trace::ROBOT_CODE)
+    }
+
+    /// Returns the round trip time and packet loss to the robot.
+    pub fn link_stats(&self) -> LinkStats {
+        self.state.lock().unwrap().link.stats(Instant::now())
+    }
+
+    /// Returns the battery voltage last reported by the robot.
+    pub fn battery_voltage(&self) -> f32 {
+        self.state.lock().unwrap().battery_voltage
+    }
+
+    /// Subscribes to the robot's console messages that match `filter`.
+    pub fn console(&self, filter: ConsoleFilter) -> ConsoleSubscription {
+        self.state.lock().unwrap().console.subscribe(filter)
+    }
+
+    /// Listens for lines printed over netconsole on `port`, usually
+    /// [NETCONSOLE_PORT](console::NETCONSOLE_PORT), and delivers them to console subscriptions
+    /// along with the messages received over TCP. Any existing listener is stopped first.
+    ///
+    /// Returns the port listened on, which is chosen by the OS if `port` is 0.
+    pub fn listen_netconsole(&mut self, port: u16) -> io::Result<u16> {
+        self.stop_netconsole();
+        let netconsole = NetConsole::new(port, self.state.clone())?;
+        let port = netconsole.port();
+        self.netconsole = Some(netconsole);
+        Ok(port)
+    }
+
+    pub fn stop_netconsole(&mut self) {
+        self.netconsole = None;
+    }
+
+    /// Returns the current telemetry, in the form it's recorded to a `.dslog` file, for exporting
+    /// or displaying live.
+    pub fn telemetry(&self) -> DsLogRecord {
+        self.state.lock().unwrap().telemetry(Instant::now())
+    }
+
+    /// Returns the battery's voltage through each phase, its brownouts and its estimated
+    /// resistance, since connecting or the last [reset_battery](Self::reset_battery).
+    pub fn battery(&self) -> BatteryReport {
+        self.state.lock().unwrap().battery.report()
+    }
+
+    /// Sets the voltages that warn, and resets the battery report.
+    pub fn set_battery_config(&self, config: BatteryConfig) {
+        self.state.lock().unwrap().battery = BatteryMonitor::new(config);
+    }
+
+    /// Resets the battery report, such as when a match starts or the battery is changed.
+    pub fn reset_battery(&self) {
+        self.state.lock().unwrap().battery.reset();
+    }
+
+    /// Returns a receiver for warnings about low voltage and brownouts as they happen.
+    pub fn battery_warnings(&self) -> mpsc::Receiver<BatteryWarning> {
+        let (sender, receiver) = mpsc::channel();
+        self.state.lock().unwrap().battery_warnings.push(sender);
+        receiver
+    }
+
+    /// Returns what the power distribution panel last reported, if the robot has reported it.
+    pub fn pdp(&self) -> Option<PdpSample> {
+        self.state.lock().unwrap().pdp.latest().cloned()
+    }
+
+    /// Returns the power distribution panel's recent readings.
+    pub fn pdp_history(&self) -> PdpHistory {
+        self.state.lock().unwrap().pdp.clone()
+    }
+
+    /// Returns a receiver for the power distribution panel's readings as they arrive.
+    pub fn pdp_samples(&self) -> mpsc::Receiver<PdpSample> {
+        let (sender, receiver) = mpsc::channel();
+        self.state.lock().unwrap().pdp_samples.push(sender);
+        receiver
+    }
+
+    /// Enables or disables the robot.
+    ///
+    /// Disabling always succeeds. Enabling is refused while an FMS is attached, and is checked
+    /// against the configured [Interlocks] otherwise, returning the reason it was refused if any
+    /// of them are not satisfied.
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), EnableRefusal> {
+        let mut state = self.state.lock().unwrap();
+        if enabled {
+            if state.fms_attached {
+                return Err(EnableRefusal::FmsAttached);
+            }
+            state.check_interlocks(Instant::now())?;
+            state.interlock_trip = None;
         }
+        state.enabled = enabled;
+        Ok(())
     }
 
-    pub fn set_enabled(&self, enabled: bool) {
-        self.state.lock().unwrap().enabled = enabled;
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().unwrap().enabled
     }
 
+    /// Emergency stops the robot, which stays disabled until it is rebooted.
+    pub fn estop(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.estop = true;
+        state.enabled = false;
+    }
+
+    pub fn is_estopped(&self) -> bool {
+        self.state.lock().unwrap().estop
+    }
+
+    pub fn set_interlocks(&self, interlocks: Interlocks) {
+        self.state.lock().unwrap().interlocks = interlocks;
+    }
+
+    /// Checks whether the robot could currently be enabled without changing anything.
+    pub fn can_enable(&self) -> Result<(), EnableRefusal> {
+        self.state.lock().unwrap().check_interlocks(Instant::now())
+    }
+
+    /// Returns the reason the robot was last disabled by an interlock, if it has not been
+    /// re-enabled since.
+    pub fn interlock_trip(&self) -> Option<EnableRefusal> {
+        self.state.lock().unwrap().interlock_trip.clone()
+    }
+
+    /// Sets the robot mode. This has no effect while an FMS is attached.
     pub fn set_mode(&self, mode: RobotMode) {
-        self.state.lock().unwrap().mode = mode;
+        let mut state = self.state.lock().unwrap();
+        if !state.fms_attached {
+            state.mode = mode;
+        }
+    }
+
+    pub fn mode(&self) -> RobotMode {
+        self.state.lock().unwrap().mode
     }
 
+    /// Sets the alliance station. This has no effect while an FMS is attached.
     pub fn set_alliance(&self, alliance: Alliance) {
-        self.state.lock().unwrap().alliance = alliance;
+        let mut state = self.state.lock().unwrap();
+        if !state.fms_attached {
+            state.alliance = alliance;
+        }
     }
 
-    pub fn set_game_data(&self, data: String) {
-        self.state.lock().unwrap().game_data = data.clone();
-        if let Some(ref conn) = self.connection {
-            conn.send_tcp(TcpTag::GameData(GameData::new(data)));
+    pub fn alliance(&self) -> Alliance {
+        self.state.lock().unwrap().alliance.clone()
+    }
+
+    /// Sets the joystick in the given slot (0-5), or clears it with [None].
+    pub fn set_joystick(&self, slot: usize, joystick: Option<Joystick>) {
+        if let Some(stick) = self.state.lock().unwrap().joysticks.get_mut(slot) {
+            *stick = joystick;
         }
     }
 
+    /// Returns the joystick in each slot.
+    pub fn joysticks(&self) -> Vec<Option<Joystick>> {
+        self.state.lock().unwrap().joysticks.clone()
+    }
+
+    pub fn set_game_data(&self, data: String) {
+        let mut state = self.state.lock().unwrap();
+        state.game_data = data.clone();
+        state.send_tcp(TcpTag::GameData(GameData::new(data)));
+    }
+
+    pub fn game_data(&self) -> String {
+        self.state.lock().unwrap().game_data.clone()
+    }
+
+    /// Sets the match info sent to the robot. This has no effect while an FMS is attached.
     pub fn set_match_info(&self, info: MatchInfo) {
-        self.state.lock().unwrap().match_info = info.clone();
-        if let Some(ref conn) = self.connection {
-            conn.send_tcp(TcpTag::MatchInfo(info));
+        if !self.is_fms_attached() {
+            self.send_match_info(info);
+        }
+    }
+
+    fn send_match_info(&self, info: MatchInfo) {
+        let mut state = self.state.lock().unwrap();
+        state.match_info = info.clone();
+        state.send_tcp(TcpTag::MatchInfo(info));
+    }
+
+    pub fn match_info(&self) -> MatchInfo {
+        self.state.lock().unwrap().match_info.clone()
+    }
+
+    /// Sets the IANA timezone name, such as `America/New_York`, sent to the robot. This defaults
+    /// to the host's timezone.
+    pub fn set_timezone(&self, timezone: String) {
+        self.state.lock().unwrap().timezone = timezone;
+    }
+
+    pub fn timezone(&self) -> String {
+        self.state.lock().unwrap().timezone.clone()
+    }
+
+    /// Sets where the date sent to the robot comes from. This defaults to the system time.
+    pub fn set_wall_clock<C: WallClock + Send + Sync + 'static>(&self, clock: C) {
+        self.state.lock().unwrap().wall_clock = Arc::new(clock);
+    }
+
+    /// Forces the protocol version spoken to the robot, or with [None] follows the comm version
+    /// the robot reports, which is the default.
+    pub fn set_protocol_version(&self, version: Option<ProtocolVersion>) {
+        self.state.lock().unwrap().force_protocol_version(version);
+    }
+
+    /// Returns the protocol version currently being spoken to the robot.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.state.lock().unwrap().protocol_version()
+    }
+
+    /// Starts recording to a new `.dslog` and `.dsevents` pair in `dir`, replacing any
+    /// recording in progress. Recording stops by itself if writing fails.
+    pub fn start_logging<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let logger = Logger::create(dir, state.wall_clock.now_utc())?;
+        state.logger = Some(logger);
+        Ok(())
+    }
+
+    /// Stops recording, flushing what has been written.
+    pub fn stop_logging(&self) -> io::Result<()> {
+        match self.state.lock().unwrap().logger.take() {
+            Some(mut logger) => logger.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.state.lock().unwrap().logger.is_some()
+    }
+
+    /// Connects to an FMS, which takes control of the robot once it starts sending control
+    /// packets. Any existing FMS connection is closed first.
+    pub fn connect_fms(&mut self, config: FmsConfig) -> io::Result<()> {
+        self.disconnect_fms();
+        self.fms = Some(FmsConnection::new(config, self.state.clone())?);
+        Ok(())
+    }
+
+    /// Disconnects from the FMS and returns control of the robot to this driver station.
+    pub fn disconnect_fms(&mut self) {
+        if let Some(fms) = self.fms.take() {
+            drop(fms);
+            self.set_fms_attached(false);
         }
     }
+
+    /// Returns whether the FMS considers this driver station to be in the right station, if an
+    /// FMS has said so.
+    pub fn fms_station_status(&self) -> Option<StationStatus> {
+        self.state.lock().unwrap().fms_station
+    }
+
+    /// Hands control of the robot to an FMS, or takes it back.
+    ///
+    /// While attached, the FMS bit is set in control packets and the robot's enable state, mode,
+    /// alliance and match info can only be changed through the `apply_fms_*` methods. The robot
+    /// is disabled whenever control changes hands.
+    pub fn set_fms_attached(&self, attached: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.fms_attached = attached;
+        state.enabled = false;
+    }
+
+    pub fn is_fms_attached(&self) -> bool {
+        self.state.lock().unwrap().fms_attached
+    }
+
+    /// Applies the control state commanded by an attached FMS.
+    ///
+    /// This is ignored unless an FMS is attached. The robot is only enabled if the interlocks
+    /// allow it, otherwise the reason is returned and the robot is left disabled.
+    pub fn apply_fms_control(&self, control: &FmsControl) -> Result<(), EnableRefusal> {
+        let mut state = self.state.lock().unwrap();
+        if state.fms_attached {
+            state.apply_fms_control(control)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sets the match info from an attached FMS. This is ignored unless an FMS is attached.
+    pub fn apply_fms_match_info(&self, info: MatchInfo) {
+        if self.is_fms_attached() {
+            self.send_match_info(info);
+        }
+    }
+}
+
+impl Default for DriverStation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,137 @@
+//! The quality of the UDP link to the robot, measured by matching the sequence numbers the robot
+//! echoes in its status packets to the control packets they reply to.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many of the most recent control packets are considered, which is 2 seconds' worth.
+const WINDOW: usize = 100;
+
+/// How long to wait for a reply before counting a control packet as lost.
+pub const LOSS_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkStats {
+    /// The round trip time of the last control packet the robot replied to.
+    pub trip_time: Option<Duration>,
+    /// The average round trip time over the last 2 seconds.
+    pub average_trip_time: Option<Duration>,
+    /// The fraction of control packets from the last 2 seconds that the robot hasn't replied to,
+    /// from 0 to 1.
+    pub packet_loss: f32,
+    /// The number of replies that arrived after a reply to a later packet.
+    pub out_of_order: u64,
+    /// The number of control packets sent and replies received since connecting.
+    pub sent: u64,
+    pub received: u64,
+    /// The number of control packets since connecting that the robot didn't reply to in time.
+    pub lost: u64,
+}
+
+struct Sent {
+    sequence_num: u16,
+    at: Instant,
+    /// When the reply arrived, if it has.
+    replied: Option<Instant>,
+}
+
+/// Tracks the control packets sent to the robot and the replies to them.
+#[derive(Default)]
+pub struct LinkMonitor {
+    sent: VecDeque<Sent>,
+    last_trip: Option<Duration>,
+    /// The highest sequence number replied to, allowing for wrapping.
+    highest_reply: Option<u16>,
+    out_of_order: u64,
+    total_sent: u64,
+    total_received: u64,
+    /// Lost packets that have left the window.
+    lost_before_window: u64,
+}
+
+impl LinkMonitor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records a control packet being sent.
+    pub fn on_sent(&mut self, sequence_num: u16, at: Instant) {
+        if self.sent.len() == WINDOW {
+            let oldest = self.sent.pop_front();
+            if oldest.is_some_and(|oldest| oldest.replied.is_none()) {
+                self.lost_before_window += 1;
+            }
+        }
+        self.sent.push_back(Sent {
+            sequence_num,
+            at,
+            replied: None,
+        });
+        self.total_sent += 1;
+    }
+
+    /// Records a status packet arriving in reply to the control packet with `sequence_num`.
+    pub fn on_received(&mut self, sequence_num: u16, at: Instant) {
+        self.total_received += 1;
+        match self.highest_reply {
+            Some(highest) if sequence_num.wrapping_sub(highest) as i16 <= 0 => {
+                self.out_of_order += 1
+            }
+            _ => self.highest_reply = Some(sequence_num),
+        }
+
+        let sent = self
+            .sent
+            .iter_mut()
+            .rev()
+            .find(|sent| sent.sequence_num == sequence_num && sent.replied.is_none());
+        if let Some(sent) = sent {
+            sent.replied = Some(at);
+            self.last_trip = Some(at.saturating_duration_since(sent.at));
+        }
+    }
+
+    /// Forgets everything, for when the connection is restarted.
+    pub fn reset(&mut self) {
+        *self = LinkMonitor::default();
+    }
+
+    pub fn stats(&self, now: Instant) -> LinkStats {
+        let (trip_sum, trips) = self
+            .sent
+            .iter()
+            .filter_map(|sent| Some(sent.replied?.saturating_duration_since(sent.at)))
+            .fold((Duration::ZERO, 0u32), |(sum, count), trip| {
+                (sum + trip, count + 1)
+            });
+        let average_trip_time = if trips == 0 {
+            None
+        } else {
+            Some(trip_sum / trips)
+        };
+
+        // Packets sent too recently for a reply to be expected yet aren't counted either way.
+        let (due, lost) = self
+            .sent
+            .iter()
+            .filter(|sent| now.saturating_duration_since(sent.at) >= LOSS_TIMEOUT)
+            .fold((0, 0), |(due, lost), sent| {
+                (due + 1, lost + sent.replied.is_none() as u32)
+            });
+        let packet_loss = if due == 0 {
+            0.0
+        } else {
+            lost as f32 / due as f32
+        };
+
+        LinkStats {
+            trip_time: self.last_trip,
+            average_trip_time,
+            packet_loss,
+            out_of_order: self.out_of_order,
+            sent: self.total_sent,
+            received: self.total_received,
+            lost: self.lost_before_window + u64::from(lost),
+        }
+    }
+}
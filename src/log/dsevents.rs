@@ -0,0 +1,203 @@
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::messages::rio::RioTcpPacket;
+use crate::packet::{PacketReader, PacketWriter, ReadError};
+
+/// Formats a message from the robot the way the driver station records it, or returns [None]
+/// for messages that aren't recorded.
+///
+/// Each field is introduced by a tag in angle brackets, and the timestamp is the robot's time
+/// in seconds.
+pub fn event_text(packet: &RioTcpPacket) -> Option<String> {
+    match packet {
+        RioTcpPacket::StandardOutput {
+            timestamp, message, ..
+        } => Some(format!(
+            "<TagVersion>1 <time> {:.3} <message> {} ",
+            timestamp, message
+        )),
+        RioTcpPacket::ErrorMessage {
+            timestamp,
+            error_code,
+            is_error,
+            details,
+            location,
+            call_stack,
+            ..
+        } => Some(format!(
+            "<TagVersion>1 <time> {:.3} <count> 1 <flags> {} <Code> {} <details> {} \
+             <location> {} <stack> {} ",
+            timestamp, *is_error as u8, error_code, details, location, call_stack
+        )),
+        RioTcpPacket::RadioEvent(message) => Some(format!("<TagVersion>1 <message> {} ", message)),
+        _ => None,
+    }
+}
+
+/// The tags [event_text] and the driver station introduce fields with.
+const FIELD_TAGS: [&str; 9] = [
+    "TagVersion",
+    "time",
+    "count",
+    "flags",
+    "Code",
+    "details",
+    "location",
+    "stack",
+    "message",
+];
+
+/// Splits an event's text into its fields, as tag names and values. Only known tags are
+/// recognized, so values can contain angle brackets of their own.
+fn fields(text: &str) -> Vec<(&str, &str)> {
+    // Finds the earliest known tag at or after `from`, as its name and the range it covers.
+    let next_tag = |from: usize| {
+        FIELD_TAGS
+            .iter()
+            .filter_map(|name| {
+                let tag = format!("<{}>", name);
+                text[from..]
+                    .find(&tag)
+                    .map(|pos| (*name, from + pos, from + pos + tag.len()))
+            })
+            .min_by_key(|(_, start, _)| *start)
+    };
+
+    let mut fields = Vec::new();
+    let mut current = next_tag(0);
+    while let Some((name, _, value_start)) = current {
+        current = next_tag(value_start);
+        let value_end = current.map_or(text.len(), |(_, start, _)| start);
+        let value = &text[value_start..value_end];
+        // Values are separated from their tags by a single space on each side.
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        let value = value.strip_suffix(' ').unwrap_or(value);
+        fields.push((name, value));
+    }
+    fields
+}
+
+/// Parses the text of an event back into the message from the robot it records, or returns
+/// [None] if it isn't one.
+///
+/// Sequence numbers aren't recorded, so they're 0.
+pub fn parse_event_text(text: &str) -> Option<RioTcpPacket> {
+    let fields = fields(text);
+    let field = |name| {
+        fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| *value)
+    };
+    let timestamp = field("time").and_then(|time| time.trim().parse().ok());
+
+    if let Some(code) = field("Code") {
+        Some(RioTcpPacket::ErrorMessage {
+            timestamp: timestamp.unwrap_or(0.0),
+            sequence_number: 0,
+            print_msg: true,
+            error_code: code.trim().parse().ok()?,
+            is_error: field("flags") != Some("0"),
+            details: field("details").unwrap_or("").to_owned(),
+            location: field("location").unwrap_or("").to_owned(),
+            call_stack: field("stack").unwrap_or("").to_owned(),
+        })
+    } else {
+        let message = field("message")?.to_owned();
+        match timestamp {
+            Some(timestamp) => Some(RioTcpPacket::StandardOutput {
+                timestamp,
+                sequence_number: 0,
+                message,
+            }),
+            None => Some(RioTcpPacket::RadioEvent(message)),
+        }
+    }
+}
+
+/// One entry of a `.dsevents` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsEvent {
+    /// When the driver station received it.
+    pub time: DateTime<Utc>,
+    pub text: String,
+}
+
+impl DsEvent {
+    /// Returns the message from the robot this records, if it is one.
+    pub fn message(&self) -> Option<RioTcpPacket> {
+        parse_event_text(&self.text)
+    }
+}
+
+/// The contents of a `.dsevents` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsEvents {
+    /// The time recording started.
+    pub start: DateTime<Utc>,
+    pub events: Vec<DsEvent>,
+}
+
+impl DsEvents {
+    /// Decodes a whole file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        let (_, start) = super::read_header(&mut packet)?;
+        let mut events = Vec::new();
+        while !packet.is_empty() {
+            let time = super::read_timestamp(&mut packet)?;
+            let size = packet.next_i32()?;
+            if size < 0 {
+                return Err(packet.invalid(4, "event size"));
+            }
+            let text = packet.next_string(size as usize)?;
+            events.push(DsEvent { time, text });
+        }
+        Ok(DsEvents { start, events })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        super::read_file(path, DsEvents::from_bytes)
+    }
+}
+
+/// Writes a `.dsevents` file, which is a header followed by timestamped text entries.
+pub struct DsEventsWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> DsEventsWriter<W> {
+    pub fn new(mut writer: W, start: DateTime<Utc>) -> io::Result<Self> {
+        super::write_header(&mut writer, start)?;
+        Ok(DsEventsWriter { writer })
+    }
+
+    /// Writes an entry of any text, which is recorded as the driver station received it.
+    pub fn write_event(&mut self, time: DateTime<Utc>, text: &str) -> io::Result<()> {
+        let mut packet = PacketWriter::new();
+        super::write_timestamp(&mut packet, time);
+        packet.write_i32(text.len() as i32);
+        packet.write_slice(text.as_bytes());
+        self.writer.write_all(&packet.into_vec())
+    }
+
+    /// Writes a message from the robot, if it's one that's recorded.
+    pub fn write_message(&mut self, time: DateTime<Utc>, packet: &RioTcpPacket) -> io::Result<()> {
+        match event_text(packet) {
+            Some(text) => self.write_event(time, &text),
+            None => Ok(()),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
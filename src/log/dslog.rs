@@ -0,0 +1,239 @@
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::messages::ds::udp::Control;
+use crate::messages::rio::{Status, Trace};
+use crate::packet::{PacketReader, PacketWriter, ReadError, ReadErrorKind};
+use crate::pdp::{PdhLog, PdpLog, PDP_SIZE};
+use crate::states::RobotMode;
+
+/// How often records are written. Records have no timestamps of their own, so the n-th record
+/// is taken to be n periods after the start of the file.
+pub const RECORD_PERIOD: Duration = Duration::from_millis(20);
+
+/// The size of a whole record, whose power distribution section is a PDP status tag.
+const RECORD_SIZE: usize = 10 + PDP_SIZE;
+
+/// The size of the header of a version 4 record's power distribution section. Its last byte is
+/// the type of power distribution on the robot, which decides how long the rest of the section
+/// is.
+const PD_HEADER_SIZE: usize = 4;
+const PD_TYPE_REV: u8 = 0x21;
+const PD_TYPE_CTRE: u8 = 0x19;
+
+/// The size of the rest of a version 4 section for a CTRE panel, which is the version 3 section
+/// without the id.
+const CTRE_SIZE: usize = PDP_SIZE - 1;
+
+/// One 20ms sample of a `.dslog` file.
+///
+/// Values are stored at a limited resolution, so they come back slightly rounded when read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsLogRecord {
+    /// The round trip time to the robot in milliseconds, stored in halves up to 127.5.
+    pub trip_time_ms: f32,
+    /// The fraction of control packets lost, stored in steps of 4%.
+    pub packet_loss: f32,
+    pub battery_voltage: f32,
+    /// The roboRIO's CPU usage from 0 to 1, stored in steps of 0.5%.
+    pub rio_cpu: f32,
+    /// The mode and enabled state the driver station was sending.
+    pub control: Control,
+    /// The robot's status. Only [BROWNOUT](Status::BROWNOUT) is recorded.
+    pub status: Status,
+    /// The robot's trace. Only the mode echoes are recorded.
+    pub trace: Trace,
+    /// Whether the robot's watchdog had disabled its outputs, which happens when it loses
+    /// contact with the driver station.
+    pub watchdog: bool,
+    /// CAN bus utilization from 0 to 1, stored in steps of 0.5%.
+    pub can_utilization: f32,
+    /// The radio's signal strength in dB, stored in halves.
+    pub wifi_db: f32,
+    /// The radio's bandwidth use in megabits per second.
+    pub bandwidth_mb: f32,
+    /// What a CTRE power distribution panel reported. This is all zeroes when the robot has a
+    /// REV hub or no power distribution, and the id isn't recorded by version 4 files.
+    pub pdp: PdpLog,
+    /// What a REV power distribution hub reported, in version 4 files from robots with one.
+    pub pdh: Option<PdhLog>,
+}
+
+impl DsLogRecord {
+    /// The status byte, whose bits are set to 0 for true.
+    fn status_byte(&self) -> u8 {
+        let enabled = self.control.contains(Control::ENABLED);
+        let mode = self.control.robot_mode();
+        let flags = [
+            self.status.contains(Status::BROWNOUT),
+            self.watchdog,
+            mode == Some(RobotMode::Teleop),
+            mode == Some(RobotMode::Auto),
+            !enabled,
+            self.trace.contains(Trace::TELEOP_MODE),
+            self.trace.contains(Trace::AUTO_MODE),
+            self.trace.contains(Trace::DISABLED),
+        ];
+        let mut byte = 0;
+        for (bit, flag) in flags.iter().enumerate() {
+            if *flag {
+                byte |= 0x80 >> bit;
+            }
+        }
+        !byte
+    }
+
+    /// Returns the sum of the power distribution's channel currents in amps.
+    pub fn total_current(&self) -> f32 {
+        match self.pdh {
+            Some(ref pdh) => pdh.total_current(),
+            None => self.pdp.total_current(),
+        }
+    }
+
+    fn read(packet: &mut PacketReader, version: i32) -> Result<Self, ReadError> {
+        let trip_time_ms = f32::from(packet.next_u8()?) / 2.0;
+        let packet_loss = f32::from(packet.next_i8()?) / 25.0;
+        let battery_voltage = f32::from(packet.next_u16()?) / 256.0;
+        let rio_cpu = f32::from(packet.next_u8()?) / 200.0;
+        let flags = !packet.next_u8()?;
+        let flag = |bit: u8| flags & (0x80 >> bit) != 0;
+
+        let mut control = if flag(2) {
+            Control::TELEOP
+        } else if flag(3) {
+            Control::AUTO
+        } else {
+            Control::TEST
+        };
+        if !flag(4) {
+            control |= Control::ENABLED;
+        }
+        let mut status = Status::empty();
+        status.set(Status::BROWNOUT, flag(0));
+        let mut trace = Trace::empty();
+        trace.set(Trace::TELEOP_MODE, flag(5));
+        trace.set(Trace::AUTO_MODE, flag(6));
+        trace.set(Trace::DISABLED, flag(7));
+
+        let can_utilization = f32::from(packet.next_u8()?) / 200.0;
+        let wifi_db = f32::from(packet.next_u8()?) / 2.0;
+        let bandwidth_mb = f32::from(packet.next_u16()?) / 256.0;
+        let (pdp, pdh) = if version == 3 {
+            (PdpLog::read(packet)?, None)
+        } else {
+            let header = packet.next_bytes(PD_HEADER_SIZE)?;
+            match header[PD_HEADER_SIZE - 1] {
+                PD_TYPE_CTRE => {
+                    let bytes = packet.next_bytes(CTRE_SIZE)?;
+                    (PdpLog::read_without_id(bytes), None)
+                }
+                PD_TYPE_REV => (PdpLog::default(), Some(PdhLog::read(packet)?)),
+                _ => (PdpLog::default(), None),
+            }
+        };
+
+        Ok(DsLogRecord {
+            trip_time_ms,
+            packet_loss,
+            battery_voltage,
+            rio_cpu,
+            control,
+            status,
+            trace,
+            watchdog: flag(1),
+            can_utilization,
+            wifi_db,
+            bandwidth_mb,
+            pdp,
+            pdh,
+        })
+    }
+
+    /// Writes the record in version 3, which has no room for [pdh](Self::pdh).
+    fn write(&self, packet: &mut PacketWriter) {
+        packet.write_u8((self.trip_time_ms * 2.0).round().clamp(0.0, 255.0) as u8);
+        packet.write_i8((self.packet_loss * 25.0).round().clamp(-128.0, 127.0) as i8);
+        packet.write_u16((self.battery_voltage * 256.0).round().clamp(0.0, 65535.0) as u16);
+        packet.write_u8((self.rio_cpu * 200.0).round().clamp(0.0, 255.0) as u8);
+        packet.write_u8(self.status_byte());
+        packet.write_u8((self.can_utilization * 200.0).round().clamp(0.0, 255.0) as u8);
+        packet.write_u8((self.wifi_db * 2.0).round().clamp(0.0, 255.0) as u8);
+        packet.write_u16((self.bandwidth_mb * 256.0).round().clamp(0.0, 65535.0) as u16);
+        self.pdp.write(packet);
+    }
+}
+
+/// The contents of a `.dslog` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsLog {
+    /// The time of the first record.
+    pub start: DateTime<Utc>,
+    pub records: Vec<DsLogRecord>,
+}
+
+impl DsLog {
+    /// Decodes a whole file. A partly written record at the end, which is left when the driver
+    /// station stops abruptly, is ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        let (version, start) = super::read_header(&mut packet)?;
+        let mut records = Vec::with_capacity(packet.len() / RECORD_SIZE);
+        while !packet.is_empty() {
+            // Version 4 records vary in size, so the only way to know one is complete is to
+            // read it.
+            let mut next = packet.clone();
+            match DsLogRecord::read(&mut next, version) {
+                Ok(record) => records.push(record),
+                Err(ReadError {
+                    kind: ReadErrorKind::UnexpectedEnd { .. },
+                    ..
+                }) => break,
+                Err(e) => return Err(e),
+            }
+            packet = next;
+        }
+        Ok(DsLog { start, records })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        super::read_file(path, DsLog::from_bytes)
+    }
+
+    /// Returns the time the record at `index` was taken.
+    pub fn time_of(&self, index: usize) -> DateTime<Utc> {
+        self.start + chrono::Duration::milliseconds(RECORD_PERIOD.as_millis() as i64 * index as i64)
+    }
+}
+
+/// Writes a `.dslog` file, which is a header followed by a fixed size record every
+/// [RECORD_PERIOD].
+pub struct DsLogWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> DsLogWriter<W> {
+    /// Writes the header, with `start` as the time of the first record.
+    pub fn new(mut writer: W, start: DateTime<Utc>) -> io::Result<Self> {
+        super::write_header(&mut writer, start)?;
+        Ok(DsLogWriter { writer })
+    }
+
+    pub fn write_record(&mut self, record: &DsLogRecord) -> io::Result<()> {
+        let mut packet = PacketWriter::new();
+        record.write(&mut packet);
+        self.writer.write_all(&packet.into_vec())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
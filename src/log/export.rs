@@ -0,0 +1,500 @@
+//! Exporting telemetry and messages from the robot as CSV or JSON Lines for spreadsheets and
+//! scripts.
+//!
+//! Telemetry is exported from [DsLogRecord]s, which can be read from a `.dslog` file or taken
+//! live from [DriverStation::telemetry](crate::DriverStation::telemetry). Messages are exported
+//! from [RioTcpPacket]s or the events of a `.dsevents` file.
+//!
+//! ```no_run
+//! use libds::log::{export::*, DsLog};
+//!
+//! let log = DsLog::open("2024_03_09 13_45_30 Sat.dslog").unwrap();
+//! let config = ExportConfig {
+//!     columns: vec![Column::Elapsed, Column::BatteryVoltage, Column::Brownout],
+//!     ..ExportConfig::default()
+//! };
+//! let mut exporter = TelemetryExporter::new(std::io::stdout(), config).unwrap();
+//! exporter.write_log(&log).unwrap();
+//! ```
+
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::io::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{DsEvents, DsLog, DsLogRecord};
+use crate::messages::ds::udp::Control;
+use crate::messages::rio::{RioTcpPacket, Status};
+use crate::states::RobotMode;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// Comma separated values with a header row of column names.
+    Csv,
+    /// One JSON object per line, keyed by column name.
+    JsonLines,
+}
+
+/// A column of exported telemetry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Column {
+    /// The time of the sample in RFC 3339 format.
+    Time,
+    /// Seconds since the first exported row.
+    Elapsed,
+    /// The mode being sent to the robot: `teleop`, `auto` or `test`.
+    Mode,
+    /// Whether the robot was being enabled.
+    Enabled,
+    BatteryVoltage,
+    Brownout,
+    /// The round trip time in milliseconds.
+    TripTime,
+    /// The fraction of packets lost, from 0 to 1.
+    PacketLoss,
+    /// The roboRIO's CPU usage, from 0 to 1.
+    RioCpu,
+    /// CAN bus utilization, from 0 to 1.
+    CanUtilization,
+    /// Whether the robot's watchdog had disabled its outputs.
+    Watchdog,
+    /// The current in amps on a power distribution channel, from 0 to 15, or to 23 for a REV
+    /// hub.
+    PdpCurrent(usize),
+    /// The total current in amps across the power distribution channels.
+    TotalCurrent,
+    /// The power distribution panel's temperature in degrees Celsius.
+    PdpTemperature,
+}
+
+impl Column {
+    /// The telemetry exported by default.
+    pub const DEFAULT: [Column; 7] = [
+        Column::Time,
+        Column::Mode,
+        Column::Enabled,
+        Column::BatteryVoltage,
+        Column::Brownout,
+        Column::TripTime,
+        Column::PacketLoss,
+    ];
+
+    /// The name used in the CSV header and as the JSON key.
+    pub fn name(self) -> String {
+        match self {
+            Column::Time => "time".to_owned(),
+            Column::Elapsed => "elapsed".to_owned(),
+            Column::Mode => "mode".to_owned(),
+            Column::Enabled => "enabled".to_owned(),
+            Column::BatteryVoltage => "battery_voltage".to_owned(),
+            Column::Brownout => "brownout".to_owned(),
+            Column::TripTime => "trip_time_ms".to_owned(),
+            Column::PacketLoss => "packet_loss".to_owned(),
+            Column::RioCpu => "rio_cpu".to_owned(),
+            Column::CanUtilization => "can_utilization".to_owned(),
+            Column::Watchdog => "watchdog".to_owned(),
+            Column::PdpCurrent(channel) => format!("pdp_current_{}", channel),
+            Column::TotalCurrent => "total_current".to_owned(),
+            Column::PdpTemperature => "pdp_temperature".to_owned(),
+        }
+    }
+
+    fn value(self, time: DateTime<Utc>, elapsed: f32, record: &DsLogRecord) -> Value {
+        match self {
+            Column::Time => Value::Time(time),
+            Column::Elapsed => Value::Number(elapsed),
+            Column::Mode => Value::Text(mode_name(record.control.robot_mode()).to_owned()),
+            Column::Enabled => Value::Bool(record.control.contains(Control::ENABLED)),
+            Column::BatteryVoltage => Value::Number(record.battery_voltage),
+            Column::Brownout => Value::Bool(record.status.contains(Status::BROWNOUT)),
+            Column::TripTime => Value::Number(record.trip_time_ms),
+            Column::PacketLoss => Value::Number(record.packet_loss),
+            Column::RioCpu => Value::Number(record.rio_cpu),
+            Column::CanUtilization => Value::Number(record.can_utilization),
+            Column::Watchdog => Value::Bool(record.watchdog),
+            Column::PdpCurrent(channel) => {
+                let currents = match record.pdh {
+                    Some(ref pdh) => &pdh.currents[..],
+                    None => &record.pdp.currents[..],
+                };
+                currents
+                    .get(channel)
+                    .map_or(Value::Empty, |current| Value::Number(*current))
+            }
+            Column::TotalCurrent => Value::Number(record.total_current()),
+            Column::PdpTemperature => Value::Number(record.pdp.temperature()),
+        }
+    }
+}
+
+/// A column of exported messages. Columns that don't apply to a message are left empty.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MessageColumn {
+    /// The time the message was received in RFC 3339 format.
+    Time,
+    /// Seconds since the first exported row.
+    Elapsed,
+    /// The robot's timestamp on the message, in seconds.
+    RobotTime,
+    /// `stdout`, `error`, `warning`, `radio`, or `event` for other text in a `.dsevents` file.
+    Kind,
+    /// The error code of an error or warning.
+    Code,
+    /// The printed text, or an error's details.
+    Message,
+    Location,
+    Stack,
+}
+
+impl MessageColumn {
+    /// The message fields exported by default.
+    pub const DEFAULT: [MessageColumn; 5] = [
+        MessageColumn::Time,
+        MessageColumn::Kind,
+        MessageColumn::Code,
+        MessageColumn::Message,
+        MessageColumn::Location,
+    ];
+
+    /// The name used in the CSV header and as the JSON key.
+    pub fn name(self) -> String {
+        match self {
+            MessageColumn::Time => "time",
+            MessageColumn::Elapsed => "elapsed",
+            MessageColumn::RobotTime => "robot_time",
+            MessageColumn::Kind => "kind",
+            MessageColumn::Code => "code",
+            MessageColumn::Message => "message",
+            MessageColumn::Location => "location",
+            MessageColumn::Stack => "stack",
+        }
+        .to_owned()
+    }
+
+    fn value(self, time: DateTime<Utc>, elapsed: f32, message: &Message) -> Value {
+        use self::Message::Robot;
+        use crate::messages::rio::RioTcpPacket::*;
+        match self {
+            MessageColumn::Time => Value::Time(time),
+            MessageColumn::Elapsed => Value::Number(elapsed),
+            MessageColumn::Kind => Value::Text(message.kind().to_owned()),
+            MessageColumn::RobotTime => match message {
+                Robot(StandardOutput { timestamp, .. }) | Robot(ErrorMessage { timestamp, .. }) => {
+                    Value::Number(*timestamp)
+                }
+                _ => Value::Empty,
+            },
+            MessageColumn::Code => match message {
+                Robot(ErrorMessage { error_code, .. }) => Value::Number(f32::from(*error_code)),
+                _ => Value::Empty,
+            },
+            MessageColumn::Message => match message {
+                Robot(StandardOutput { message, .. })
+                | Robot(RadioEvent(message))
+                | Robot(ErrorMessage {
+                    details: message, ..
+                }) => Value::Text(message.clone()),
+                Message::Text(text) => Value::Text((*text).to_owned()),
+                _ => Value::Empty,
+            },
+            MessageColumn::Location => match message {
+                Robot(ErrorMessage { location, .. }) => Value::Text(location.clone()),
+                _ => Value::Empty,
+            },
+            MessageColumn::Stack => match message {
+                Robot(ErrorMessage { call_stack, .. }) => Value::Text(call_stack.clone()),
+                _ => Value::Empty,
+            },
+        }
+    }
+}
+
+fn mode_name(mode: Option<RobotMode>) -> &'static str {
+    match mode {
+        Some(RobotMode::Teleop) => "teleop",
+        Some(RobotMode::Auto) => "auto",
+        Some(RobotMode::Test) => "test",
+        None => "unknown",
+    }
+}
+
+/// What to export, and how.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportConfig<C> {
+    pub format: Format,
+    /// The columns, in order.
+    pub columns: Vec<C>,
+    /// Rows before this time are skipped.
+    pub from: Option<DateTime<Utc>>,
+    /// Rows at or after this time are skipped.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl<C> ExportConfig<C> {
+    /// Returns whether a row at `time` is in the time range.
+    pub fn includes(&self, time: DateTime<Utc>) -> bool {
+        self.from.map_or(true, |from| time >= from) && self.until.map_or(true, |until| time < until)
+    }
+}
+
+impl Default for ExportConfig<Column> {
+    fn default() -> Self {
+        ExportConfig {
+            format: Format::Csv,
+            columns: Column::DEFAULT.to_vec(),
+            from: None,
+            until: None,
+        }
+    }
+}
+
+impl Default for ExportConfig<MessageColumn> {
+    fn default() -> Self {
+        ExportConfig {
+            format: Format::Csv,
+            columns: MessageColumn::DEFAULT.to_vec(),
+            from: None,
+            until: None,
+        }
+    }
+}
+
+enum Value {
+    Time(DateTime<Utc>),
+    Number(f32),
+    Bool(bool),
+    Text(String),
+    Empty,
+}
+
+enum Message<'a> {
+    Robot(&'a RioTcpPacket),
+    /// Text from a `.dsevents` file that isn't a message from the robot.
+    Text(&'a str),
+}
+
+impl<'a> Message<'a> {
+    /// The name of the kind of message for [MessageColumn::Kind].
+    fn kind(&self) -> &'static str {
+        match self {
+            Message::Robot(RioTcpPacket::StandardOutput { .. }) => "stdout",
+            Message::Robot(RioTcpPacket::ErrorMessage { is_error: true, .. }) => "error",
+            Message::Robot(RioTcpPacket::ErrorMessage { .. }) => "warning",
+            Message::Robot(RioTcpPacket::RadioEvent(_)) => "radio",
+            Message::Robot(_) | Message::Text(_) => "event",
+        }
+    }
+}
+
+/// Writes rows in either format, keeping track of the time of the first row.
+struct RowWriter<W: Write> {
+    writer: W,
+    format: Format,
+    names: Vec<String>,
+    first: Option<DateTime<Utc>>,
+    line: String,
+}
+
+impl<W: Write> RowWriter<W> {
+    fn new(mut writer: W, format: Format, names: Vec<String>) -> io::Result<Self> {
+        if format == Format::Csv {
+            let header = names
+                .iter()
+                .map(|name| csv_field(name))
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(writer, "{}", header)?;
+        }
+        Ok(RowWriter {
+            writer,
+            format,
+            names,
+            first: None,
+            line: String::new(),
+        })
+    }
+
+    /// Returns the seconds since the first row, making `time` the first if there isn't one.
+    fn elapsed(&mut self, time: DateTime<Utc>) -> f32 {
+        let first = *self.first.get_or_insert(time);
+        (time - first).num_microseconds().unwrap_or(0) as f32 / 1_000_000.0
+    }
+
+    fn write_row<I: Iterator<Item = Value>>(&mut self, values: I) -> io::Result<()> {
+        self.line.clear();
+        let line = &mut self.line;
+        match self.format {
+            Format::Csv => {
+                for (i, value) in values.enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    match value {
+                        Value::Time(time) => line.push_str(&time_string(time)),
+                        Value::Number(n) => write!(line, "{}", n).unwrap(),
+                        Value::Bool(b) => write!(line, "{}", b).unwrap(),
+                        Value::Text(text) => line.push_str(&csv_field(&text)),
+                        Value::Empty => {}
+                    }
+                }
+            }
+            Format::JsonLines => {
+                line.push('{');
+                for (i, (name, value)) in self.names.iter().zip(values).enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    json_string(line, name);
+                    line.push(':');
+                    match value {
+                        Value::Time(time) => json_string(line, &time_string(time)),
+                        Value::Number(n) if n.is_finite() => write!(line, "{}", n).unwrap(),
+                        Value::Number(_) | Value::Empty => line.push_str("null"),
+                        Value::Bool(b) => write!(line, "{}", b).unwrap(),
+                        Value::Text(text) => json_string(line, &text),
+                    }
+                }
+                line.push('}');
+            }
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())
+    }
+}
+
+fn time_string(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Quotes a CSV field if it contains anything that would break the row up.
+fn csv_field(text: &str) -> String {
+    if text.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Exports telemetry, one row per record.
+pub struct TelemetryExporter<W: Write> {
+    rows: RowWriter<W>,
+    config: ExportConfig<Column>,
+}
+
+impl<W: Write> TelemetryExporter<W> {
+    /// Creates an exporter, writing the header if there is one.
+    pub fn new(writer: W, config: ExportConfig<Column>) -> io::Result<Self> {
+        let names = config.columns.iter().map(|column| column.name()).collect();
+        Ok(TelemetryExporter {
+            rows: RowWriter::new(writer, config.format, names)?,
+            config,
+        })
+    }
+
+    /// Writes a record taken at `time`, if it's in the time range.
+    pub fn write_record(&mut self, time: DateTime<Utc>, record: &DsLogRecord) -> io::Result<()> {
+        if !self.config.includes(time) {
+            return Ok(());
+        }
+        let elapsed = self.rows.elapsed(time);
+        let columns = &self.config.columns;
+        self.rows.write_row(
+            columns
+                .iter()
+                .map(|column| column.value(time, elapsed, record)),
+        )
+    }
+
+    /// Writes every record of a `.dslog` file in the time range.
+    pub fn write_log(&mut self, log: &DsLog) -> io::Result<()> {
+        for (i, record) in log.records.iter().enumerate() {
+            self.write_record(log.time_of(i), record)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.rows.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.rows.writer
+    }
+}
+
+/// Exports messages from the robot, one row per message.
+pub struct MessageExporter<W: Write> {
+    rows: RowWriter<W>,
+    config: ExportConfig<MessageColumn>,
+}
+
+impl<W: Write> MessageExporter<W> {
+    /// Creates an exporter, writing the header if there is one.
+    pub fn new(writer: W, config: ExportConfig<MessageColumn>) -> io::Result<Self> {
+        let names = config.columns.iter().map(|column| column.name()).collect();
+        Ok(MessageExporter {
+            rows: RowWriter::new(writer, config.format, names)?,
+            config,
+        })
+    }
+
+    fn write(&mut self, time: DateTime<Utc>, message: Message) -> io::Result<()> {
+        if !self.config.includes(time) {
+            return Ok(());
+        }
+        let elapsed = self.rows.elapsed(time);
+        let columns = &self.config.columns;
+        self.rows.write_row(
+            columns
+                .iter()
+                .map(|column| column.value(time, elapsed, &message)),
+        )
+    }
+
+    /// Writes a message received at `time`, if it's in the time range. Only printed text,
+    /// errors, warnings and radio events are exported.
+    pub fn write_message(&mut self, time: DateTime<Utc>, packet: &RioTcpPacket) -> io::Result<()> {
+        match packet {
+            RioTcpPacket::StandardOutput { .. }
+            | RioTcpPacket::ErrorMessage { .. }
+            | RioTcpPacket::RadioEvent(_) => self.write(time, Message::Robot(packet)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes every event of a `.dsevents` file in the time range.
+    pub fn write_events(&mut self, events: &DsEvents) -> io::Result<()> {
+        for event in &events.events {
+            match event.message() {
+                Some(packet) => self.write_message(event.time, &packet)?,
+                None => self.write(event.time, Message::Text(&event.text))?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.rows.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.rows.writer
+    }
+}
@@ -0,0 +1,143 @@
+//! Recording matches in the `.dslog` and `.dsevents` formats the FRC log viewer opens.
+//!
+//! Both files start with a header of the format version as an [i32] followed by the time
+//! recording started, and every value in them is big endian. Times are LabVIEW timestamps:
+//! whole seconds since 1904-01-01 UTC as an [i64], then the fraction of a second as a [u64] in
+//! units of 2^-64 seconds.
+//!
+//! Version 3 of the formats is written, and versions 3 and 4 are read. Version 4, which the
+//! driver station has written since 2022, changes only the power distribution section of
+//! `.dslog` records: it starts with a header giving the type of power distribution, so records
+//! from robots with a REV hub can hold its 24 channels. That layout isn't documented officially;
+//! it's the one AdvantageScope's reader decodes
+//! (<https://github.com/Mechanical-Advantage/AdvantageScope>).
+
+mod dsevents;
+mod dslog;
+pub mod export;
+
+pub use self::dsevents::{event_text, parse_event_text, DsEvent, DsEvents, DsEventsWriter};
+pub use self::dslog::{DsLog, DsLogRecord, DsLogWriter, RECORD_PERIOD};
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::messages::rio::RioTcpPacket;
+use crate::packet::{PacketReader, PacketWriter, ReadError};
+
+/// The version of the file formats that is written.
+pub const LOG_VERSION: i32 = 3;
+
+/// The newest version of the file formats that can be read.
+const NEWEST_VERSION: i32 = 4;
+
+/// Seconds from the LabVIEW epoch, 1904-01-01, to the Unix epoch.
+const LABVIEW_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Writes `time` as a LabVIEW timestamp.
+fn write_timestamp(packet: &mut PacketWriter, time: DateTime<Utc>) {
+    packet.write_i64(time.timestamp() + LABVIEW_EPOCH_OFFSET);
+    // Nanoseconds scaled to 2^-64 seconds, which is 2^64 / 10^9 per nanosecond.
+    let fraction = (u128::from(time.timestamp_subsec_nanos()) << 64) / 1_000_000_000;
+    packet.write_u64(fraction as u64);
+}
+
+/// Reads a LabVIEW timestamp.
+fn read_timestamp(packet: &mut PacketReader) -> Result<DateTime<Utc>, ReadError> {
+    let seconds = packet.next_i64()?;
+    let fraction = packet.next_u64()?;
+    let nanos = (u128::from(fraction) * 1_000_000_000) >> 64;
+    seconds
+        .checked_sub(LABVIEW_EPOCH_OFFSET)
+        .and_then(|seconds| Utc.timestamp_opt(seconds, nanos as u32).single())
+        .ok_or_else(|| packet.invalid(16, "timestamp"))
+}
+
+/// Reads the header both formats share, returning the format version and the time recording
+/// started.
+fn read_header(packet: &mut PacketReader) -> Result<(i32, DateTime<Utc>), ReadError> {
+    let version = packet.next_i32()?;
+    if !(LOG_VERSION..=NEWEST_VERSION).contains(&version) {
+        return Err(packet.invalid(4, "log version"));
+    }
+    Ok((version, read_timestamp(packet)?))
+}
+
+/// Reads a whole file, reporting a file that can't be decoded as [InvalidData].
+///
+/// [InvalidData]: io::ErrorKind::InvalidData
+fn read_file<T, P: AsRef<Path>>(
+    path: P,
+    decode: fn(&[u8]) -> Result<T, ReadError>,
+) -> io::Result<T> {
+    let bytes = fs::read(path)?;
+    decode(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes the header both formats share.
+fn write_header<W: io::Write>(writer: &mut W, start: DateTime<Utc>) -> io::Result<()> {
+    let mut header = PacketWriter::new();
+    header.write_i32(LOG_VERSION);
+    write_timestamp(&mut header, start);
+    writer.write_all(&header.into_vec())
+}
+
+/// A pair of `.dslog` and `.dsevents` files for one session, named after the time it started
+/// the way the driver station names them, such as `2024_03_09 13_45_30 Sat.dslog`.
+pub struct Logger {
+    dslog: DsLogWriter<BufWriter<File>>,
+    dsevents: DsEventsWriter<BufWriter<File>>,
+    path: PathBuf,
+}
+
+impl Logger {
+    /// Creates both files in `dir`.
+    pub fn create<P: AsRef<Path>>(dir: P, start: DateTime<Utc>) -> io::Result<Self> {
+        let path = dir
+            .as_ref()
+            .join(start.format("%Y_%m_%d %H_%M_%S %a").to_string());
+        let dslog = File::create(path.with_extension("dslog"))?;
+        let dsevents = File::create(path.with_extension("dsevents"))?;
+        Ok(Logger {
+            dslog: DsLogWriter::new(BufWriter::new(dslog), start)?,
+            dsevents: DsEventsWriter::new(BufWriter::new(dsevents), start)?,
+            path,
+        })
+    }
+
+    /// Returns the path of the `.dslog` file.
+    pub fn dslog_path(&self) -> PathBuf {
+        self.path.with_extension("dslog")
+    }
+
+    /// Returns the path of the `.dsevents` file.
+    pub fn dsevents_path(&self) -> PathBuf {
+        self.path.with_extension("dsevents")
+    }
+
+    /// Appends a record, which should be done every [RECORD_PERIOD].
+    pub fn log(&mut self, record: &DsLogRecord) -> io::Result<()> {
+        self.dslog.write_record(record)
+    }
+
+    /// Appends a message from the robot to the events, if it's one that's shown in the log
+    /// viewer.
+    pub fn log_message(&mut self, time: DateTime<Utc>, packet: &RioTcpPacket) -> io::Result<()> {
+        self.dsevents.write_message(time, packet)
+    }
+
+    /// Appends some text to the events.
+    pub fn log_event(&mut self, time: DateTime<Utc>, text: &str) -> io::Result<()> {
+        self.dsevents.write_event(time, text)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.dslog.flush()?;
+        self.dsevents.flush()
+    }
+}
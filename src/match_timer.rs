@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::interlock::EnableRefusal;
+use crate::messages::ds::tcp::MatchInfo;
+use crate::states::RobotMode;
+use crate::DriverStation;
+
+/// Timings and field data for a practice match.
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    pub countdown: Duration,
+    pub auto: Duration,
+    pub pause: Duration,
+    pub teleop: Duration,
+    /// Sent to the robot when the match is started.
+    pub match_info: Option<MatchInfo>,
+    /// Sent to the robot at the start of autonomous.
+    pub game_data: Option<String>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            countdown: Duration::from_secs(3),
+            auto: Duration::from_secs(15),
+            pause: Duration::from_secs(3),
+            teleop: Duration::from_secs(135),
+            match_info: None,
+            game_data: None,
+        }
+    }
+}
+
+impl MatchConfig {
+    /// Returns how long after the start of the countdown the given phase ends.
+    pub fn phase_end(&self, phase: MatchPhase) -> Duration {
+        match phase {
+            MatchPhase::Idle => Duration::from_secs(0),
+            MatchPhase::Countdown => self.countdown,
+            MatchPhase::Auto => self.countdown + self.auto,
+            MatchPhase::Pause => self.countdown + self.auto + self.pause,
+            MatchPhase::Teleop | MatchPhase::Finished => {
+                self.countdown + self.auto + self.pause + self.teleop
+            }
+        }
+    }
+
+    /// Returns the phase a match is in at the given time after the start of the countdown.
+    pub fn phase_at(&self, elapsed: Duration) -> MatchPhase {
+        let mut phase = MatchPhase::Countdown;
+        while phase != MatchPhase::Finished && elapsed >= self.phase_end(phase) {
+            phase = phase.next();
+        }
+        phase
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchPhase {
+    Idle,
+    Countdown,
+    Auto,
+    Pause,
+    Teleop,
+    Finished,
+}
+
+impl MatchPhase {
+    fn next(self) -> Self {
+        match self {
+            MatchPhase::Idle => MatchPhase::Countdown,
+            MatchPhase::Countdown => MatchPhase::Auto,
+            MatchPhase::Auto => MatchPhase::Pause,
+            MatchPhase::Pause => MatchPhase::Teleop,
+            MatchPhase::Teleop | MatchPhase::Finished => MatchPhase::Finished,
+        }
+    }
+}
+
+/// Runs a practice match on a [DriverStation].
+///
+/// The timer does not run on its own; [update](MatchTimer::update) should be called regularly
+/// (every control packet is plenty) and switches the robot's mode and enable state as the match
+/// moves from phase to phase.
+pub struct MatchTimer<C: Clock = SystemClock> {
+    config: MatchConfig,
+    clock: C,
+    started: Option<Instant>,
+    phase: MatchPhase,
+}
+
+impl MatchTimer<SystemClock> {
+    pub fn new(config: MatchConfig) -> Self {
+        MatchTimer::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> MatchTimer<C> {
+    pub fn with_clock(config: MatchConfig, clock: C) -> Self {
+        MatchTimer {
+            config,
+            clock,
+            started: None,
+            phase: MatchPhase::Idle,
+        }
+    }
+
+    pub fn config(&self) -> &MatchConfig {
+        &self.config
+    }
+
+    pub fn phase(&self) -> MatchPhase {
+        self.phase
+    }
+
+    /// Starts the countdown to a new match, restarting the match if one is running.
+    pub fn start(&mut self, ds: &DriverStation) {
+        self.started = Some(self.clock.now());
+        self.phase = MatchPhase::Idle;
+        // Entering the countdown can't fail because it only disables the robot.
+        let _ = self.advance(ds, MatchPhase::Countdown);
+    }
+
+    /// Ends the match early and disables the robot.
+    pub fn stop(&mut self, ds: &DriverStation) {
+        if self.phase != MatchPhase::Idle {
+            self.phase = MatchPhase::Finished;
+        }
+        self.started = None;
+        // Only enabling can be refused, so there's nothing to handle here.
+        ds.set_enabled(false).unwrap_or(());
+    }
+
+    /// Moves the match to the phase for the current time.
+    ///
+    /// Returns the current phase, or the reason the robot could not be enabled if a phase that
+    /// enables it was entered. The match carries on either way.
+    pub fn update(&mut self, ds: &DriverStation) -> Result<MatchPhase, EnableRefusal> {
+        match self.started {
+            Some(started) => {
+                let elapsed = self.clock.now().saturating_duration_since(started);
+                let target = self.config.phase_at(elapsed);
+                self.advance(ds, target)
+            }
+            None => Ok(self.phase),
+        }
+    }
+
+    /// Returns the time left in the current phase.
+    pub fn remaining(&self) -> Duration {
+        match self.started {
+            Some(started) => {
+                let elapsed = self.clock.now().saturating_duration_since(started);
+                self.config.phase_end(self.phase).saturating_sub(elapsed)
+            }
+            None => Duration::from_secs(0),
+        }
+    }
+
+    fn advance(
+        &mut self,
+        ds: &DriverStation,
+        target: MatchPhase,
+    ) -> Result<MatchPhase, EnableRefusal> {
+        let mut result = Ok(());
+        while self.phase != target {
+            self.phase = self.phase.next();
+            result = self.enter(ds);
+        }
+        if self.phase == MatchPhase::Finished {
+            self.started = None;
+        }
+        result.map(|_| self.phase)
+    }
+
+    fn enter(&self, ds: &DriverStation) -> Result<(), EnableRefusal> {
+        match self.phase {
+            MatchPhase::Idle => Ok(()),
+            MatchPhase::Countdown => {
+                ds.set_enabled(false)?;
+                ds.set_mode(RobotMode::Auto);
+                if let Some(ref info) = self.config.match_info {
+                    ds.set_match_info(info.clone());
+                }
+                Ok(())
+            }
+            MatchPhase::Auto => {
+                if let Some(ref data) = self.config.game_data {
+                    ds.set_game_data(data.clone());
+                }
+                ds.set_mode(RobotMode::Auto);
+                ds.set_enabled(true)
+            }
+            MatchPhase::Pause => {
+                ds.set_enabled(false)?;
+                ds.set_mode(RobotMode::Teleop);
+                Ok(())
+            }
+            MatchPhase::Teleop => {
+                ds.set_mode(RobotMode::Teleop);
+                ds.set_enabled(true)
+            }
+            MatchPhase::Finished => ds.set_enabled(false),
+        }
+    }
+}
@@ -1,6 +1,191 @@
+pub mod udp {
+    use chrono::prelude::*;
+
+    use crate::joystick::Joystick;
+    use crate::packet::{PacketReader, PacketWriter, ReadError};
+    use crate::states::{Alliance, RobotMode};
+
+    bitflags! {
+        pub struct Control: u8 {
+            const ESTOP = 0b1000_0000;
+            const FMS_CONNECTED = 0b0000_1000;
+            const ENABLED = 0b0000_0100;
+
+            const TELEOP = 0b00;
+            const TEST = 0b01;
+            const AUTO = 0b10;
+        }
+    }
+
+    impl Control {
+        pub fn robot_mode(&self) -> Option<RobotMode> {
+            // TELEOP is all zeroes, so compare the mode bits rather than using contains.
+            RobotMode::from(self.bits() & 0b11)
+        }
+    }
+
+    bitflags! {
+        pub struct Request: u8 {
+            const REBOOT_ROBORIO = 0b1000;
+            const RESTART_ROBOT_CODE = 0b0100;
+        }
+    }
+
+    /// The control packet a driver station sends to the robot every 20ms.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DsUdpPacket {
+        pub sequence_num: u16,
+        pub comm_version: u8,
+        pub control: Control,
+        pub request: Request,
+        pub alliance: Alliance,
+        /// One entry for each joystick tag, with [None] for empty slots.
+        pub joysticks: Vec<Option<Joystick>>,
+        /// Seconds left in the current match period.
+        pub countdown: Option<f32>,
+        /// The current time, sent when the robot asks for it. Only whole microseconds are sent,
+        /// and only years from 1900 to 2155 can be represented.
+        pub date: Option<DateTime<Utc>>,
+        /// The IANA name of the driver station's timezone, such as `America/New_York`, sent
+        /// along with the date.
+        pub timezone: Option<String>,
+        /// Every other tag, as its id and contents.
+        pub tags: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl DsUdpPacket {
+        /// Parses a control packet.
+        pub fn decode(bytes: &[u8]) -> Result<Self, ReadError> {
+            let mut packet = PacketReader::new(bytes);
+            let sequence_num = packet.next_u16()?;
+            let comm_version = packet.next_u8()?;
+            let control = Control::from_bits_truncate(packet.next_u8()?);
+            if control.robot_mode().is_none() {
+                return Err(packet.invalid(1, "robot mode"));
+            }
+            let request = Request::from_bits_truncate(packet.next_u8()?);
+            let alliance = Alliance::from_position_u8(packet.next_u8()?);
+
+            let mut decoded = DsUdpPacket {
+                sequence_num,
+                comm_version,
+                control,
+                request,
+                alliance,
+                joysticks: Vec::new(),
+                countdown: None,
+                date: None,
+                timezone: None,
+                tags: Vec::new(),
+            };
+            while !packet.is_empty() {
+                let (id, mut tag) = packet.next_tag()?;
+                match id {
+                    0x07 => decoded.countdown = Some(tag.next_f32()?),
+                    0x0c => decoded.joysticks.push(if tag.is_empty() {
+                        None
+                    } else {
+                        Some(Joystick::read_udp_tag(&mut tag)?)
+                    }),
+                    0x0f => decoded.date = Some(read_date(&mut tag)?),
+                    0x10 => decoded.timezone = Some(tag.rest_string()),
+                    _ => decoded.tags.push((id, tag.rest().to_vec())),
+                }
+                tag.finish()?;
+            }
+
+            Ok(decoded)
+        }
+
+        /// Encodes the packet. Tags are written in a fixed order: joysticks, countdown, timezone,
+        /// date and then every other tag.
+        pub fn encode(&self) -> Vec<u8> {
+            let mut buf = Vec::new();
+            self.encode_into(&mut buf);
+            buf
+        }
+
+        /// Encodes the packet into `buf`, replacing its contents. Once `buf` has grown large
+        /// enough this doesn't allocate.
+        pub fn encode_into(&self, buf: &mut Vec<u8>) {
+            let mut packet = PacketWriter::from_vec(std::mem::take(buf));
+            packet.write_u16(self.sequence_num);
+            packet.write_u8(self.comm_version);
+            packet.write_u8(self.control.bits());
+            packet.write_u8(self.request.bits());
+            packet.write_u8(self.alliance.to_position_u8());
+
+            for stick in &self.joysticks {
+                packet.write_tag(0x0c, |tag| {
+                    if let Some(stick) = stick {
+                        stick.write_udp_tag(tag);
+                    }
+                });
+            }
+            if let Some(countdown) = self.countdown {
+                packet.write_tag(0x07, |tag| tag.write_f32(countdown));
+            }
+            if let Some(ref timezone) = self.timezone {
+                let timezone = timezone.as_bytes();
+                packet.write_tag(0x10, |tag| {
+                    tag.write_slice(&timezone[..timezone.len().min(254)])
+                });
+            }
+            if let Some(date) = self.date {
+                packet.write_tag(0x0f, |tag| write_date(tag, date));
+            }
+            for (id, contents) in &self.tags {
+                packet.write_tag(*id, |tag| tag.write_slice(contents));
+            }
+
+            *buf = packet.into_vec();
+        }
+    }
+
+    /// Writes the contents of a date tag, which follows C's `struct tm`: microseconds as a
+    /// [u32], then seconds, minutes, hours, day of the month (1-31), month (0-11) and years
+    /// since 1900, as one byte each. Years outside 1900-2155 are clamped.
+    pub(crate) fn write_date(tag: &mut PacketWriter, date: DateTime<Utc>) {
+        let micros = date.nanosecond() / 1000;
+        let year = (date.year() - 1900).max(0).min(i32::from(u8::MAX));
+
+        tag.write_u32(micros.min(999_999));
+        tag.write_u8(date.second() as u8);
+        tag.write_u8(date.minute() as u8);
+        tag.write_u8(date.hour() as u8);
+        tag.write_u8(date.day() as u8);
+        tag.write_u8(date.month0() as u8);
+        tag.write_u8(year as u8);
+    }
+
+    pub(crate) fn read_date(tag: &mut PacketReader) -> Result<DateTime<Utc>, ReadError> {
+        let micros = tag.next_u32()?;
+        let second = tag.next_u8()?;
+        let minute = tag.next_u8()?;
+        let hour = tag.next_u8()?;
+        let day = tag.next_u8()?;
+        let month0 = tag.next_u8()?;
+        let year = i32::from(tag.next_u8()?) + 1900;
+
+        NaiveDate::from_ymd_opt(year, u32::from(month0) + 1, u32::from(day))
+            .and_then(|date| {
+                date.and_hms_micro_opt(
+                    u32::from(hour),
+                    u32::from(minute),
+                    u32::from(second),
+                    micros,
+                )
+            })
+            .map(|date| DateTime::from_utc(date, Utc))
+            .ok_or_else(|| tag.invalid(10, "date"))
+    }
+}
+
 pub mod tcp {
     use crate::joystick::{AxisType, JoystickType};
+    use crate::packet::{PacketReader, ReadError, ReadErrorKind};
     use crate::states::MatchType;
+    use crate::version::ProtocolVersion;
 
     use byteorder::{NetworkEndian, WriteBytesExt};
 
@@ -9,20 +194,32 @@ pub mod tcp {
 
         fn as_bytes(&self) -> Vec<u8>;
 
+        /// Returns the contents of the tag as laid out in the given version of the protocol.
+        fn as_bytes_for(&self, _version: ProtocolVersion) -> Vec<u8> {
+            self.as_bytes()
+        }
+
         fn to_packet(&self) -> Vec<u8> {
+            self.to_packet_for(ProtocolVersion::CURRENT)
+        }
+
+        fn to_packet_for(&self, version: ProtocolVersion) -> Vec<u8> {
             let mut buf = Vec::new();
             buf.push(self.id());
-            buf.extend(self.as_bytes());
+            buf.extend(self.as_bytes_for(version));
 
+            // Whatever doesn't fit in a frame is cut off, so the length always matches.
+            buf.truncate(u16::MAX as usize);
             let len = buf.len();
             let mut packet = Vec::new();
-            packet.write_u16::<NetworkEndian>(len as u16);
+            packet.write_u16::<NetworkEndian>(len as u16).unwrap();
             packet.extend(buf);
 
             packet
         }
     }
 
+    #[derive(Clone, Debug, PartialEq)]
     pub enum TcpTag {
         JoystickDescriptor(JoystickDescriptor),
         MatchInfo(MatchInfo),
@@ -31,29 +228,137 @@ pub mod tcp {
 
     impl TcpTag {
         pub fn to_packet(&self) -> Vec<u8> {
+            self.to_packet_for(ProtocolVersion::CURRENT)
+        }
+
+        pub fn to_packet_for(&self, version: ProtocolVersion) -> Vec<u8> {
             match self {
-                TcpTag::JoystickDescriptor(jd) => Vec::new(), // TODO
-                TcpTag::MatchInfo(mi) => mi.to_packet(),
-                TcpTag::GameData(gd) => gd.to_packet(),
+                TcpTag::JoystickDescriptor(jd) => jd.to_packet_for(version),
+                TcpTag::MatchInfo(mi) => mi.to_packet_for(version),
+                TcpTag::GameData(gd) => gd.to_packet_for(version),
             }
         }
+
+        /// Parses a tag from its id and contents, without the length prefix.
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+            TcpTag::from_bytes_for(bytes, ProtocolVersion::CURRENT)
+        }
+
+        /// Parses a tag laid out as in the given version of the protocol.
+        pub fn from_bytes_for(bytes: &[u8], version: ProtocolVersion) -> Result<Self, ReadError> {
+            let mut packet = PacketReader::new(bytes);
+            let tag = match packet.next_u8()? {
+                0x02 => {
+                    let index = packet.next_u8()?;
+                    let xbox_flag = if version.has_xbox_flag() {
+                        Some(packet.next_u8()? == 0x01)
+                    } else {
+                        None
+                    };
+                    let stick_type =
+                        JoystickType::from(packet.next_i8()?).unwrap_or(JoystickType::Unknown);
+                    let is_xbox = xbox_flag.unwrap_or_else(|| stick_type.is_xinput());
+                    let name = packet.next_u8_string()?;
+                    let num_axes = packet.next_u8()?;
+                    let mut axis_types = Vec::with_capacity(num_axes as usize);
+                    for _ in 0..num_axes {
+                        let axis = packet.next_u8()?;
+                        axis_types.push(
+                            AxisType::from(axis).ok_or_else(|| packet.invalid(1, "axis type"))?,
+                        );
+                    }
+                    TcpTag::JoystickDescriptor(JoystickDescriptor {
+                        index,
+                        is_xbox,
+                        stick_type,
+                        name,
+                        axis_types,
+                        button_count: packet.next_u8()?,
+                        pov_count: packet.next_u8()?,
+                    })
+                }
+                0x07 => {
+                    let competition = packet.next_u8_string()?;
+                    let match_type = packet.next_u8()?;
+                    let match_type = MatchType::from(match_type)
+                        .ok_or_else(|| packet.invalid(1, "match type"))?;
+                    let (match_number, replay_number) = if version.has_match_numbers() {
+                        (packet.next_u16()?, packet.next_u8()?)
+                    } else {
+                        (0, 0)
+                    };
+                    TcpTag::MatchInfo(MatchInfo {
+                        competition,
+                        match_type,
+                        match_number,
+                        replay_number,
+                    })
+                }
+                0x0e => TcpTag::GameData(GameData::new(packet.rest_string())),
+                id => {
+                    return Err(ReadError {
+                        offset: 0,
+                        kind: ReadErrorKind::UnknownTag(id),
+                    })
+                }
+            };
+            packet.finish()?;
+            Ok(tag)
+        }
+    }
+
+    /// Writes a string prefixed with its length as a [u8], cutting off anything past 255 bytes.
+    fn write_u8_string(buf: &mut Vec<u8>, string: &str) {
+        let bytes = string.as_bytes();
+        let bytes = &bytes[..bytes.len().min(u8::MAX as usize)];
+        buf.push(bytes.len() as u8);
+        buf.extend(bytes);
     }
 
+    #[derive(Clone, Debug, PartialEq)]
     pub struct JoystickDescriptor {
-        index: u8,
-        is_xbox: bool,
-        stick_type: JoystickType,
-        name: String,
-        axis_count: u8,
-        axis_types: Vec<AxisType>,
-        button_count: u8,
-        pov_count: u8,
+        pub index: u8,
+        pub is_xbox: bool,
+        pub stick_type: JoystickType,
+        pub name: String,
+        pub axis_types: Vec<AxisType>,
+        pub button_count: u8,
+        pub pov_count: u8,
     }
 
-    #[derive(Clone)]
+    impl Tag for JoystickDescriptor {
+        fn id(&self) -> u8 {
+            0x02
+        }
+
+        fn as_bytes(&self) -> Vec<u8> {
+            self.as_bytes_for(ProtocolVersion::CURRENT)
+        }
+
+        fn as_bytes_for(&self, version: ProtocolVersion) -> Vec<u8> {
+            let mut buf = Vec::new();
+            buf.push(self.index);
+            if version.has_xbox_flag() {
+                buf.push(self.is_xbox as u8);
+            }
+            buf.push(self.stick_type as i8 as u8);
+            write_u8_string(&mut buf, &self.name);
+            let axis_types = &self.axis_types[..self.axis_types.len().min(u8::MAX as usize)];
+            buf.push(axis_types.len() as u8);
+            buf.extend(axis_types.iter().map(|axis| *axis as u8));
+            buf.push(self.button_count);
+            buf.push(self.pov_count);
+
+            buf
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
     pub struct MatchInfo {
         pub competition: String,
         pub match_type: MatchType,
+        pub match_number: u16,
+        pub replay_number: u8,
     }
 
     impl Tag for MatchInfo {
@@ -62,14 +367,23 @@ pub mod tcp {
         }
 
         fn as_bytes(&self) -> Vec<u8> {
+            self.as_bytes_for(ProtocolVersion::CURRENT)
+        }
+
+        fn as_bytes_for(&self, version: ProtocolVersion) -> Vec<u8> {
             let mut buf = Vec::new();
-            buf.extend(self.competition.as_bytes());
+            write_u8_string(&mut buf, &self.competition);
             buf.push(self.match_type as u8);
+            if version.has_match_numbers() {
+                buf.write_u16::<NetworkEndian>(self.match_number).unwrap();
+                buf.push(self.replay_number);
+            }
 
             buf
         }
     }
 
+    #[derive(Clone, Debug, PartialEq)]
     pub struct GameData {
         data: String,
     }
@@ -78,6 +392,10 @@ pub mod tcp {
         pub fn new(data: String) -> Self {
             Self { data }
         }
+
+        pub fn data(&self) -> &str {
+            &self.data
+        }
     }
 
     impl Tag for GameData {
@@ -0,0 +1,301 @@
+//! Messages exchanged between a driver station and an FMS.
+//!
+//! The FMS sends a [FmsControlPacket] to each driver station over UDP and the driver station
+//! answers with a [DsStatusPacket]. Event and station information is sent over a TCP
+//! connection the driver station opens to the FMS, framed the same way as the robot's TCP tags.
+
+use chrono::prelude::*;
+
+use crate::messages::ds::udp::{read_date, write_date};
+use crate::packet::{PacketReader, PacketWriter, ReadError, ReadErrorKind};
+use crate::states::{Alliance, MatchType, RobotMode};
+
+// The ports are those documented at https://frcture.readthedocs.io/en/latest/driverstation/
+// (the "FMS to DS" and "DS to FMS" pages). Field port lists also mention 1120, but neither side
+// of this exchange uses it, and the TCP connection goes to 1750 rather than a UDP port number.
+
+/// The UDP port driver stations receive control packets on.
+pub const DS_CONTROL_PORT: u16 = 1121;
+/// The UDP port the FMS receives status packets on.
+pub const FMS_STATUS_PORT: u16 = 1160;
+/// The TCP port the FMS listens on.
+pub const FMS_TCP_PORT: u16 = 1750;
+
+/// Sent by the FMS to control a driver station and its robot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FmsControlPacket {
+    pub sequence_num: u16,
+    pub estop: bool,
+    pub enabled: bool,
+    pub mode: RobotMode,
+    pub alliance: Alliance,
+    pub match_type: MatchType,
+    pub match_number: u16,
+    pub replay_number: u8,
+    pub time: DateTime<Utc>,
+    /// Seconds left in the current match period.
+    pub remaining_seconds: u16,
+}
+
+impl FmsControlPacket {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = PacketWriter::new();
+        packet.write_u16(self.sequence_num);
+        packet.write_u8(0x00); // comm version
+
+        let mut control = self.mode as u8;
+        if self.enabled {
+            control |= 0x04;
+        }
+        if self.estop {
+            control |= 0x80;
+        }
+        packet.write_u8(control);
+        packet.write_u8(0x00); // unused
+        packet.write_u8(self.alliance.to_position_u8());
+        packet.write_u8(self.match_type as u8);
+        packet.write_u16(self.match_number);
+        packet.write_u8(self.replay_number);
+
+        // The date has the same layout as the one the driver station forwards to the robot,
+        // following C's `struct tm`, so the month counts from 0.
+        write_date(&mut packet, self.time);
+
+        packet.write_u16(self.remaining_seconds);
+        packet.into_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        let sequence_num = packet.next_u16()?;
+        packet.next_u8()?; // comm version
+        let control = packet.next_u8()?;
+        let mode =
+            RobotMode::from(control & 0b11).ok_or_else(|| packet.invalid(1, "robot mode"))?;
+        packet.next_u8()?; // unused
+        let alliance = Alliance::from_position_u8(packet.next_u8()?);
+        let match_type = packet.next_u8()?;
+        let match_type =
+            MatchType::from(match_type).ok_or_else(|| packet.invalid(1, "match type"))?;
+        let match_number = packet.next_u16()?;
+        let replay_number = packet.next_u8()?;
+
+        let time = read_date(&mut packet)?;
+        Ok(FmsControlPacket {
+            sequence_num,
+            estop: control & 0x80 != 0,
+            enabled: control & 0x04 != 0,
+            mode,
+            alliance,
+            match_type,
+            match_number,
+            replay_number,
+            time,
+            remaining_seconds: packet.next_u16()?,
+        })
+    }
+}
+
+bitflags! {
+    pub struct DsStatus: u8 {
+        const ESTOP = 0b1000_0000;
+        const ROBOT_COMMS = 0b0010_0000;
+        const RADIO_PING = 0b0001_0000;
+        const RIO_PING = 0b0000_1000;
+        const ENABLED = 0b0000_0100;
+
+        const TELEOP = 0b00;
+        const TEST = 0b01;
+        const AUTO = 0b10;
+    }
+}
+
+/// Connection quality between a driver station and its robot.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CommsMetrics {
+    pub lost_packets: u16,
+    pub sent_packets: u16,
+    pub trip_time_ms: u8,
+}
+
+/// Sent by a driver station to the FMS to report on itself and its robot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsStatusPacket {
+    pub sequence_num: u16,
+    pub status: DsStatus,
+    pub team: u16,
+    pub battery_voltage: f32,
+    pub comms: Option<CommsMetrics>,
+}
+
+impl DsStatusPacket {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = PacketWriter::new();
+        packet.write_u16(self.sequence_num);
+        packet.write_u8(0x00); // comm version
+        packet.write_u8(self.status.bits());
+        packet.write_u16(self.team);
+        packet.write_u8(self.battery_voltage.trunc() as u8);
+        packet.write_u8((self.battery_voltage.fract() * 256.0) as u8);
+
+        if let Some(ref comms) = self.comms {
+            packet.write_u8(6); // size
+            packet.write_u8(0x01); // id
+            packet.write_u16(comms.lost_packets);
+            packet.write_u16(comms.sent_packets);
+            packet.write_u8(comms.trip_time_ms);
+        }
+
+        packet.into_vec()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        let sequence_num = packet.next_u16()?;
+        packet.next_u8()?; // comm version
+        let status = DsStatus::from_bits_truncate(packet.next_u8()?);
+        let team = packet.next_u16()?;
+        let battery_voltage = f32::from(packet.next_u8()?) + f32::from(packet.next_u8()?) / 256.0;
+
+        let mut comms = None;
+        while !packet.is_empty() {
+            let (id, mut tag) = packet.next_tag()?;
+            if id == 0x01 {
+                comms = Some(CommsMetrics {
+                    lost_packets: tag.next_u16()?,
+                    sent_packets: tag.next_u16()?,
+                    trip_time_ms: tag.next_u8()?,
+                });
+            }
+        }
+
+        Ok(DsStatusPacket {
+            sequence_num,
+            status,
+            team,
+            battery_voltage,
+            comms,
+        })
+    }
+}
+
+/// Whether the driver station is in the station the FMS expects it in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StationStatus {
+    Good = 0,
+    Bad = 1,
+    Waiting = 2,
+}
+
+impl StationStatus {
+    pub fn from(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(StationStatus::Good),
+            1 => Some(StationStatus::Bad),
+            2 => Some(StationStatus::Waiting),
+            _ => None,
+        }
+    }
+}
+
+/// Tags sent over TCP from the FMS to a driver station.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FmsTcpTag {
+    EventCode(String), // 0x14
+    StationInfo {
+        // 0x19
+        alliance: Alliance,
+        status: StationStatus,
+    },
+    GameData(String), // 0x1c
+}
+
+impl FmsTcpTag {
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut tag = PacketWriter::new();
+        match self {
+            FmsTcpTag::EventCode(code) => {
+                tag.write_u8(0x14);
+                write_u8_string(&mut tag, code);
+            }
+            FmsTcpTag::StationInfo { alliance, status } => {
+                tag.write_u8(0x19);
+                tag.write_u8(alliance.to_position_u8());
+                tag.write_u8(*status as u8);
+            }
+            FmsTcpTag::GameData(data) => {
+                tag.write_u8(0x1c);
+                write_u8_string(&mut tag, data);
+            }
+        }
+        frame(tag)
+    }
+
+    /// Parses a tag from the bytes of a frame, not including its length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        match packet.next_u8()? {
+            0x14 => Ok(FmsTcpTag::EventCode(packet.next_u8_string()?)),
+            0x19 => {
+                let alliance = Alliance::from_position_u8(packet.next_u8()?);
+                let status = packet.next_u8()?;
+                Ok(FmsTcpTag::StationInfo {
+                    alliance,
+                    status: StationStatus::from(status)
+                        .ok_or_else(|| packet.invalid(1, "station status"))?,
+                })
+            }
+            0x1c => Ok(FmsTcpTag::GameData(packet.next_u8_string()?)),
+            id => Err(unknown_tag(id)),
+        }
+    }
+}
+
+/// Tags sent over TCP from a driver station to the FMS.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DsFmsTcpTag {
+    TeamNumber(u16), // 0x18
+}
+
+impl DsFmsTcpTag {
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut tag = PacketWriter::new();
+        match self {
+            DsFmsTcpTag::TeamNumber(team) => {
+                tag.write_u8(0x18);
+                tag.write_u16(*team);
+            }
+        }
+        frame(tag)
+    }
+
+    /// Parses a tag from the bytes of a frame, not including its length.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        match packet.next_u8()? {
+            0x18 => Ok(DsFmsTcpTag::TeamNumber(packet.next_u16()?)),
+            id => Err(unknown_tag(id)),
+        }
+    }
+}
+
+fn frame(tag: PacketWriter) -> Vec<u8> {
+    let mut packet = PacketWriter::new();
+    packet.write_u16(tag.len() as u16);
+    packet.append_packet(tag);
+    packet.into_vec()
+}
+
+fn write_u8_string(packet: &mut PacketWriter, string: &str) {
+    let bytes = string.as_bytes();
+    let bytes = &bytes[..bytes.len().min(u8::MAX as usize)];
+    packet.write_u8(bytes.len() as u8);
+    packet.write_slice(bytes);
+}
+
+fn unknown_tag(id: u8) -> ReadError {
+    ReadError {
+        offset: 0,
+        kind: ReadErrorKind::UnknownTag(id),
+    }
+}
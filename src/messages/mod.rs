@@ -1,2 +1,5 @@
 pub mod ds;
+pub mod fms;
 pub mod rio;
+
+pub use crate::packet::{ReadError, ReadErrorKind};
@@ -1,6 +1,10 @@
+use std::borrow::Cow;
 use std::convert::From;
+use std::fmt;
+use std::iter::FromIterator;
 
-use crate::packet::PacketReader;
+use crate::packet::{PacketReader, PacketWriter, ReadError, ReadErrorKind};
+use crate::pdp::PdpLog;
 use crate::states::RobotMode;
 
 bitflags! {
@@ -31,54 +35,186 @@ bitflags! {
 
 impl Status {
     pub fn robot_mode(&self) -> Option<RobotMode> {
-        return if self.contains(Status::AUTO) {
-            Some(RobotMode::Auto)
-        } else if self.contains(Status::TELEOP) {
-            Some(RobotMode::Teleop)
-        } else if self.contains(Status::TEST) {
-            Some(RobotMode::Test)
-        } else {
-            None
-        };
+        // TELEOP is all zeroes, so compare the mode bits rather than using contains.
+        RobotMode::from(self.bits() & 0b11)
     }
 }
 
-pub struct RioUdpPacket {
+#[derive(Clone, Debug, PartialEq)]
+pub struct RioUdpPacket<'a> {
     pub sequence_num: u16,
     pub comm_version: u8,
     pub status: Status,
     pub trace: Trace,
+    /// Sent as a whole number of volts and a number of 256ths, so anything finer is lost.
     pub battery_voltage: f32,
     pub request_date: bool,
-    pub tags: PacketReader, // TODO: parse tags from this Packet
+    pub tags: StatusTags<'a>,
 }
 
-impl RioUdpPacket {
-    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
-        if bytes.len() > 8 {
-            None
-        } else {
-            let mut packet = PacketReader::from_vec(bytes);
-            Some(RioUdpPacket {
-                sequence_num: packet.next_u16().unwrap(),
-                comm_version: packet.next_u8().unwrap(),
-                status: Status::from_bits(packet.next_u8().unwrap()).unwrap(),
-                trace: Trace::from_bits(packet.next_u8().unwrap()).unwrap(),
-                battery_voltage: f32::from(packet.next_u8().unwrap())
-                    + f32::from(packet.next_u8().unwrap()) / 256.0,
-                request_date: packet.next_u8().unwrap() == 0x01,
-                tags: packet,
-            })
+impl<'a> RioUdpPacket<'a> {
+    /// Parses a status packet. The tags borrow from `bytes` rather than being copied.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, ReadError> {
+        let mut packet = PacketReader::new(bytes);
+        let sequence_num = packet.next_u16()?;
+        let comm_version = packet.next_u8()?;
+        let status = Status::from_bits_truncate(packet.next_u8()?);
+        let trace = Trace::from_bits_truncate(packet.next_u8()?);
+        let battery_voltage = f32::from(packet.next_u8()?) + f32::from(packet.next_u8()?) / 256.0;
+        let request_date = packet.next_u8()? == 0x01;
+        let tags = StatusTags::read(&mut packet)?;
+
+        Ok(RioUdpPacket {
+            sequence_num,
+            comm_version,
+            status,
+            trace,
+            battery_voltage,
+            request_date,
+            tags,
+        })
+    }
+
+    /// Returns the contents of the first tag with `id`, if the robot sent one.
+    pub fn tag(&self, id: u8) -> Option<&[u8]> {
+        self.tags.get(id)
+    }
+
+    /// Returns the roboRIO's CPU usage from 0 to 1, averaged over its cores, from the CPU tag.
+    ///
+    /// The tag is the number of cores followed by four percentages for each, the time spent in
+    /// time critical, above normal, normal and low priority threads. Returns `Ok(None)` if there
+    /// is no CPU tag or it has no cores, and an error if it claims more cores than it has.
+    pub fn cpu_usage(&self) -> Result<Option<f32>, ReadError> {
+        let mut tag = match self.tag(0x05) {
+            Some(tag) => PacketReader::new(tag),
+            None => return Ok(None),
+        };
+        let count = tag.next_u8()?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let mut total = 0.0;
+        for _ in 0..usize::from(count) * 4 {
+            total += tag.next_f32()?;
         }
+        Ok(Some(total / f32::from(count) / 100.0))
+    }
+
+    /// Returns what the power distribution panel reported, from the PDP tag.
+    pub fn pdp(&self) -> Option<PdpLog> {
+        PdpLog::from_tag(self.tag(0x08)?).ok()
+    }
+
+    /// Returns the CAN bus utilization from 0 to 1, from the CAN tag.
+    pub fn can_utilization(&self) -> Option<f32> {
+        let mut tag = PacketReader::new(self.tag(0x0e)?);
+        tag.next_f32().ok().map(|percent| percent / 100.0)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = PacketWriter::new();
+        packet.write_u16(self.sequence_num);
+        packet.write_u8(self.comm_version);
+        packet.write_u8(self.status.bits());
+        packet.write_u8(self.trace.bits());
+        packet.write_u8(self.battery_voltage.trunc() as u8);
+        packet.write_u8((self.battery_voltage.fract() * 256.0) as u8);
+        packet.write_u8(if self.request_date { 0x01 } else { 0x00 });
+        packet.write_slice(self.tags.as_bytes());
+        packet.into_vec()
     }
 }
 
+/// The tags at the end of a [RioUdpPacket], kept as the length prefixed tags they are sent as.
+///
+/// Decoded tags borrow the received packet, so reading one copies nothing. Tags built with
+/// [push](StatusTags::push) or [collect](Iterator::collect) own their bytes.
+#[derive(Clone, Default, PartialEq)]
+pub struct StatusTags<'a>(Cow<'a, [u8]>);
+
+impl<'a> StatusTags<'a> {
+    pub fn new() -> Self {
+        StatusTags::default()
+    }
+
+    /// Reads every remaining tag in `packet`, checking that each one fits.
+    fn read(packet: &mut PacketReader<'a>) -> Result<Self, ReadError> {
+        let mut tags = packet.clone();
+        while !tags.is_empty() {
+            tags.next_tag()?;
+        }
+        Ok(StatusTags(Cow::Borrowed(packet.rest())))
+    }
+
+    /// Appends a tag. `contents` must be shorter than 255 bytes to fit the size prefix.
+    pub fn push(&mut self, id: u8, contents: &[u8]) {
+        assert!(contents.len() < 255, "status tag too long");
+        let bytes = self.0.to_mut();
+        bytes.push(contents.len() as u8 + 1);
+        bytes.push(id);
+        bytes.extend_from_slice(contents);
+    }
+
+    /// Returns every tag, as its id and contents, in the order they were sent.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        let mut tags = PacketReader::new(&self.0);
+        // Every tag was checked when these were read or pushed, so this can't fail part way.
+        std::iter::from_fn(move || {
+            let (id, mut tag) = tags.next_tag().ok()?;
+            Some((id, tag.rest()))
+        })
+    }
+
+    /// Returns the contents of the first tag with `id`.
+    pub fn get(&self, id: u8) -> Option<&[u8]> {
+        self.iter()
+            .find(|(tag_id, _)| *tag_id == id)
+            .map(|(_, contents)| contents)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the tags as they are sent, each prefixed with its size.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Copies borrowed tags so that they can outlive the packet they were read from.
+    pub fn into_owned(self) -> StatusTags<'static> {
+        StatusTags(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl<'a, 'b> FromIterator<(u8, &'b [u8])> for StatusTags<'a> {
+    fn from_iter<I: IntoIterator<Item = (u8, &'b [u8])>>(iter: I) -> Self {
+        let mut tags = StatusTags::new();
+        for (id, contents) in iter {
+            tags.push(id, contents);
+        }
+        tags
+    }
+}
+
+impl<'a> fmt::Debug for StatusTags<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum RioTcpPacket {
     RadioEvent(String), // 0x00
     UsageReport {
         // Ignoring this for now, apparently just forwarded to fms anyway.
         // 0x01
-        team_num: String,
+        team_num: u16,
         unknown: u8,
         entries: Vec<u8>,
     },
@@ -122,90 +258,169 @@ pub enum RioTcpPacket {
 }
 
 impl RioTcpPacket {
-    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
-        if bytes.len() == 0 {
-            None
-        } else {
-            use self::RioTcpPacket::*;
-            let mut packet = PacketReader::from_vec(bytes);
-            match packet.next_u8().unwrap() {
-                0x00 => Some(RadioEvent({
-                    let size = packet.len();
-                    packet.extract_string(size).unwrap()
-                })),
-                0x04 => {
-                    if packet.len() != 4 {
-                        None
-                    } else {
-                        Some(DisableFaults {
-                            comms: packet.next_u16().unwrap(),
-                            twelve_v: packet.next_u16().unwrap(),
-                        })
-                    }
-                }
-                0x05 => {
-                    if packet.len() != 6 {
-                        None
-                    } else {
-                        Some(RailFaults {
-                            six_v: packet.next_u16().unwrap(),
-                            five_v: packet.next_u16().unwrap(),
-                            three_point_three_v: packet.next_u16().unwrap(),
-                        })
-                    }
-                }
-                0x0a => {
-                    if packet.len() < 5 {
-                        None
-                    } else {
-                        None // TODO
-                    }
+    /// Parses a tag from its id and contents, without the length prefix.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReadError> {
+        use self::RioTcpPacket::*;
+        let mut packet = PacketReader::new(bytes);
+        let tag = match packet.next_u8()? {
+            0x00 => RadioEvent(packet.rest_string()),
+            0x01 => UsageReport {
+                team_num: packet.next_u16()?,
+                unknown: packet.next_u8()?,
+                entries: packet.rest().to_vec(),
+            },
+            0x04 => DisableFaults {
+                comms: packet.next_u16()?,
+                twelve_v: packet.next_u16()?,
+            },
+            0x05 => RailFaults {
+                six_v: packet.next_u16()?,
+                five_v: packet.next_u16()?,
+                three_point_three_v: packet.next_u16()?,
+            },
+            0x0a => {
+                let device_type = packet.next_u8()?;
+                VersionInfo {
+                    device_type: DeviceType::from(device_type)
+                        .ok_or_else(|| packet.invalid(1, "device type"))?,
+                    unknown: packet.next_u16()?,
+                    id: packet.next_u8()?,
+                    name: packet.next_u8_string()?,
+                    version: packet.next_u8_string()?,
                 }
-                0x0b => {
-                    if packet.len() < 16 {
-                        None
-                    } else {
-                        Some(ErrorMessage {
-                            timestamp: packet.next_f32().unwrap(),
-                            sequence_number: packet.next_u16().unwrap(),
-                            print_msg: packet.next_u8().unwrap() == 0x01,
-                            error_code: packet.next_u16().unwrap(),
-                            is_error: packet.next_u8().unwrap() != 0,
-                            details: {
-                                let size = packet.next_u16().unwrap() as usize;
-                                packet.extract_string(size).unwrap()
-                            },
-                            location: {
-                                let size = packet.next_u16().unwrap() as usize;
-                                packet.extract_string(size).unwrap()
-                            },
-                            call_stack: {
-                                let size = packet.next_u16().unwrap() as usize;
-                                packet.extract_string(size).unwrap()
-                            },
-                        })
-                    }
+            }
+            0x0b => ErrorMessage {
+                timestamp: packet.next_f32()?,
+                sequence_number: packet.next_u16()?,
+                print_msg: packet.next_u8()? == 0x01,
+                error_code: packet.next_u16()?,
+                is_error: packet.next_u8()? != 0,
+                details: packet.next_u16_string()?,
+                location: packet.next_u16_string()?,
+                call_stack: packet.next_u16_string()?,
+            },
+            0x0c => StandardOutput {
+                timestamp: packet.next_f32()?,
+                sequence_number: packet.next_u16()?,
+                message: packet.rest_string(),
+            },
+            0x0d => Unknown(packet.rest().to_vec()),
+            id => {
+                return Err(ReadError {
+                    offset: 0,
+                    kind: ReadErrorKind::UnknownTag(id),
+                })
+            }
+        };
+        packet.finish()?;
+        Ok(tag)
+    }
+}
+
+impl RioTcpPacket {
+    /// Returns the tag with its id and length prefix, ready to be written to the stream.
+    pub fn to_packet(&self) -> Vec<u8> {
+        use self::RioTcpPacket::*;
+        let mut tag = PacketWriter::new();
+        match self {
+            RadioEvent(message) => {
+                tag.write_u8(0x00);
+                tag.write_slice(message.as_bytes());
+            }
+            UsageReport {
+                team_num,
+                unknown,
+                entries,
+            } => {
+                tag.write_u8(0x01);
+                tag.write_u16(*team_num);
+                tag.write_u8(*unknown);
+                tag.write_slice(entries);
+            }
+            DisableFaults { comms, twelve_v } => {
+                tag.write_u8(0x04);
+                tag.write_u16(*comms);
+                tag.write_u16(*twelve_v);
+            }
+            RailFaults {
+                six_v,
+                five_v,
+                three_point_three_v,
+            } => {
+                tag.write_u8(0x05);
+                tag.write_u16(*six_v);
+                tag.write_u16(*five_v);
+                tag.write_u16(*three_point_three_v);
+            }
+            VersionInfo {
+                device_type,
+                unknown,
+                id,
+                name,
+                version,
+            } => {
+                tag.write_u8(0x0a);
+                tag.write_u8(*device_type as u8);
+                tag.write_u16(*unknown);
+                tag.write_u8(*id);
+                for string in &[name, version] {
+                    let bytes = &string.as_bytes()[..string.len().min(u8::MAX as usize)];
+                    tag.write_u8(bytes.len() as u8);
+                    tag.write_slice(bytes);
                 }
-                0x0c => {
-                    if packet.len() < 6 {
-                        None
-                    } else {
-                        Some(StandardOutput {
-                            timestamp: packet.next_f32().unwrap(),
-                            sequence_number: packet.next_u16().unwrap(),
-                            message: {
-                                let size = packet.len();
-                                packet.extract_string(size).unwrap()
-                            },
-                        })
-                    }
+            }
+            ErrorMessage {
+                timestamp,
+                sequence_number,
+                print_msg,
+                error_code,
+                is_error,
+                details,
+                location,
+                call_stack,
+            } => {
+                tag.write_u8(0x0b);
+                tag.write_f32(*timestamp);
+                tag.write_u16(*sequence_number);
+                tag.write_u8(*print_msg as u8);
+                tag.write_u16(*error_code);
+                tag.write_u8(*is_error as u8);
+                // The strings share the frame with each other and the 17 bytes around them.
+                let mut room = u16::MAX as usize - 17;
+                for string in &[details, location, call_stack] {
+                    let bytes = &string.as_bytes()[..string.len().min(room)];
+                    room -= bytes.len();
+                    tag.write_u16(bytes.len() as u16);
+                    tag.write_slice(bytes);
                 }
-                _ => None,
+            }
+            StandardOutput {
+                timestamp,
+                sequence_number,
+                message,
+            } => {
+                tag.write_u8(0x0c);
+                tag.write_f32(*timestamp);
+                tag.write_u16(*sequence_number);
+                tag.write_slice(message.as_bytes());
+            }
+            Unknown(bytes) => {
+                tag.write_u8(0x0d);
+                tag.write_slice(bytes);
             }
         }
+
+        // Whatever doesn't fit in a frame is cut off, so the length always matches.
+        let mut tag = tag.into_vec();
+        tag.truncate(u16::MAX as usize);
+        let mut packet = PacketWriter::new();
+        packet.write_u16(tag.len() as u16);
+        packet.write_slice(&tag);
+        packet.into_vec()
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DeviceType {
     Software = 0,
     CANTalon = 2,
@@ -213,7 +428,19 @@ pub enum DeviceType {
     PCM = 9,
 }
 
-pub enum RioPacket {
-    Udp(RioUdpPacket),
+impl DeviceType {
+    pub fn from(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(DeviceType::Software),
+            2 => Some(DeviceType::CANTalon),
+            8 => Some(DeviceType::PDP),
+            9 => Some(DeviceType::PCM),
+            _ => None,
+        }
+    }
+}
+
+pub enum RioPacket<'a> {
+    Udp(RioUdpPacket<'a>),
     Tcp(RioTcpPacket),
 }
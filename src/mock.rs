@@ -0,0 +1,151 @@
+//! A fake roboRIO for testing code that drives a [DriverStation](crate::DriverStation).
+//!
+//! [MockRio] records every control packet and TCP tag the driver station sends, and answers
+//! with whatever status and messages the test scripts.
+
+use std::collections::VecDeque;
+use std::io;
+
+use crate::messages::ds::udp::DsUdpPacket;
+use crate::messages::rio::{RioTcpPacket, RioUdpPacket, Status, Trace};
+use crate::server::{Handler, RobotServer};
+use crate::version::ProtocolVersion;
+
+/// The ports a [MockRio] listens and replies on.
+#[derive(Clone, Debug)]
+pub struct MockRioConfig {
+    pub udp_port: u16,
+    pub tcp_port: u16,
+    /// The port status packets are sent to on the driver station, or [None] to reply to the port
+    /// control packets come from.
+    pub ds_port: Option<u16>,
+}
+
+impl Default for MockRioConfig {
+    fn default() -> Self {
+        MockRioConfig {
+            udp_port: 1110,
+            tcp_port: 1740,
+            ds_port: Some(1150),
+        }
+    }
+}
+
+/// What the mock robot reports about itself in each status packet.
+#[derive(Clone, Debug)]
+pub struct RobotStatus {
+    pub status: Status,
+    pub trace: Trace,
+    pub battery_voltage: f32,
+    pub request_date: bool,
+    /// The comm version byte, which tells the driver station which protocol version to speak.
+    pub comm_version: u8,
+    /// The status tags, as ids and their contents.
+    pub tags: Vec<(u8, Vec<u8>)>,
+}
+
+impl Default for RobotStatus {
+    fn default() -> Self {
+        RobotStatus {
+            status: Status::empty(),
+            trace: Trace::ROBOT_CODE | Trace::IS_ROBORIO,
+            battery_voltage: 12.0,
+            request_date: false,
+            comm_version: ProtocolVersion::CURRENT.comm_version(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    status: RobotStatus,
+    control_packets: Vec<DsUdpPacket>,
+    tcp_tags: Vec<Vec<u8>>,
+    outgoing: VecDeque<Vec<u8>>,
+}
+
+impl Handler for Shared {
+    fn on_control(&mut self, packet: DsUdpPacket) -> RioUdpPacket<'static> {
+        let status = &self.status;
+        let reply = RioUdpPacket {
+            sequence_num: packet.sequence_num,
+            comm_version: status.comm_version,
+            status: status.status,
+            trace: status.trace,
+            battery_voltage: status.battery_voltage,
+            request_date: status.request_date,
+            tags: status
+                .tags
+                .iter()
+                .map(|(id, contents)| (*id, contents.as_slice()))
+                .collect(),
+        };
+        self.control_packets.push(packet);
+        reply
+    }
+
+    fn on_tcp(&mut self, frames: Vec<Vec<u8>>) {
+        self.tcp_tags.extend(frames);
+    }
+
+    fn outgoing(&mut self) -> &mut VecDeque<Vec<u8>> {
+        &mut self.outgoing
+    }
+}
+
+/// A fake roboRIO, which runs until it's dropped.
+pub struct MockRio {
+    server: RobotServer<Shared>,
+}
+
+impl MockRio {
+    pub fn start(config: MockRioConfig) -> io::Result<Self> {
+        let server = RobotServer::start(
+            config.udp_port,
+            config.tcp_port,
+            config.ds_port,
+            Shared::default(),
+        )?;
+        Ok(MockRio { server })
+    }
+
+    pub fn udp_port(&self) -> u16 {
+        self.server.udp_port()
+    }
+
+    pub fn tcp_port(&self) -> u16 {
+        self.server.tcp_port()
+    }
+
+    /// Sets what is reported in reply to every following control packet.
+    pub fn set_status(&self, status: RobotStatus) {
+        self.server.lock().status = status;
+    }
+
+    /// Queues a message to be sent to the driver station once it's connected over TCP.
+    pub fn send_tcp(&self, packet: &RioTcpPacket) {
+        self.server.lock().outgoing.push_back(packet.to_packet());
+    }
+
+    /// Returns every control packet received so far, in order.
+    pub fn control_packets(&self) -> Vec<DsUdpPacket> {
+        self.server.lock().control_packets.clone()
+    }
+
+    pub fn last_control_packet(&self) -> Option<DsUdpPacket> {
+        self.server.lock().control_packets.last().cloned()
+    }
+
+    /// Returns every TCP tag received so far, as its id followed by its contents.
+    pub fn tcp_tags(&self) -> Vec<Vec<u8>> {
+        self.server.lock().tcp_tags.clone()
+    }
+
+    /// Forgets everything received so far.
+    pub fn clear(&self) {
+        let mut shared = self.server.lock();
+        shared.control_packets.clear();
+        shared.tcp_tags.clear();
+    }
+}
@@ -0,0 +1,104 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::str;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::console::{ConsoleMessage, ConsoleSource, Severity};
+use crate::ds::DriverStationState;
+
+/// The longest line kept while waiting for its line break. Anything longer is delivered in
+/// pieces of this size, so a robot that never prints a line break can't use up memory.
+const MAX_LINE: usize = 4096;
+
+/// Receives the lines robot code prints over netconsole, which arrive as UDP datagrams that
+/// don't necessarily end at line breaks.
+pub struct NetConsole {
+    thread: Option<JoinHandle<()>>,
+    stop: mpsc::Sender<()>,
+    port: u16,
+}
+
+impl NetConsole {
+    pub fn new(port: u16, state: Arc<Mutex<DriverStationState>>) -> io::Result<Self> {
+        let udp = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), port))?;
+        udp.set_read_timeout(Some(Duration::from_millis(20)))?;
+        let port = udp.local_addr()?.port();
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let t = thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            // Bytes rather than text, since a character can be split between datagrams.
+            let mut partial = Vec::new();
+            while let Err(mpsc::TryRecvError::Empty) = stopped.try_recv() {
+                if let Ok((n, _)) = udp.recv_from(&mut buf) {
+                    partial.extend_from_slice(&buf[..n]);
+                }
+
+                let mut lines = Vec::new();
+                while let Some(end) = partial.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = partial.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    lines.push(line.trim_end_matches(&['\r', '\n'][..]).to_owned());
+                }
+                while partial.len() >= MAX_LINE {
+                    let end = char_boundary(&partial[..MAX_LINE]);
+                    let piece: Vec<u8> = partial.drain(..end).collect();
+                    lines.push(String::from_utf8_lossy(&piece).into_owned());
+                }
+
+                let mut state = state.lock().unwrap();
+                // Repeats are delivered here too, for when there's no connection to the robot.
+                state.console.deliver_repeats(Instant::now());
+                if !lines.is_empty() {
+                    let now = state.wall_clock.now_utc();
+                    let messages = lines.into_iter().map(|text| ConsoleMessage {
+                        severity: Severity::Print,
+                        source: ConsoleSource::NetConsole,
+                        time: now,
+                        robot_time: 0.0,
+                        sequence_number: 0,
+                        error_code: None,
+                        text,
+                        location: String::new(),
+                        call_stack: String::new(),
+                        count: 1,
+                    });
+                    state.console.deliver_unsequenced(messages);
+                }
+            }
+        });
+
+        Ok(NetConsole {
+            thread: Some(t),
+            stop,
+            port,
+        })
+    }
+
+    /// Returns the port being listened on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Returns where to split `bytes` so a character that doesn't fit isn't cut in two.
+fn char_boundary(bytes: &[u8]) -> usize {
+    match str::from_utf8(bytes) {
+        // The error has no length when the bytes end partway through a character.
+        Err(e) if e.error_len().is_none() && e.valid_up_to() > 0 => e.valid_up_to(),
+        _ => bytes.len(),
+    }
+}
+
+impl Drop for NetConsole {
+    fn drop(&mut self) {
+        self.stop.send(()).unwrap_or(());
+        // Waiting for the thread frees the port before the listener is replaced.
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
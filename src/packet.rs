@@ -1,92 +1,202 @@
-use byteorder::{ByteOrder, NetworkEndian, ReadBytesExt, WriteBytesExt};
-use std::collections::VecDeque;
+use byteorder::{ByteOrder, NetworkEndian, WriteBytesExt};
+use std::error::Error;
+use std::fmt;
 use std::io::{Cursor, Write};
 
-/// [PacketReader] represents a recieved data packet and allows for extracting components in order.
-pub struct PacketReader(VecDeque<u8>);
+/// Why a packet could not be decoded, and where in it the problem was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadError {
+    /// The offset from the start of the packet, in bytes.
+    pub offset: usize,
+    pub kind: ReadErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadErrorKind {
+    /// The packet ended before a field that needed this many bytes.
+    UnexpectedEnd { needed: usize },
+    /// A field held a value it can't take, such as an unknown enum variant.
+    Invalid(&'static str),
+    /// A tag had an id that isn't known.
+    UnknownTag(u8),
+    /// A fixed size field or tag was followed by this many bytes that weren't read.
+    TrailingBytes(usize),
+}
 
-impl PacketReader {
-    /// Creates a [Packet] from the given [Vec<u8>].
-    pub fn from_vec(vec: Vec<u8>) -> Self {
-        PacketReader(VecDeque::from(vec))
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ReadErrorKind::UnexpectedEnd { needed } => write!(
+                f,
+                "packet ended at byte {} with {} more bytes needed",
+                self.offset, needed
+            ),
+            ReadErrorKind::Invalid(what) => write!(f, "invalid {} at byte {}", what, self.offset),
+            ReadErrorKind::UnknownTag(id) => {
+                write!(f, "unknown tag {:#04x} at byte {}", id, self.offset)
+            }
+            ReadErrorKind::TrailingBytes(count) => {
+                write!(f, "{} unexpected bytes at byte {}", count, self.offset)
+            }
+        }
     }
+}
 
-    /// Returns a [Vec] of the remaining bytes.
-    pub fn into_vec(self) -> Vec<u8> {
-        Vec::from(self.0)
+impl Error for ReadError {}
+
+/// [PacketReader] is a cursor over a received packet which allows for extracting components in
+/// order without copying.
+///
+/// Every read is bounds checked and fails with the offset of the field that could not be read,
+/// counted from the start of the outermost packet.
+#[derive(Clone, Debug)]
+pub struct PacketReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// The offset of `buf` in the packet it came from, for sub-readers.
+    base: usize,
+}
+
+macro_rules! next_number {
+    ($(#[$doc:meta] $name:ident -> $ty:ty, $read:expr;)*) => {
+        $(
+            #[$doc]
+            pub fn $name(&mut self) -> Result<$ty, ReadError> {
+                let bytes = self.next_bytes(::std::mem::size_of::<$ty>())?;
+                Ok($read(bytes))
+            }
+        )*
+    };
+}
+
+impl<'a> PacketReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        PacketReader {
+            buf,
+            pos: 0,
+            base: 0,
+        }
+    }
+
+    /// Returns the offset of the next byte from the start of the packet.
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
     }
 
-    /// Returns the number of bytes left in the [Packet].
+    /// Returns the number of bytes left to read.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.buf.len() - self.pos
     }
 
-    /// Returns an [Option] containing the next byte if there is one or [None] otherwise.
-    pub fn next_u8(&mut self) -> Option<u8> {
-        self.0.pop_front()
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    /// Parses and returns the next two bytes as a [u16] if they are present, otherwaise returns [None].
-    pub fn next_u16(&mut self) -> Option<u16> {
-        if self.0.len() < 2 {
-            None
-        } else {
-            Some(NetworkEndian::read_u16(&[
-                self.next_u8().unwrap(),
-                self.next_u8().unwrap(),
-            ]))
+    /// Returns an error of the given kind at the current offset.
+    pub fn error(&self, kind: ReadErrorKind) -> ReadError {
+        ReadError {
+            offset: self.offset(),
+            kind,
         }
     }
 
-    /// Parses and returns the next four bytes as a [f32] if they are present, otherwaise returns [None].
-    pub fn next_f32(&mut self) -> Option<f32> {
-        if self.0.len() < 4 {
-            None
-        } else {
-            Some(NetworkEndian::read_f32(&[
-                self.next_u8().unwrap(),
-                self.next_u8().unwrap(),
-                self.next_u8().unwrap(),
-                self.next_u8().unwrap(),
-            ]))
+    /// Returns an error for an invalid value in the field that was just read, which is `size`
+    /// bytes long.
+    pub fn invalid(&self, size: usize, what: &'static str) -> ReadError {
+        ReadError {
+            offset: self.offset() - size,
+            kind: ReadErrorKind::Invalid(what),
         }
     }
 
-    /// Creates a string from the next [size] bytes of the packet.
-    ///
-    /// Returns an Option containing the string or None if the [Packet] is not long enough.
-    pub fn extract_string(&mut self, size: usize) -> Option<String> {
-        if self.len() < 2 {
-            None
+    /// Fails if anything is left to read, for when the whole buffer should have been consumed.
+    pub fn finish(&self) -> Result<(), ReadError> {
+        if self.is_empty() {
+            Ok(())
         } else {
-            let mut vec = Vec::new();
-            for i in 0..size {
-                vec.push(self.next_u8().unwrap());
-            }
-            Some(String::from_utf8_lossy(vec.as_ref()).to_string())
+            Err(self.error(ReadErrorKind::TrailingBytes(self.len())))
         }
     }
-}
 
-/// Extracts a string from a [VecDeque<u8>] where the first two bytes are a [u16] representing the length of the string.
-/// That many bytes will be used to construct the string, being removed from the [VecDeque].
-///
-/// Returns an Option containing the string or None if the VecDeque was not long enough.
-pub fn extract_string_u16_size(bytes: &mut VecDeque<u8>) -> Option<String> {
-    if bytes.len() < 2 {
-        None
-    } else {
-        let size =
-            NetworkEndian::read_u16(&[bytes.pop_front().unwrap(), bytes.pop_front().unwrap()]);
-        if bytes.len() < size as usize {
-            None
-        } else {
-            let mut vec = Vec::new();
-            for i in 0..size {
-                vec.push(bytes.pop_front().unwrap());
-            }
-            Some(String::from_utf8_lossy(vec.as_ref()).to_string())
+    /// Returns the next `size` bytes.
+    pub fn next_bytes(&mut self, size: usize) -> Result<&'a [u8], ReadError> {
+        if self.len() < size {
+            return Err(self.error(ReadErrorKind::UnexpectedEnd { needed: size }));
+        }
+        let bytes = &self.buf[self.pos..self.pos + size];
+        self.pos += size;
+        Ok(bytes)
+    }
+
+    /// Returns every remaining byte.
+    pub fn rest(&mut self) -> &'a [u8] {
+        let bytes = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        bytes
+    }
+
+    next_number! {
+        /// Reads a [u8].
+        next_u8 -> u8, |b: &[u8]| b[0];
+        /// Reads an [i8].
+        next_i8 -> i8, |b: &[u8]| b[0] as i8;
+        /// Reads a big endian [u16].
+        next_u16 -> u16, NetworkEndian::read_u16;
+        /// Reads a big endian [i16].
+        next_i16 -> i16, NetworkEndian::read_i16;
+        /// Reads a big endian [u32].
+        next_u32 -> u32, NetworkEndian::read_u32;
+        /// Reads a big endian [i32].
+        next_i32 -> i32, NetworkEndian::read_i32;
+        /// Reads a big endian [u64].
+        next_u64 -> u64, NetworkEndian::read_u64;
+        /// Reads a big endian [i64].
+        next_i64 -> i64, NetworkEndian::read_i64;
+        /// Reads a big endian [f32].
+        next_f32 -> f32, NetworkEndian::read_f32;
+    }
+
+    /// Reads `size` bytes as a string, replacing anything that isn't valid UTF-8.
+    pub fn next_string(&mut self, size: usize) -> Result<String, ReadError> {
+        Ok(String::from_utf8_lossy(self.next_bytes(size)?).into_owned())
+    }
+
+    /// Reads a string prefixed with its length as a [u8].
+    pub fn next_u8_string(&mut self) -> Result<String, ReadError> {
+        let size = self.next_u8()?;
+        self.next_string(size as usize)
+    }
+
+    /// Reads a string prefixed with its length as a [u16].
+    pub fn next_u16_string(&mut self) -> Result<String, ReadError> {
+        let size = self.next_u16()?;
+        self.next_string(size as usize)
+    }
+
+    /// Reads every remaining byte as a string.
+    pub fn rest_string(&mut self) -> String {
+        String::from_utf8_lossy(self.rest()).into_owned()
+    }
+
+    /// Returns a reader over the next `size` bytes, which reports offsets in this packet.
+    pub fn sub_reader(&mut self, size: usize) -> Result<PacketReader<'a>, ReadError> {
+        let base = self.offset();
+        Ok(PacketReader {
+            buf: self.next_bytes(size)?,
+            pos: 0,
+            base,
+        })
+    }
+
+    /// Reads a UDP tag, which is prefixed by a [u8] size that includes the id, and returns its id
+    /// and a reader over its contents.
+    pub fn next_tag(&mut self) -> Result<(u8, PacketReader<'a>), ReadError> {
+        let size = self.next_u8()?;
+        if size == 0 {
+            return Err(self.invalid(1, "tag size"));
         }
+        let id = self.next_u8()?;
+        Ok((id, self.sub_reader(size as usize - 1)?))
     }
 }
 
@@ -99,6 +209,13 @@ impl PacketWriter {
         PacketWriter(Cursor::new(Vec::new()))
     }
 
+    /// Creates a [PacketWriter] that writes into `vec`, clearing it but keeping its capacity so
+    /// that a buffer can be reused between packets.
+    pub fn from_vec(mut vec: Vec<u8>) -> Self {
+        vec.clear();
+        PacketWriter(Cursor::new(vec))
+    }
+
     /// Returns a [Vec<u8>] representing the packet.
     pub fn into_vec(self) -> Vec<u8> {
         self.0.into_inner()
@@ -109,14 +226,19 @@ impl PacketWriter {
         self.0.get_ref().len()
     }
 
+    /// Overwrites the byte at `index`, which must already have been written.
+    pub fn set_u8(&mut self, index: usize, val: u8) {
+        self.0.get_mut()[index] = val;
+    }
+
     /// Writes one [u8] to the end of the packet.
     pub fn write_u8(&mut self, val: u8) {
-        self.0.write(&[val.to_be()]).unwrap();
+        self.0.write_all(&[val.to_be()]).unwrap();
     }
 
     /// Writes one [i8] to the end of the packet.
     pub fn write_i8(&mut self, val: i8) {
-        self.0.write(&[val.to_be() as u8]).unwrap();
+        self.0.write_all(&[val.to_be() as u8]).unwrap();
     }
 
     /// Writes one [u16] to the end of the packet.
@@ -124,35 +246,49 @@ impl PacketWriter {
         self.0.write_u16::<NetworkEndian>(val).unwrap();
     }
 
-    /// Writes one [i16] to the end of the packet.
-    pub fn write_i16(&mut self, val: i16) {
-        self.0.write_i16::<NetworkEndian>(val).unwrap();
-    }
-
     /// Writes one [u32] to the end of the packet.
     pub fn write_u32(&mut self, val: u32) {
         self.0.write_u32::<NetworkEndian>(val).unwrap();
     }
 
+    /// Writes one [i32] to the end of the packet.
+    pub fn write_i32(&mut self, val: i32) {
+        self.0.write_i32::<NetworkEndian>(val).unwrap();
+    }
+
+    /// Writes one [i64] to the end of the packet.
+    pub fn write_i64(&mut self, val: i64) {
+        self.0.write_i64::<NetworkEndian>(val).unwrap();
+    }
+
+    /// Writes one [u64] to the end of the packet.
+    pub fn write_u64(&mut self, val: u64) {
+        self.0.write_u64::<NetworkEndian>(val).unwrap();
+    }
+
     /// Writes one [f32] to the end of the packet.
     pub fn write_f32(&mut self, val: f32) {
         self.0.write_f32::<NetworkEndian>(val).unwrap();
     }
 
-    /// Writes a [String] to the end of the packet.
-    pub fn write_string(&mut self, val: String) {
-        self.0.write(val.as_bytes()).unwrap();
+    /// Writes a UDP tag with the given id, whose contents are written by `contents`, and fills
+    /// in its size afterwards. Contents past the 254 bytes a tag can hold are cut off.
+    pub fn write_tag<F: FnOnce(&mut PacketWriter)>(&mut self, id: u8, contents: F) {
+        let start = self.len();
+        self.write_u8(0);
+        self.write_u8(id);
+        contents(self);
+        let end = self.len().min(start + 1 + u8::MAX as usize);
+        self.0.get_mut().truncate(end);
+        self.0.set_position(end as u64);
+        self.set_u8(start, (end - start - 1) as u8);
     }
 
     pub fn append_packet(&mut self, other: PacketWriter) {
-        self.0.write(other.into_vec().as_ref()).unwrap();
+        self.0.write_all(other.into_vec().as_ref()).unwrap();
     }
 
     pub fn write_slice(&mut self, slice: &[u8]) {
-        self.0.write(slice).unwrap();
-    }
-
-    pub fn write_vec(&mut self, vec: Vec<u8>) {
-        self.write_slice(vec.as_ref());
+        self.0.write_all(slice).unwrap();
     }
 }
@@ -0,0 +1,234 @@
+//! Power distribution readings over time, for spotting motors that draw more current than they
+//! should or channels that stop drawing any.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+
+use crate::packet::{PacketReader, PacketWriter, ReadError};
+
+/// The size of a PDP status tag.
+pub(crate) const PDP_SIZE: usize = 25;
+
+/// The size of a REV hub's readings in a version 4 `.dslog` record, after the section header: 20
+/// currents packed three to a 32 bit block, four low current channels a byte each, and a byte
+/// that isn't decoded.
+const REV_SIZE: usize = 27 + 4 + 1;
+
+/// Reads a 10 bit number starting `offset` bits into `bytes`, most significant bit first.
+fn read_10_bits(bytes: &[u8], offset: usize) -> u16 {
+    (offset..offset + 10).fold(0, |value, index| {
+        value << 1 | u16::from(bytes[index / 8] >> (7 - index % 8) & 1)
+    })
+}
+
+/// Writes the low 10 bits of `value` starting `offset` bits into `bytes`.
+fn write_10_bits(bytes: &mut [u8], offset: usize, value: u16) {
+    for bit in 0..10 {
+        if value & (1 << (9 - bit)) != 0 {
+            let index = offset + bit;
+            bytes[index / 8] |= 0x80 >> (index % 8);
+        }
+    }
+}
+
+/// What the power distribution panel reported in the robot's PDP status tag, which is also how
+/// `.dslog` records store it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PdpLog {
+    /// The panel's CAN id.
+    pub id: u8,
+    /// The current on each channel in amps, stored in eighths of an amp up to 127.875.
+    pub currents: [f32; 16],
+    /// The raw resistance, voltage and temperature bytes as the panel reports them over CAN.
+    /// Use [bus_voltage](Self::bus_voltage) and [temperature](Self::temperature) for them in
+    /// volts and degrees.
+    pub resistance: u8,
+    pub voltage: u8,
+    pub temperature: u8,
+}
+
+impl PdpLog {
+    /// Returns the offset in bits of a channel's current from the start of the section. The
+    /// currents are 10 bit numbers packed into three 64 bit blocks of six, the last one only
+    /// partly used, after the id.
+    fn current_offset(channel: usize) -> usize {
+        8 + 64 * (channel / 6) + 10 * (channel % 6)
+    }
+
+    /// Decodes the contents of the robot's PDP status tag.
+    pub fn from_tag(contents: &[u8]) -> Result<Self, ReadError> {
+        PdpLog::read(&mut PacketReader::new(contents))
+    }
+
+    /// Encodes the contents of a PDP status tag.
+    pub fn to_tag(&self) -> Vec<u8> {
+        let mut packet = PacketWriter::new();
+        self.write(&mut packet);
+        packet.into_vec()
+    }
+
+    /// Returns the sum of the channel currents in amps.
+    pub fn total_current(&self) -> f32 {
+        self.currents.iter().sum()
+    }
+
+    /// Returns the voltage at the panel, scaled the way the panel's CAN frames are.
+    pub fn bus_voltage(&self) -> f32 {
+        f32::from(self.voltage) * 0.05 + 4.0
+    }
+
+    /// Returns the panel's temperature in degrees Celsius, scaled the way the panel's CAN frames
+    /// are.
+    pub fn temperature(&self) -> f32 {
+        f32::from(self.temperature) * 1.032_508_4 - 67.856_45
+    }
+
+    pub(crate) fn read(packet: &mut PacketReader) -> Result<Self, ReadError> {
+        let bytes = packet.next_bytes(PDP_SIZE)?;
+        let mut pdp = PdpLog::read_without_id(&bytes[1..]);
+        pdp.id = bytes[0];
+        Ok(pdp)
+    }
+
+    /// Reads the currents, resistance, voltage and temperature, which start at `bytes[0]`
+    /// rather than after the id.
+    pub(crate) fn read_without_id(bytes: &[u8]) -> Self {
+        let mut currents = [0.0; 16];
+        for (channel, current) in currents.iter_mut().enumerate() {
+            let offset = PdpLog::current_offset(channel) - 8;
+            *current = f32::from(read_10_bits(bytes, offset)) / 8.0;
+        }
+        PdpLog {
+            id: 0,
+            currents,
+            resistance: bytes[21],
+            voltage: bytes[22],
+            temperature: bytes[23],
+        }
+    }
+
+    pub(crate) fn write(&self, packet: &mut PacketWriter) {
+        let mut bytes = [0u8; PDP_SIZE];
+        bytes[0] = self.id;
+        for (channel, current) in self.currents.iter().enumerate() {
+            let value = (current * 8.0).round().clamp(0.0, 1023.0) as u16;
+            write_10_bits(&mut bytes, PdpLog::current_offset(channel), value);
+        }
+        bytes[22] = self.resistance;
+        bytes[23] = self.voltage;
+        bytes[24] = self.temperature;
+        packet.write_slice(&bytes);
+    }
+}
+
+/// What a REV power distribution hub reported, which only version 4 `.dslog` files record.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PdhLog {
+    /// The current on each channel in amps. Channels 0-19 are stored in eighths of an amp up to
+    /// 127.875, and the low current channels 20-23 in sixteenths up to 15.9375.
+    pub currents: [f32; 24],
+}
+
+impl PdhLog {
+    /// Returns the sum of the channel currents in amps.
+    pub fn total_current(&self) -> f32 {
+        self.currents.iter().sum()
+    }
+
+    pub(crate) fn read(packet: &mut PacketReader) -> Result<Self, ReadError> {
+        let bytes = packet.next_bytes(REV_SIZE)?;
+        let mut currents = [0.0; 24];
+        for (channel, current) in currents.iter_mut().enumerate().take(20) {
+            let offset = 32 * (channel / 3) + 10 * (channel % 3);
+            *current = f32::from(read_10_bits(bytes, offset)) / 8.0;
+        }
+        for (current, byte) in currents[20..].iter_mut().zip(&bytes[27..31]) {
+            *current = f32::from(*byte) / 16.0;
+        }
+        Ok(PdhLog { currents })
+    }
+}
+
+/// How many samples a [PdpHistory] keeps by default, which is a minute of status packets.
+pub const HISTORY: usize = 3000;
+
+/// What the power distribution panel reported in one status packet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdpSample {
+    pub time: DateTime<Utc>,
+    pub pdp: PdpLog,
+}
+
+/// The most recent samples from the power distribution panel, oldest first.
+#[derive(Clone, Debug)]
+pub struct PdpHistory {
+    samples: VecDeque<PdpSample>,
+    capacity: usize,
+}
+
+impl PdpHistory {
+    /// Creates a history that keeps the last `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        PdpHistory {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a sample, dropping the oldest if the history is full.
+    pub fn push(&mut self, sample: PdpSample) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn latest(&self) -> Option<&PdpSample> {
+        self.samples.back()
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &PdpSample> {
+        self.samples.iter()
+    }
+
+    /// Returns the current on `channel` in each sample, with the time it was reported.
+    pub fn channel(&self, channel: usize) -> Vec<(DateTime<Utc>, f32)> {
+        self.samples
+            .iter()
+            .filter_map(|sample| Some((sample.time, *sample.pdp.currents.get(channel)?)))
+            .collect()
+    }
+
+    /// Returns the highest current on each channel over the history.
+    pub fn peak_currents(&self) -> [f32; 16] {
+        let mut peaks = [0.0f32; 16];
+        for sample in &self.samples {
+            for (peak, current) in peaks.iter_mut().zip(&sample.pdp.currents) {
+                *peak = peak.max(*current);
+            }
+        }
+        peaks
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl Default for PdpHistory {
+    fn default() -> Self {
+        PdpHistory::new(HISTORY)
+    }
+}
@@ -0,0 +1,294 @@
+//! The robot side of the protocol, for writing robot software or coprocessors that talk to a
+//! driver station.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::MutexGuard;
+use std::time::{Duration, Instant};
+
+use crate::joystick::Joystick;
+use crate::messages::ds::tcp::{MatchInfo, TcpTag};
+use crate::messages::ds::udp::{Control, DsUdpPacket};
+use crate::messages::rio::{RioTcpPacket, RioUdpPacket, Status, StatusTags, Trace};
+use crate::server::{Handler, RobotServer};
+use crate::states::{Alliance, RobotMode};
+use crate::version::ProtocolVersion;
+
+/// The ports to listen on and how long the driver station can be silent before the robot is
+/// disabled.
+#[derive(Clone, Debug)]
+pub struct RobotConfig {
+    pub udp_port: u16,
+    pub tcp_port: u16,
+    /// The port status packets are sent to on the driver station, or [None] to reply to the port
+    /// control packets come from.
+    pub ds_port: Option<u16>,
+    pub timeout: Duration,
+    /// The protocol version reported to the driver station and used to read its TCP tags.
+    pub version: ProtocolVersion,
+}
+
+impl Default for RobotConfig {
+    fn default() -> Self {
+        RobotConfig {
+            udp_port: 1110,
+            tcp_port: 1740,
+            ds_port: Some(1150),
+            timeout: Duration::from_secs(1),
+            version: ProtocolVersion::CURRENT,
+        }
+    }
+}
+
+struct RobotState {
+    control: Option<DsUdpPacket>,
+    last_control: Option<Instant>,
+    /// Set by the first control packet with the E-stop bit, and never cleared.
+    estopped: bool,
+    game_data: String,
+    match_info: Option<MatchInfo>,
+    has_code: bool,
+    code_initializing: bool,
+    brownout: bool,
+    battery_voltage: f32,
+    has_date: bool,
+    started: Instant,
+    console_sequence: u16,
+    outgoing: VecDeque<Vec<u8>>,
+    version: ProtocolVersion,
+}
+
+impl RobotState {
+    fn is_ds_connected(&self, now: Instant, timeout: Duration) -> bool {
+        match self.last_control {
+            Some(last) => now.saturating_duration_since(last) <= timeout,
+            None => false,
+        }
+    }
+
+    /// Returns whether the last control packet enables the robot and it hasn't been E-stopped.
+    fn is_enabled(&self) -> bool {
+        !self.estopped
+            && self
+                .control
+                .as_ref()
+                .is_some_and(|packet| packet.control.contains(Control::ENABLED))
+    }
+
+    fn status_packet(&self, sequence_num: u16) -> RioUdpPacket<'static> {
+        let mode = self
+            .control
+            .as_ref()
+            .and_then(|packet| packet.control.robot_mode())
+            .unwrap_or(RobotMode::Teleop);
+        let enabled = self.is_enabled();
+
+        let mut status = Status::from_bits_truncate(mode as u8);
+        if self.estopped {
+            status |= Status::ESTOP;
+        }
+        if enabled {
+            status |= Status::ENABLED;
+        }
+        if self.brownout {
+            status |= Status::BROWNOUT;
+        }
+        if self.code_initializing {
+            status |= Status::CODE_INITIALIZING;
+        }
+
+        let mut trace = Trace::IS_ROBORIO;
+        if self.has_code {
+            trace |= Trace::ROBOT_CODE;
+        }
+        trace |= if !enabled {
+            Trace::DISABLED
+        } else {
+            match mode {
+                RobotMode::Teleop => Trace::TELEOP_MODE,
+                RobotMode::Test => Trace::TEST_MODE,
+                RobotMode::Auto => Trace::AUTO_MODE,
+            }
+        };
+
+        RioUdpPacket {
+            sequence_num,
+            comm_version: self.version.comm_version(),
+            status,
+            trace,
+            battery_voltage: self.battery_voltage,
+            request_date: !self.has_date,
+            tags: StatusTags::new(),
+        }
+    }
+}
+
+impl Handler for RobotState {
+    fn on_control(&mut self, packet: DsUdpPacket) -> RioUdpPacket<'static> {
+        if packet.date.is_some() {
+            self.has_date = true;
+        }
+        if packet.control.contains(Control::ESTOP) {
+            self.estopped = true;
+        }
+
+        let sequence_num = packet.sequence_num;
+        self.control = Some(packet);
+        self.last_control = Some(Instant::now());
+        self.status_packet(sequence_num)
+    }
+
+    fn on_tcp(&mut self, frames: Vec<Vec<u8>>) {
+        let version = self.version;
+        for tag in frames
+            .into_iter()
+            .filter_map(|frame| TcpTag::from_bytes_for(&frame, version).ok())
+        {
+            match tag {
+                TcpTag::GameData(data) => self.game_data = data.data().to_string(),
+                TcpTag::MatchInfo(info) => self.match_info = Some(info),
+                TcpTag::JoystickDescriptor(_) => {}
+            }
+        }
+    }
+
+    fn outgoing(&mut self) -> &mut VecDeque<Vec<u8>> {
+        &mut self.outgoing
+    }
+}
+
+/// Accepts control packets from a driver station and reports the robot's status back, which
+/// runs until it's dropped.
+///
+/// The robot only reports itself as enabled while the driver station keeps sending control
+/// packets, so anything driving motors should follow [is_enabled](RobotComms::is_enabled).
+pub struct RobotComms {
+    server: RobotServer<RobotState>,
+    timeout: Duration,
+}
+
+impl RobotComms {
+    pub fn start(config: RobotConfig) -> io::Result<Self> {
+        let state = RobotState {
+            control: None,
+            last_control: None,
+            estopped: false,
+            game_data: String::new(),
+            match_info: None,
+            has_code: true,
+            code_initializing: false,
+            brownout: false,
+            battery_voltage: 0.0,
+            has_date: false,
+            started: Instant::now(),
+            console_sequence: 0,
+            outgoing: VecDeque::new(),
+            version: config.version,
+        };
+        let server = RobotServer::start(config.udp_port, config.tcp_port, config.ds_port, state)?;
+
+        Ok(RobotComms {
+            server,
+            timeout: config.timeout,
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, RobotState> {
+        self.server.lock()
+    }
+
+    pub fn udp_port(&self) -> u16 {
+        self.server.udp_port()
+    }
+
+    pub fn tcp_port(&self) -> u16 {
+        self.server.tcp_port()
+    }
+
+    /// Returns whether a control packet has been received within the timeout.
+    pub fn is_ds_connected(&self) -> bool {
+        self.state().is_ds_connected(Instant::now(), self.timeout)
+    }
+
+    /// Returns whether the robot should be running, which requires the driver station to be
+    /// connected, enabling the robot and never to have emergency stopped it.
+    pub fn is_enabled(&self) -> bool {
+        let state = self.state();
+        state.is_ds_connected(Instant::now(), self.timeout) && state.is_enabled()
+    }
+
+    /// Returns whether the driver station has emergency stopped the robot. This stays set until
+    /// the robot is restarted, even if later control packets clear the E-stop bit.
+    pub fn is_estopped(&self) -> bool {
+        self.state().estopped
+    }
+
+    pub fn is_fms_attached(&self) -> bool {
+        self.control()
+            .is_some_and(|packet| packet.control.contains(Control::FMS_CONNECTED))
+    }
+
+    pub fn mode(&self) -> RobotMode {
+        self.control()
+            .and_then(|packet| packet.control.robot_mode())
+            .unwrap_or(RobotMode::Teleop)
+    }
+
+    pub fn alliance(&self) -> Option<Alliance> {
+        self.control().map(|packet| packet.alliance)
+    }
+
+    /// Returns the joystick in the given slot, if there is one.
+    pub fn joystick(&self, slot: usize) -> Option<Joystick> {
+        self.control()
+            .and_then(|packet| packet.joysticks.get(slot).cloned())
+            .and_then(|joystick| joystick)
+    }
+
+    /// Returns the last control packet received from the driver station.
+    pub fn control(&self) -> Option<DsUdpPacket> {
+        self.state().control.clone()
+    }
+
+    pub fn game_data(&self) -> String {
+        self.state().game_data.clone()
+    }
+
+    pub fn match_info(&self) -> Option<MatchInfo> {
+        self.state().match_info.clone()
+    }
+
+    /// Sets whether user code is reported as running. This is set by default.
+    pub fn set_has_code(&self, has_code: bool) {
+        self.state().has_code = has_code;
+    }
+
+    pub fn set_code_initializing(&self, initializing: bool) {
+        self.state().code_initializing = initializing;
+    }
+
+    pub fn set_brownout(&self, brownout: bool) {
+        self.state().brownout = brownout;
+    }
+
+    pub fn set_battery_voltage(&self, voltage: f32) {
+        self.state().battery_voltage = voltage;
+    }
+
+    /// Prints a message to the driver station console.
+    pub fn print(&self, message: &str) {
+        let mut state = self.state();
+        let packet = RioTcpPacket::StandardOutput {
+            timestamp: state.started.elapsed().as_secs_f32(),
+            sequence_number: state.console_sequence,
+            message: message.to_string(),
+        };
+        state.console_sequence = state.console_sequence.wrapping_add(1);
+        state.outgoing.push_back(packet.to_packet());
+    }
+
+    /// Queues a message to be sent to the driver station once it's connected over TCP.
+    pub fn send_tcp(&self, packet: &RioTcpPacket) {
+        self.state().outgoing.push_back(packet.to_packet());
+    }
+}
@@ -0,0 +1,130 @@
+//! The sockets and thread shared by [RobotComms](crate::robot::RobotComms) and
+//! [MockRio](crate::mock::MockRio), which differ only in what they do with each message.
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::codec::FramedStream;
+use crate::messages::ds::udp::DsUdpPacket;
+use crate::messages::rio::RioUdpPacket;
+
+/// What a [RobotServer] does with the messages it receives.
+pub(crate) trait Handler: Send + 'static {
+    /// Handles a control packet and returns the status packet to reply with.
+    fn on_control(&mut self, packet: DsUdpPacket) -> RioUdpPacket<'static>;
+
+    /// Handles the frames received over TCP since the last call.
+    fn on_tcp(&mut self, frames: Vec<Vec<u8>>);
+
+    /// Returns the frames waiting to be sent over TCP.
+    fn outgoing(&mut self) -> &mut VecDeque<Vec<u8>>;
+}
+
+/// Listens for a driver station on a background thread, which is stopped and joined on drop.
+pub(crate) struct RobotServer<H> {
+    handler: Arc<Mutex<H>>,
+    thread: Option<JoinHandle<()>>,
+    stop: mpsc::Sender<()>,
+    udp_port: u16,
+    tcp_port: u16,
+}
+
+impl<H: Handler> RobotServer<H> {
+    /// Binds the given ports, where 0 picks any free port, and replies to control packets on
+    /// `ds_port`, or to the port they came from if it's [None].
+    pub fn start(
+        udp_port: u16,
+        tcp_port: u16,
+        ds_port: Option<u16>,
+        handler: H,
+    ) -> io::Result<Self> {
+        let udp = UdpSocket::bind(SocketAddr::new([0, 0, 0, 0].into(), udp_port))?;
+        udp.set_nonblocking(true)?;
+        let listener = TcpListener::bind(SocketAddr::new([0, 0, 0, 0].into(), tcp_port))?;
+        listener.set_nonblocking(true)?;
+
+        let udp_port = udp.local_addr()?.port();
+        let tcp_port = listener.local_addr()?.port();
+
+        let handler = Arc::new(Mutex::new(handler));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let t = {
+            let handler = handler.clone();
+            thread::spawn(move || {
+                let mut tcp: Option<FramedStream> = None;
+                while let Err(mpsc::TryRecvError::Empty) = stopped.try_recv() {
+                    let mut handler = handler.lock().unwrap();
+                    receive_udp(&udp, ds_port, &mut *handler);
+
+                    if let Ok((stream, _)) = listener.accept() {
+                        tcp = FramedStream::new(stream).ok();
+                    }
+                    if let Some(ref mut stream) = tcp {
+                        if exchange_tcp(stream, &mut *handler).is_err() {
+                            tcp = None;
+                        }
+                    }
+
+                    drop(handler);
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        Ok(RobotServer {
+            handler,
+            thread: Some(t),
+            stop,
+            udp_port,
+            tcp_port,
+        })
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, H> {
+        self.handler.lock().unwrap()
+    }
+
+    pub fn udp_port(&self) -> u16 {
+        self.udp_port
+    }
+
+    pub fn tcp_port(&self) -> u16 {
+        self.tcp_port
+    }
+}
+
+impl<H> Drop for RobotServer<H> {
+    fn drop(&mut self) {
+        self.stop.send(()).unwrap_or(());
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
+
+fn receive_udp<H: Handler>(udp: &UdpSocket, ds_port: Option<u16>, handler: &mut H) {
+    let mut buf = [0u8; 1024];
+    while let Ok((n, from)) = udp.recv_from(&mut buf) {
+        let packet = match DsUdpPacket::decode(&buf[..n]) {
+            Ok(packet) => packet,
+            Err(_) => continue,
+        };
+
+        let reply = handler.on_control(packet);
+        let to = match ds_port {
+            Some(port) => SocketAddr::new(from.ip(), port),
+            None => from,
+        };
+        udp.send_to(&reply.to_bytes(), to).unwrap_or(0);
+    }
+}
+
+fn exchange_tcp<H: Handler>(stream: &mut FramedStream, handler: &mut H) -> io::Result<()> {
+    handler.on_tcp(stream.receive()?);
+    stream.send_queued(handler.outgoing())
+}
@@ -1,5 +1,5 @@
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum RobotMode {
     Teleop = 0,
     Test = 1,
@@ -17,7 +17,7 @@ impl RobotMode {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Alliance {
     Red(u8),
     Blue(u8),
@@ -43,10 +43,22 @@ impl Alliance {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum MatchType {
     None = 0,
     Practice = 1,
     Qualification = 2,
     Elimination = 3,
 }
+
+impl MatchType {
+    pub fn from(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(MatchType::None),
+            1 => Some(MatchType::Practice),
+            2 => Some(MatchType::Qualification),
+            3 => Some(MatchType::Elimination),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+extern crate libds;
+
+mod common;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use libds::{
+    battery::{BatteryConfig, BatteryMonitor, BatteryWarning, Brownout, Phase},
+    log::DsLogRecord,
+    messages::ds::udp::Control,
+    messages::rio::{Status, Trace},
+    mock::RobotStatus,
+    pdp::PdpLog,
+    DriverStation,
+};
+
+use common::at;
+
+/// The time of the sample `n` 20ms periods into the match.
+fn sample(voltage: f32, control: Control, brownout: bool, current: f32) -> DsLogRecord {
+    let mut pdp = PdpLog::default();
+    pdp.currents[0] = current;
+    DsLogRecord {
+        trip_time_ms: 0.0,
+        packet_loss: 0.0,
+        battery_voltage: voltage,
+        rio_cpu: 0.0,
+        control,
+        status: if brownout {
+            Status::BROWNOUT
+        } else {
+            Status::empty()
+        },
+        trace: Trace::empty(),
+        watchdog: false,
+        can_utilization: 0.0,
+        wifi_db: 0.0,
+        bandwidth_mb: 0.0,
+        pdp,
+        pdh: None,
+    }
+}
+
+#[test]
+fn voltage_per_phase() {
+    let mut monitor = BatteryMonitor::default();
+    let samples = [
+        (12.5, Control::AUTO),
+        (12.0, Control::AUTO | Control::ENABLED),
+        (11.0, Control::AUTO | Control::ENABLED),
+        // No robot, so ignored.
+        (0.0, Control::AUTO | Control::ENABLED),
+        (10.0, Control::TELEOP | Control::ENABLED),
+    ];
+    for (n, (voltage, control)) in samples.iter().enumerate() {
+        monitor.update(at(n as i64), &sample(*voltage, *control, false, 0.0));
+    }
+
+    let report = monitor.report();
+    let disabled = report.phase(Phase::Disabled).unwrap();
+    assert_eq!(
+        (disabled.min, disabled.average, disabled.samples),
+        (12.5, 12.5, 1)
+    );
+    let auto = report.phase(Phase::Auto).unwrap();
+    assert_eq!((auto.min, auto.average, auto.samples), (11.0, 11.5, 2));
+    let teleop = report.phase(Phase::Teleop).unwrap();
+    assert_eq!((teleop.min, teleop.samples), (10.0, 1));
+    assert_eq!(report.phase(Phase::Test), None);
+    assert_eq!(report.resistance, None);
+}
+
+#[test]
+fn brownouts_with_durations() {
+    let mut monitor = BatteryMonitor::new(BatteryConfig { thresholds: vec![] });
+    let teleop = Control::TELEOP | Control::ENABLED;
+    let brownouts = [false, true, true, true, false, false, true];
+    let mut warnings = Vec::new();
+    for (n, brownout) in brownouts.iter().enumerate() {
+        let voltage = if *brownout {
+            6.5 - n as f32 * 0.125
+        } else {
+            9.0
+        };
+        warnings.extend(monitor.update(at(n as i64), &sample(voltage, teleop, *brownout, 0.0)));
+    }
+
+    assert_eq!(
+        monitor.report().brownouts,
+        vec![
+            Brownout {
+                start: at(1),
+                duration: Some(Duration::from_millis(60)),
+                min_voltage: 6.125,
+            },
+            Brownout {
+                start: at(6),
+                duration: None,
+                min_voltage: 5.75,
+            },
+        ]
+    );
+    assert_eq!(
+        warnings,
+        vec![
+            BatteryWarning::Brownout { voltage: 6.375 },
+            BatteryWarning::Brownout { voltage: 5.75 },
+        ]
+    );
+}
+
+#[test]
+fn estimates_resistance_from_sag() {
+    let mut monitor = BatteryMonitor::default();
+    let teleop = Control::TELEOP | Control::ENABLED;
+    // 20 milliohms between the battery and the panel.
+    for n in 0..50 {
+        let current = (n % 10) as f32 * 10.0;
+        let voltage = 12.5 - 0.02 * current;
+        monitor.update(at(n), &sample(voltage, teleop, false, current));
+    }
+    let resistance = monitor.report().resistance.unwrap();
+    assert!((resistance - 0.02).abs() < 0.0005, "{}", resistance);
+}
+
+#[test]
+fn warns_once_per_threshold_crossing() {
+    let mut monitor = BatteryMonitor::new(BatteryConfig {
+        thresholds: vec![10.0, 8.0],
+    });
+    let teleop = Control::TELEOP | Control::ENABLED;
+    let voltages = [11.0, 9.5, 9.25, 7.5, 9.75, 10.25, 10.5, 9.0];
+    let warnings: Vec<_> = voltages
+        .iter()
+        .enumerate()
+        .map(|(n, voltage)| monitor.update(at(n as i64), &sample(*voltage, teleop, false, 0.0)))
+        .collect();
+
+    let low = |threshold, voltage| vec![BatteryWarning::LowVoltage { threshold, voltage }];
+    assert_eq!(
+        warnings,
+        vec![
+            vec![],
+            low(10.0, 9.5),
+            vec![],
+            low(8.0, 7.5),
+            vec![],
+            // Not far enough above 10V to warn again.
+            vec![],
+            vec![],
+            low(10.0, 9.0),
+        ]
+    );
+}
+
+#[test]
+fn driver_station_warns_live() {
+    let rio = common::start_mock();
+    rio.set_status(RobotStatus {
+        battery_voltage: 8.5,
+        ..RobotStatus::default()
+    });
+
+    let mut ds = DriverStation::new();
+    let warnings = ds.battery_warnings();
+    common::connect(&rio, &mut ds);
+
+    assert_eq!(
+        warnings.recv_timeout(Duration::from_secs(3)),
+        Ok(BatteryWarning::LowVoltage {
+            threshold: 9.0,
+            voltage: 8.5
+        })
+    );
+    assert_eq!(
+        warnings.recv_timeout(Duration::from_millis(200)),
+        Err(mpsc::RecvTimeoutError::Timeout)
+    );
+    let disabled = ds.battery().phase(Phase::Disabled).unwrap();
+    assert_eq!(disabled.min, 8.5);
+}
+
+#[test]
+fn driver_station_ignores_the_battery_without_comms() {
+    let (rio, ds) = common::start();
+    let samples = || {
+        ds.battery()
+            .phase(Phase::Disabled)
+            .map_or(0, |phase| phase.samples)
+    };
+    assert!(common::wait_for(|| samples() >= 5));
+
+    drop(rio);
+    assert!(common::wait_for(|| !ds.has_robot_comms()));
+    let before = samples();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(samples(), before);
+}
@@ -0,0 +1,114 @@
+extern crate libds;
+
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libds::codec::FramedStream;
+
+/// Returns a plain blocking stream and a framed stream connected to each other.
+fn pair() -> (TcpStream, FramedStream) {
+    pair_with_max_pending(libds::codec::DEFAULT_MAX_PENDING)
+}
+
+fn pair_with_max_pending(max_pending: usize) -> (TcpStream, FramedStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let raw = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (accepted, _) = listener.accept().unwrap();
+    (
+        raw,
+        FramedStream::with_max_pending(accepted, max_pending).unwrap(),
+    )
+}
+
+/// Polls until at least `count` frames have arrived.
+fn receive(stream: &mut FramedStream, count: usize) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + Duration::from_secs(3);
+    let mut frames = Vec::new();
+    while frames.len() < count && Instant::now() < deadline {
+        frames.extend(stream.poll().unwrap());
+        thread::sleep(Duration::from_millis(5));
+    }
+    frames
+}
+
+#[test]
+fn keeps_partial_frames_until_complete() {
+    let (mut raw, mut framed) = pair();
+
+    raw.write_all(&[0x00]).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert!(framed.receive().unwrap().is_empty());
+
+    raw.write_all(&[0x03, 0x0e, b'h']).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert!(framed.receive().unwrap().is_empty());
+
+    raw.write_all(b"i").unwrap();
+    assert_eq!(receive(&mut framed, 1), vec![vec![0x0e, b'h', b'i']]);
+}
+
+#[test]
+fn splits_several_frames_from_one_write() {
+    let (mut raw, mut framed) = pair();
+
+    raw.write_all(&[0x00, 0x01, 0x0a, 0x00, 0x00, 0x00, 0x02, 0x0b, 0x0c, 0x00])
+        .unwrap();
+    assert_eq!(
+        receive(&mut framed, 3),
+        vec![vec![0x0a], vec![], vec![0x0b, 0x0c]]
+    );
+
+    // The trailing byte is the start of the next frame.
+    raw.write_all(&[0x01, 0xff]).unwrap();
+    assert_eq!(receive(&mut framed, 1), vec![vec![0xff]]);
+}
+
+#[test]
+fn sends_frames_in_order() {
+    let (mut raw, mut framed) = pair();
+
+    framed.send_frame(&[0x0e, b'a']).unwrap();
+    framed.send(&[0x00, 0x01, 0x0c, 0x00, 0x00]).unwrap();
+
+    let mut buf = [0u8; 9];
+    raw.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [0x00, 0x02, 0x0e, b'a', 0x00, 0x01, 0x0c, 0x00, 0x00]);
+}
+
+#[test]
+fn refuses_frames_too_long_for_their_length() {
+    let (mut raw, mut framed) = pair();
+
+    let error = framed.send_frame(&vec![0u8; 65536]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(framed.pending(), 0);
+
+    framed.send_frame(&[0x0e]).unwrap();
+    let mut buf = [0u8; 3];
+    raw.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [0x00, 0x01, 0x0e]);
+}
+
+#[test]
+fn pushes_back_when_the_peer_stops_reading() {
+    let (_raw, mut framed) = pair_with_max_pending(1024);
+
+    // Fill the socket's buffers and then the queue, which should refuse more than its limit.
+    let frame = vec![0u8; 512];
+    let mut refused = false;
+    for _ in 0..100_000 {
+        match framed.send_frame(&frame) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                refused = true;
+                break;
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+    assert!(refused);
+    assert!(framed.pending() <= 1024);
+}
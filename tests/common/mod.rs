@@ -0,0 +1,62 @@
+//! Fixtures shared by the tests that connect a driver station to a robot on localhost.
+
+// Each test file uses only some of these.
+#![allow(dead_code)]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use libds::{
+    mock::{MockRio, MockRioConfig},
+    ConnectionConfig, DriverStation,
+};
+
+/// Polls `condition` for up to 3 seconds, returning whether it became true.
+pub fn wait_for<F: FnMut() -> bool>(mut condition: F) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+/// A config for a robot on localhost, receiving status packets on a port chosen by the OS.
+pub fn local_config(udp_port: u16, tcp_port: u16) -> ConnectionConfig {
+    let mut config = ConnectionConfig::new([127, 0, 0, 1].into());
+    config.robot_udp_port = udp_port;
+    config.robot_tcp_port = tcp_port;
+    config.ds_udp_port = 0;
+    config
+}
+
+/// Starts a mock robot on ports chosen by the OS.
+pub fn start_mock() -> MockRio {
+    MockRio::start(MockRioConfig {
+        udp_port: 0,
+        tcp_port: 0,
+        ds_port: None,
+    })
+    .unwrap()
+}
+
+pub fn connect(rio: &MockRio, ds: &mut DriverStation) {
+    ds.connect_with(local_config(rio.udp_port(), rio.tcp_port()))
+        .unwrap();
+}
+
+/// Starts a mock robot and a driver station connected to it.
+pub fn start() -> (MockRio, DriverStation) {
+    let rio = start_mock();
+    let mut ds = DriverStation::new();
+    connect(&rio, &mut ds);
+    (rio, ds)
+}
+
+/// The time of the `n`th control packet of a recording that starts at 2024-03-09 13:45:30.
+pub fn at(n: i64) -> DateTime<Utc> {
+    Utc.ymd(2024, 3, 9).and_hms(13, 45, 30) + chrono::Duration::milliseconds(20 * n)
+}
@@ -0,0 +1,53 @@
+extern crate libds;
+
+mod common;
+
+use std::io::Read;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use libds::DriverStation;
+
+use common::{local_config, start_mock, wait_for};
+
+/// Finds a local TCP port that nothing is listening on.
+fn closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[test]
+fn control_packets_keep_going_until_tcp_connects() {
+    let rio = start_mock();
+    let tcp_port = closed_port();
+    let mut ds = DriverStation::new();
+    ds.set_game_data("LRL".to_owned());
+    ds.connect_with(local_config(rio.udp_port(), tcp_port))
+        .unwrap();
+
+    // The robot's TCP port isn't open yet, like while it's booting.
+    assert!(wait_for(|| ds.connection_error().is_some()));
+    assert!(wait_for(|| rio.control_packets().len() >= 10));
+    assert!(wait_for(|| ds.has_robot_comms()));
+    assert!(!ds.has_robot_tcp());
+
+    let listener = TcpListener::bind(("127.0.0.1", tcp_port)).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    assert!(wait_for(|| ds.has_robot_tcp()));
+    stream
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .unwrap();
+    let mut buf = [0u8; 64];
+    let n = stream.read(&mut buf).unwrap();
+    assert!(buf[..n].windows(3).any(|bytes| bytes == b"LRL"));
+
+    // Dropping the connection mid-session is reported, and it's made again.
+    drop(stream);
+    assert!(wait_for(|| !ds.has_robot_tcp()));
+    let (_stream, _) = listener.accept().unwrap();
+    assert!(wait_for(|| ds.has_robot_tcp()));
+    assert!(ds.has_robot_comms());
+}
@@ -0,0 +1,240 @@
+extern crate libds;
+extern crate regex;
+
+mod common;
+
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use libds::{
+    console::{
+        ConsoleFilter, ConsoleMessage, ConsoleSource, ConsoleSubscription, Severity,
+        REPEAT_INTERVAL,
+    },
+    messages::rio::RioTcpPacket,
+};
+
+use common::{connect, start};
+
+fn print(sequence_number: u16, timestamp: f32, message: &str) -> RioTcpPacket {
+    RioTcpPacket::StandardOutput {
+        timestamp,
+        sequence_number,
+        message: message.to_owned(),
+    }
+}
+
+fn error(sequence_number: u16, error_code: u16, is_error: bool, location: &str) -> RioTcpPacket {
+    RioTcpPacket::ErrorMessage {
+        timestamp: 1.0,
+        sequence_number,
+        print_msg: true,
+        error_code,
+        is_error,
+        details: format!("problem {}", error_code),
+        location: location.to_owned(),
+        call_stack: String::new(),
+    }
+}
+
+/// Receives `count` messages, failing if they don't arrive in time.
+fn receive(console: &ConsoleSubscription, count: usize) -> Vec<ConsoleMessage> {
+    (0..count)
+        .map(|_| console.recv_timeout(Duration::from_secs(3)).unwrap())
+        .collect()
+}
+
+fn texts(messages: &[ConsoleMessage]) -> Vec<(&str, u32)> {
+    messages
+        .iter()
+        .map(|message| (message.text.as_str(), message.count))
+        .collect()
+}
+
+#[test]
+fn delivers_messages_with_repeat_counts() {
+    let (rio, ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+
+    rio.send_tcp(&print(1, 0.5, "hello"));
+    rio.send_tcp(&print(2, 0.6, "loop overrun"));
+    rio.send_tcp(&print(3, 0.7, "loop overrun"));
+    rio.send_tcp(&print(4, 0.8, "loop overrun"));
+    rio.send_tcp(&error(5, 44004, true, "Robot.java"));
+
+    // The repeats are delivered once, with their count, when a different message arrives.
+    let messages = receive(&console, 4);
+    assert_eq!(
+        texts(&messages),
+        vec![
+            ("hello", 1),
+            ("loop overrun", 1),
+            ("loop overrun", 3),
+            ("problem 44004", 1)
+        ]
+    );
+    assert_eq!(messages[3].severity, Severity::Error);
+    assert_eq!(messages[3].error_code, Some(44004));
+    assert_eq!(messages[3].location, "Robot.java");
+}
+
+#[test]
+fn delivers_repeat_counts_after_an_interval() {
+    let (rio, ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+
+    for n in 1..=5 {
+        rio.send_tcp(&print(n, f32::from(n), "loop overrun"));
+    }
+    assert_eq!(texts(&receive(&console, 1)), vec![("loop overrun", 1)]);
+    let start = Instant::now();
+    assert_eq!(texts(&receive(&console, 1)), vec![("loop overrun", 5)]);
+    assert!(start.elapsed() >= REPEAT_INTERVAL - Duration::from_millis(100));
+
+    // Nothing is left to deliver until the robot sends more.
+    assert_eq!(console.recv_timeout(REPEAT_INTERVAL * 2), None);
+    rio.send_tcp(&print(6, 6.0, "loop overrun"));
+    rio.send_tcp(&print(7, 7.0, "done"));
+    assert_eq!(
+        texts(&receive(&console, 2)),
+        vec![("loop overrun", 6), ("done", 1)]
+    );
+}
+
+#[test]
+fn filters_messages() {
+    let (rio, ds) = start();
+    let warnings = ds.console(ConsoleFilter {
+        min_severity: Severity::Warning,
+        ..ConsoleFilter::default()
+    });
+    let codes = ds.console(ConsoleFilter {
+        error_codes: vec![1],
+        location: Some("Drive".to_owned()),
+        ..ConsoleFilter::default()
+    });
+    let pattern = ds.console(ConsoleFilter {
+        pattern: Some(Regex::new(r"^auto \d+$").unwrap()),
+        ..ConsoleFilter::default()
+    });
+
+    rio.send_tcp(&print(1, 0.1, "auto 12"));
+    rio.send_tcp(&print(2, 0.2, "auto twelve"));
+    rio.send_tcp(&error(3, 1, false, "Drive.java"));
+    rio.send_tcp(&error(4, 1, true, "Arm.java"));
+    rio.send_tcp(&error(5, 2, true, "Drive.java"));
+
+    assert_eq!(
+        texts(&receive(&warnings, 3)),
+        vec![("problem 1", 1), ("problem 1", 1), ("problem 2", 1)]
+    );
+    let code = receive(&codes, 1);
+    assert_eq!(code[0].sequence_number, 3);
+    assert_eq!(texts(&receive(&pattern, 1)), vec![("auto 12", 1)]);
+
+    assert!(codes.recv_timeout(Duration::from_millis(200)).is_none());
+    assert!(pattern.try_recv().is_none());
+}
+
+#[test]
+fn drops_messages_sent_again_after_reconnecting() {
+    let (rio, mut ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+
+    rio.send_tcp(&print(1, 0.5, "first"));
+    rio.send_tcp(&print(2, 0.6, "second"));
+    assert_eq!(receive(&console, 2).len(), 2);
+
+    rio.clear();
+    connect(&rio, &mut ds);
+    // The driver station sends its game data and match info once it's connected.
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while rio.tcp_tags().is_empty() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    // The robot sends what it already sent along with something new, out of order.
+    rio.send_tcp(&print(3, 0.7, "third"));
+    rio.send_tcp(&print(2, 0.6, "second"));
+    assert_eq!(texts(&receive(&console, 1)), vec![("third", 1)]);
+
+    // Restarted robot code numbers its messages from the start again.
+    rio.send_tcp(&print(1, 0.1, "restarted"));
+    assert_eq!(texts(&receive(&console, 1)), vec![("restarted", 1)]);
+    assert!(console.recv_timeout(Duration::from_millis(200)).is_none());
+}
+
+#[test]
+fn netconsole_lines_join_the_stream() {
+    let (rio, mut ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+    let port = ds.listen_netconsole(0).unwrap();
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let to = ("127.0.0.1", port);
+    // Datagrams don't have to end at line breaks.
+    sender.send_to(b"hel", to).unwrap();
+    sender.send_to(b"lo\nwor", to).unwrap();
+    sender.send_to(b"ld\r\n", to).unwrap();
+    let netconsole = receive(&console, 2);
+    assert_eq!(texts(&netconsole), vec![("hello", 1), ("world", 1)]);
+    assert!(netconsole
+        .iter()
+        .all(|message| message.source == ConsoleSource::NetConsole));
+
+    rio.send_tcp(&print(1, 0.5, "over tcp"));
+    let tcp = receive(&console, 1);
+    assert_eq!(texts(&tcp), vec![("over tcp", 1)]);
+    assert_eq!(tcp[0].source, ConsoleSource::Tcp);
+
+    ds.stop_netconsole();
+    // The port is free again as soon as the listener stops.
+    UdpSocket::bind(("0.0.0.0", port)).unwrap();
+}
+
+#[test]
+fn netconsole_listens_on_the_same_port_again() {
+    let (_rio, mut ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+    let port = UdpSocket::bind("0.0.0.0:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    assert_eq!(ds.listen_netconsole(port).unwrap(), port);
+    assert_eq!(ds.listen_netconsole(port).unwrap(), port);
+    ds.stop_netconsole();
+    assert_eq!(ds.listen_netconsole(port).unwrap(), port);
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender
+        .send_to(b"still here\n", ("127.0.0.1", port))
+        .unwrap();
+    assert_eq!(texts(&receive(&console, 1)), vec![("still here", 1)]);
+}
+
+#[test]
+fn netconsole_handles_split_characters_and_long_lines() {
+    let (_rio, mut ds) = start();
+    let console = ds.console(ConsoleFilter::default());
+    let port = ds.listen_netconsole(0).unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let to = ("127.0.0.1", port);
+
+    // The two bytes of the \u{e9} arrive in different datagrams.
+    sender.send_to(b"caf\xc3", to).unwrap();
+    sender.send_to(b"\xa9\n", to).unwrap();
+    assert_eq!(texts(&receive(&console, 1)), vec![("caf\u{e9}", 1)]);
+
+    // A line that never ends is delivered in pieces rather than kept forever.
+    for _ in 0..3 {
+        sender.send_to(&[b'a'; 2000], to).unwrap();
+    }
+    let piece = receive(&console, 1);
+    assert_eq!(piece[0].text.len(), 4096);
+    sender.send_to(b"\n", to).unwrap();
+    assert_eq!(receive(&console, 1)[0].text.len(), 6000 - 4096);
+}
@@ -0,0 +1,146 @@
+extern crate libds;
+
+mod common;
+
+use libds::{
+    log::export::*,
+    log::{DsEvents, DsEventsWriter, DsLog, DsLogRecord, DsLogWriter},
+    messages::ds::udp::Control,
+    messages::rio::{RioTcpPacket, Status, Trace},
+    pdp::PdpLog,
+};
+
+use common::at;
+
+fn record(voltage: f32, control: Control, status: Status) -> DsLogRecord {
+    DsLogRecord {
+        trip_time_ms: 2.5,
+        packet_loss: 0.0,
+        battery_voltage: voltage,
+        rio_cpu: 0.5,
+        control,
+        status,
+        trace: Trace::empty(),
+        watchdog: false,
+        can_utilization: 0.0,
+        wifi_db: 0.0,
+        bandwidth_mb: 0.0,
+        pdp: PdpLog::default(),
+        pdh: None,
+    }
+}
+
+fn log() -> DsLog {
+    let mut writer = DsLogWriter::new(Vec::new(), at(0)).unwrap();
+    writer
+        .write_record(&record(12.5, Control::AUTO, Status::empty()))
+        .unwrap();
+    writer
+        .write_record(&record(
+            12.25,
+            Control::AUTO | Control::ENABLED,
+            Status::empty(),
+        ))
+        .unwrap();
+    writer
+        .write_record(&record(
+            6.5,
+            Control::TELEOP | Control::ENABLED,
+            Status::BROWNOUT,
+        ))
+        .unwrap();
+    DsLog::from_bytes(&writer.into_inner()).unwrap()
+}
+
+fn export(config: ExportConfig<Column>) -> String {
+    let mut exporter = TelemetryExporter::new(Vec::new(), config).unwrap();
+    exporter.write_log(&log()).unwrap();
+    String::from_utf8(exporter.into_inner()).unwrap()
+}
+
+#[test]
+fn telemetry_csv() {
+    assert_eq!(
+        export(ExportConfig::default()),
+        "time,mode,enabled,battery_voltage,brownout,trip_time_ms,packet_loss\n\
+         2024-03-09T13:45:30.000Z,auto,false,12.5,false,2.5,0\n\
+         2024-03-09T13:45:30.020Z,auto,true,12.25,false,2.5,0\n\
+         2024-03-09T13:45:30.040Z,teleop,true,6.5,true,2.5,0\n"
+    );
+}
+
+#[test]
+fn telemetry_json_lines_in_range() {
+    let config = ExportConfig {
+        format: Format::JsonLines,
+        columns: vec![Column::Elapsed, Column::BatteryVoltage, Column::RioCpu],
+        from: Some(at(1)),
+        until: Some(at(2)),
+    };
+    assert_eq!(
+        export(config),
+        "{\"elapsed\":0,\"battery_voltage\":12.25,\"rio_cpu\":0.5}\n"
+    );
+}
+
+#[test]
+fn messages_from_events() {
+    let mut writer = DsEventsWriter::new(Vec::new(), at(0)).unwrap();
+    let messages = [
+        RioTcpPacket::StandardOutput {
+            timestamp: 1.5,
+            sequence_number: 0,
+            message: "says \"hi\", twice".to_owned(),
+        },
+        RioTcpPacket::ErrorMessage {
+            timestamp: 2.0,
+            sequence_number: 0,
+            print_msg: true,
+            error_code: 44004,
+            is_error: true,
+            details: "Joystick unplugged".to_owned(),
+            location: "Robot.java".to_owned(),
+            call_stack: String::new(),
+        },
+    ];
+    for message in &messages {
+        writer.write_message(at(0), message).unwrap();
+    }
+    writer.write_event(at(0), "Some note").unwrap();
+    let events = DsEvents::from_bytes(&writer.into_inner()).unwrap();
+
+    let mut csv = MessageExporter::new(Vec::new(), ExportConfig::default()).unwrap();
+    csv.write_events(&events).unwrap();
+    assert_eq!(
+        String::from_utf8(csv.into_inner()).unwrap(),
+        "time,kind,code,message,location\n\
+         2024-03-09T13:45:30.000Z,stdout,,\"says \"\"hi\"\", twice\",\n\
+         2024-03-09T13:45:30.000Z,error,44004,Joystick unplugged,Robot.java\n\
+         2024-03-09T13:45:30.000Z,event,,Some note,\n"
+    );
+
+    let config = ExportConfig {
+        format: Format::JsonLines,
+        columns: vec![
+            MessageColumn::RobotTime,
+            MessageColumn::Kind,
+            MessageColumn::Message,
+        ],
+        ..ExportConfig::default()
+    };
+    let mut json = MessageExporter::new(Vec::new(), config).unwrap();
+    // Live messages can be written directly, and those that aren't text are skipped.
+    json.write_message(at(0), &messages[0]).unwrap();
+    json.write_message(
+        at(0),
+        &RioTcpPacket::DisableFaults {
+            comms: 0,
+            twelve_v: 0,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(json.into_inner()).unwrap(),
+        "{\"robot_time\":1.5,\"kind\":\"stdout\",\"message\":\"says \\\"hi\\\", twice\"}\n"
+    );
+}
@@ -0,0 +1,252 @@
+extern crate chrono;
+extern crate libds;
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use libds::{
+    fms::{FmsConfig, FmsControl},
+    interlock::EnableRefusal,
+    messages::fms::*,
+    states::{Alliance, MatchType, RobotMode},
+    ConnectionConfig, DriverStation,
+};
+
+use common::{start, wait_for};
+
+#[test]
+fn attached_fms_locks_out_local_control() {
+    let ds = DriverStation::new();
+    ds.set_fms_attached(true);
+
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::FmsAttached));
+    ds.set_mode(RobotMode::Test);
+    ds.set_alliance(Alliance::Blue(3));
+    assert_eq!(ds.mode(), RobotMode::Teleop);
+    assert_eq!(ds.alliance(), Alliance::Red(1));
+
+    let control = FmsControl {
+        enabled: true,
+        estop: false,
+        mode: RobotMode::Auto,
+        alliance: Alliance::Blue(2),
+    };
+    ds.apply_fms_control(&control).unwrap();
+    assert!(ds.is_enabled());
+    assert_eq!(ds.mode(), RobotMode::Auto);
+    assert_eq!(ds.alliance(), Alliance::Blue(2));
+
+    ds.set_fms_attached(false);
+    assert!(!ds.is_enabled());
+    assert_eq!(ds.apply_fms_control(&control), Ok(()));
+    assert!(!ds.is_enabled());
+}
+
+#[test]
+fn fms_estop_latches() {
+    let ds = DriverStation::new();
+    ds.set_fms_attached(true);
+
+    let mut control = FmsControl {
+        enabled: false,
+        estop: true,
+        mode: RobotMode::Auto,
+        alliance: Alliance::Red(1),
+    };
+    ds.apply_fms_control(&control).unwrap();
+    control.enabled = true;
+    control.estop = false;
+    assert_eq!(ds.apply_fms_control(&control), Err(EnableRefusal::EStopped));
+    assert!(!ds.is_enabled());
+}
+
+struct FakeFms {
+    udp: UdpSocket,
+    tcp: TcpListener,
+}
+
+impl FakeFms {
+    fn new() -> Self {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        udp.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        FakeFms {
+            udp,
+            tcp: TcpListener::bind("127.0.0.1:0").unwrap(),
+        }
+    }
+
+    fn config(&self, team: u16) -> FmsConfig {
+        FmsConfig {
+            control_port: 0,
+            status_port: self.udp.local_addr().unwrap().port(),
+            tcp_port: self.tcp.local_addr().unwrap().port(),
+            timeout: Duration::from_millis(300),
+            ..FmsConfig::new(team, [127, 0, 0, 1].into())
+        }
+    }
+
+    fn accept(&self) -> (TcpStream, DsFmsTcpTag) {
+        let (mut stream, _) = self.tcp.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut size = [0u8; 2];
+        stream.read_exact(&mut size).unwrap();
+        let mut frame = vec![0u8; u16::from_be_bytes(size) as usize];
+        stream.read_exact(&mut frame).unwrap();
+        (stream, DsFmsTcpTag::from_bytes(&frame).unwrap())
+    }
+
+    fn receive_status(&self) -> (DsStatusPacket, SocketAddr) {
+        let mut buf = [0u8; 64];
+        let (n, from) = self.udp.recv_from(&mut buf).unwrap();
+        (DsStatusPacket::from_bytes(&buf[..n]).unwrap(), from)
+    }
+
+    fn send_control(&self, to: SocketAddr, packet: &FmsControlPacket) {
+        self.udp.send_to(&packet.to_bytes(), to).unwrap();
+    }
+}
+
+fn control_packet(enabled: bool, estop: bool) -> FmsControlPacket {
+    FmsControlPacket {
+        sequence_num: 1,
+        estop,
+        enabled,
+        mode: RobotMode::Auto,
+        alliance: Alliance::Blue(2),
+        match_type: MatchType::Qualification,
+        match_number: 12,
+        replay_number: 1,
+        time: Utc::now(),
+        remaining_seconds: 15,
+    }
+}
+
+#[test]
+fn client_follows_the_fms() {
+    let fms = FakeFms::new();
+    let mut ds = DriverStation::new();
+    ds.connect_fms(fms.config(639)).unwrap();
+
+    let (mut tcp, hello) = fms.accept();
+    assert_eq!(hello, DsFmsTcpTag::TeamNumber(639));
+    for tag in &[
+        FmsTcpTag::EventCode("NYRO".to_owned()),
+        FmsTcpTag::StationInfo {
+            alliance: Alliance::Blue(2),
+            status: StationStatus::Good,
+        },
+        FmsTcpTag::GameData("LRL".to_owned()),
+    ] {
+        tcp.write_all(&tag.to_packet()).unwrap();
+    }
+
+    let (status, ds_addr) = fms.receive_status();
+    assert_eq!(status.team, 639);
+    assert!(!status.status.contains(DsStatus::ROBOT_COMMS));
+
+    fms.send_control(ds_addr, &control_packet(true, false));
+    assert!(wait_for(
+        || ds.is_enabled() && ds.match_info().competition == "NYRO"
+    ));
+    assert!(ds.is_fms_attached());
+    assert_eq!(ds.mode(), RobotMode::Auto);
+    assert_eq!(ds.alliance(), Alliance::Blue(2));
+    assert_eq!(ds.match_info().match_number, 12);
+    assert_eq!(ds.match_info().match_type, MatchType::Qualification);
+    assert_eq!(ds.game_data(), "LRL");
+    assert_eq!(ds.fms_station_status(), Some(StationStatus::Good));
+
+    assert!(wait_for(|| fms
+        .receive_status()
+        .0
+        .status
+        .contains(DsStatus::ENABLED)));
+
+    // The robot is disabled if the FMS goes quiet.
+    assert!(wait_for(|| !ds.is_enabled()));
+    assert!(ds.is_fms_attached());
+
+    ds.disconnect_fms();
+    assert!(!ds.is_fms_attached());
+}
+
+#[test]
+fn reconnecting_to_the_fms_reuses_the_port() {
+    let fms = FakeFms::new();
+    let mut config = fms.config(639);
+    config.control_port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut ds = DriverStation::new();
+    for _ in 0..5 {
+        ds.connect_fms(config.clone()).unwrap();
+    }
+    let (_, ds_addr) = fms.receive_status();
+    assert_eq!(ds_addr.port(), config.control_port);
+}
+
+#[test]
+fn client_reports_lost_packets() {
+    let fms = FakeFms::new();
+    let mut ds = DriverStation::new();
+    // Nothing answers the control packets.
+    let robot = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut config = ConnectionConfig::new([127, 0, 0, 1].into());
+    config.robot_udp_port = robot.local_addr().unwrap().port();
+    config.ds_udp_port = 0;
+    ds.connect_with(config).unwrap();
+    ds.connect_fms(fms.config(639)).unwrap();
+
+    assert!(wait_for(|| {
+        let comms = fms.receive_status().0.comms.unwrap();
+        comms.lost_packets >= 5 && comms.sent_packets >= comms.lost_packets
+    }));
+    assert_eq!(fms.receive_status().0.comms.unwrap().trip_time_ms, 0);
+}
+
+#[test]
+fn client_applies_fms_estop() {
+    let fms = FakeFms::new();
+    let mut ds = DriverStation::new();
+    ds.connect_fms(fms.config(254)).unwrap();
+    let _tcp = fms.accept();
+
+    let (_, ds_addr) = fms.receive_status();
+    fms.send_control(ds_addr, &control_packet(true, false));
+    assert!(wait_for(|| ds.is_enabled()));
+
+    fms.send_control(ds_addr, &control_packet(true, true));
+    assert!(wait_for(|| ds.is_estopped()));
+    assert!(!ds.is_enabled());
+    assert!(wait_for(|| fms
+        .receive_status()
+        .0
+        .status
+        .contains(DsStatus::ESTOP)));
+}
+
+#[test]
+fn client_reports_only_robot_comms() {
+    let fms = FakeFms::new();
+    let (_rio, mut ds) = start();
+    ds.connect_fms(fms.config(254)).unwrap();
+
+    assert!(wait_for(|| {
+        fms.receive_status()
+            .0
+            .status
+            .contains(DsStatus::ROBOT_COMMS)
+    }));
+    let status = fms.receive_status().0.status;
+    assert!(!status.intersects(DsStatus::RADIO_PING | DsStatus::RIO_PING));
+}
@@ -0,0 +1,128 @@
+extern crate libds;
+
+mod common;
+
+use std::net::{TcpListener, UdpSocket};
+use std::time::Duration;
+
+use libds::{
+    fms::{FmsConfig, FmsServer, FmsServerConfig},
+    match_timer::{MatchConfig, MatchPhase},
+    messages::{ds::tcp::MatchInfo, fms::StationStatus},
+    states::{Alliance, MatchType, RobotMode},
+    DriverStation,
+};
+
+use common::wait_for;
+
+fn connect(server: &FmsServer, team: u16) -> DriverStation {
+    let mut ds = DriverStation::new();
+    ds.connect_fms(FmsConfig {
+        control_port: 0,
+        status_port: server.status_port(),
+        tcp_port: server.tcp_port(),
+        ..FmsConfig::new(team, [127, 0, 0, 1].into())
+    })
+    .unwrap();
+    ds
+}
+
+#[test]
+fn restarting_reuses_the_ports() {
+    let config = FmsServerConfig {
+        status_port: UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port(),
+        tcp_port: TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port(),
+        ds_control_port: None,
+    };
+    for _ in 0..5 {
+        let server = FmsServer::start(config.clone()).unwrap();
+        assert_eq!(server.status_port(), config.status_port);
+    }
+}
+
+#[test]
+fn runs_a_match() {
+    let server = FmsServer::start(FmsServerConfig {
+        status_port: 0,
+        tcp_port: 0,
+        ds_control_port: None,
+    })
+    .unwrap();
+    server.assign(Alliance::Red(2), Some(639));
+    server.assign(Alliance::Blue(3), Some(254));
+
+    let red = connect(&server, 639);
+    let blue = connect(&server, 254);
+    assert!(wait_for(|| server
+        .stations()
+        .iter()
+        .filter(|s| s.connected)
+        .count()
+        == 2));
+    assert!(wait_for(
+        || red.fms_station_status() == Some(StationStatus::Good)
+    ));
+
+    server.start_match(MatchConfig {
+        countdown: Duration::from_millis(100),
+        auto: Duration::from_millis(400),
+        pause: Duration::from_millis(200),
+        teleop: Duration::from_millis(400),
+        match_info: Some(MatchInfo {
+            competition: "SCRIM".to_owned(),
+            match_type: MatchType::Practice,
+            match_number: 3,
+            replay_number: 0,
+        }),
+        game_data: Some("RLR".to_owned()),
+    });
+
+    assert!(wait_for(|| red.is_enabled() && blue.is_enabled()));
+    assert_eq!(server.phase(), MatchPhase::Auto);
+    assert_eq!(red.mode(), RobotMode::Auto);
+    assert_eq!(red.alliance(), Alliance::Red(2));
+    assert_eq!(blue.alliance(), Alliance::Blue(3));
+    assert!(wait_for(
+        || blue.game_data() == "RLR" && blue.match_info().competition == "SCRIM"
+    ));
+    assert_eq!(blue.match_info().match_number, 3);
+
+    server.estop(Alliance::Blue(3));
+    assert!(wait_for(|| blue.is_estopped()));
+    assert!(!blue.is_enabled());
+    assert!(red.is_enabled());
+
+    assert!(wait_for(
+        || red.mode() == RobotMode::Teleop && red.is_enabled()
+    ));
+    assert!(wait_for(|| server.phase() == MatchPhase::Finished));
+    assert!(wait_for(|| !red.is_enabled()));
+    assert!(server.stations()[5].estop);
+}
+
+#[test]
+fn unassigned_teams_wait() {
+    let server = FmsServer::start(FmsServerConfig {
+        status_port: 0,
+        tcp_port: 0,
+        ds_control_port: None,
+    })
+    .unwrap();
+
+    let ds = connect(&server, 1114);
+    assert!(wait_for(
+        || ds.fms_station_status() == Some(StationStatus::Waiting)
+    ));
+    server.assign(Alliance::Blue(1), Some(1114));
+    assert!(wait_for(
+        || ds.fms_station_status() == Some(StationStatus::Good)
+    ));
+}
@@ -0,0 +1,143 @@
+extern crate libds;
+
+mod common;
+
+use std::time::Duration;
+
+use libds::{
+    interlock::{BrownoutStorm, EnableRefusal, Interlocks},
+    joystick::Joystick,
+    messages::rio::Status,
+    mock::RobotStatus,
+};
+
+use common::{start, wait_for};
+
+fn brownout(brownout: bool) -> RobotStatus {
+    RobotStatus {
+        status: if brownout {
+            Status::BROWNOUT
+        } else {
+            Status::empty()
+        },
+        ..RobotStatus::default()
+    }
+}
+
+#[test]
+fn lost_comms_disable_the_robot() {
+    let (rio, ds) = start();
+    ds.set_interlocks(Interlocks {
+        comms_timeout: Some(Duration::from_millis(200)),
+        ..Interlocks::default()
+    });
+    assert!(wait_for(|| ds.can_enable().is_ok()));
+    ds.set_enabled(true).unwrap();
+    assert_eq!(ds.interlock_trip(), None);
+
+    drop(rio);
+    assert!(wait_for(|| !ds.is_enabled()));
+    assert_eq!(ds.interlock_trip(), Some(EnableRefusal::NoComms));
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::NoComms));
+}
+
+#[test]
+fn brownout_storm_refuses_enable() {
+    let (rio, ds) = start();
+    let window = Duration::from_secs(10);
+    ds.set_interlocks(Interlocks {
+        brownout_storm: Some(BrownoutStorm { count: 2, window }),
+        ..Interlocks::default()
+    });
+    assert!(wait_for(|| ds.has_robot_comms()));
+    ds.set_enabled(true).unwrap();
+
+    // Only the start of each brownout counts, however long it lasts.
+    rio.set_status(brownout(true));
+    assert!(wait_for(|| ds
+        .telemetry()
+        .status
+        .contains(Status::BROWNOUT)));
+    rio.set_status(brownout(false));
+    assert!(wait_for(|| !ds
+        .telemetry()
+        .status
+        .contains(Status::BROWNOUT)));
+    assert!(ds.is_enabled());
+
+    rio.set_status(brownout(true));
+    let storm = EnableRefusal::BrownoutStorm { count: 2, window };
+    assert!(wait_for(|| !ds.is_enabled()));
+    assert_eq!(ds.interlock_trip(), Some(storm.clone()));
+    assert_eq!(ds.set_enabled(true), Err(storm));
+
+    // The storm passes once the brownouts are older than the window.
+    ds.set_interlocks(Interlocks {
+        brownout_storm: Some(BrownoutStorm {
+            count: 2,
+            window: Duration::from_millis(1),
+        }),
+        ..Interlocks::default()
+    });
+    assert_eq!(ds.set_enabled(true), Ok(()));
+}
+
+#[test]
+fn missing_joystick_refuses_enable_until_attached() {
+    let (_rio, ds) = start();
+    ds.set_interlocks(Interlocks {
+        required_joysticks: vec![1],
+        ..Interlocks::default()
+    });
+    assert!(wait_for(|| ds.has_robot_comms()));
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::MissingJoystick(1)));
+
+    // A joystick in another slot doesn't count.
+    ds.set_joystick(0, Some(Joystick::new(10, 2, 1)));
+    assert_eq!(ds.can_enable(), Err(EnableRefusal::MissingJoystick(1)));
+
+    ds.set_joystick(1, Some(Joystick::new(10, 2, 1)));
+    ds.set_enabled(true).unwrap();
+
+    // Unplugging it while enabled disables the robot.
+    ds.set_joystick(1, None);
+    assert!(wait_for(|| !ds.is_enabled()));
+    assert_eq!(ds.interlock_trip(), Some(EnableRefusal::MissingJoystick(1)));
+}
+
+#[test]
+fn trip_clears_once_enabled_again() {
+    let (_rio, ds) = start();
+    ds.set_interlocks(Interlocks {
+        required_joysticks: vec![0],
+        ..Interlocks::default()
+    });
+    ds.set_joystick(0, Some(Joystick::new(10, 2, 1)));
+    assert!(wait_for(|| ds.has_robot_comms()));
+    ds.set_enabled(true).unwrap();
+
+    ds.set_joystick(0, None);
+    assert!(wait_for(|| ds.interlock_trip().is_some()));
+
+    // Putting the joystick back doesn't enable the robot by itself.
+    ds.set_joystick(0, Some(Joystick::new(10, 2, 1)));
+    assert!(wait_for(|| ds.can_enable().is_ok()));
+    assert!(!ds.is_enabled());
+    assert_eq!(ds.interlock_trip(), Some(EnableRefusal::MissingJoystick(0)));
+
+    ds.set_enabled(true).unwrap();
+    assert_eq!(ds.interlock_trip(), None);
+    assert!(ds.is_enabled());
+}
+
+#[test]
+fn estop_always_refuses_enable() {
+    let (rio, ds) = start();
+    assert!(wait_for(|| ds.has_robot_comms()));
+    rio.set_status(RobotStatus {
+        status: Status::ESTOP,
+        ..RobotStatus::default()
+    });
+    assert!(wait_for(|| ds.can_enable() == Err(EnableRefusal::EStopped)));
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::EStopped));
+}
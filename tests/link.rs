@@ -0,0 +1,96 @@
+extern crate libds;
+
+mod common;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libds::link::{LinkMonitor, LOSS_TIMEOUT};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn measures_trip_time() {
+    let start = Instant::now();
+    let mut link = LinkMonitor::new();
+    link.on_sent(0, start);
+    link.on_sent(1, start + ms(20));
+    link.on_received(0, start + ms(4));
+    link.on_received(1, start + ms(28));
+
+    let stats = link.stats(start + ms(30));
+    assert_eq!(stats.trip_time, Some(ms(8)));
+    assert_eq!(stats.average_trip_time, Some(ms(6)));
+    assert_eq!(stats.sent, 2);
+    assert_eq!(stats.received, 2);
+    assert_eq!(stats.out_of_order, 0);
+}
+
+#[test]
+fn counts_lost_packets_once_overdue() {
+    let start = Instant::now();
+    let mut link = LinkMonitor::new();
+    for i in 0..4 {
+        link.on_sent(i, start + ms(20 * u64::from(i)));
+    }
+    link.on_received(0, start + ms(5));
+    link.on_received(2, start + ms(45));
+
+    // Nothing is overdue yet.
+    assert_eq!(link.stats(start + ms(100)).packet_loss, 0.0);
+    // Packets 0 and 1 are overdue, and 1 has no reply.
+    assert_eq!(link.stats(start + ms(20) + LOSS_TIMEOUT).packet_loss, 0.5);
+    // All four are overdue, and 1 and 3 have no replies.
+    let stats = link.stats(start + ms(60) + LOSS_TIMEOUT);
+    assert_eq!(stats.packet_loss, 0.5);
+    assert_eq!(stats.lost, 2);
+}
+
+#[test]
+fn counts_lost_packets_after_they_leave_the_window() {
+    let start = Instant::now();
+    let mut link = LinkMonitor::new();
+    for i in 0..250 {
+        link.on_sent(i, start + ms(20 * u64::from(i)));
+        if i % 5 != 0 {
+            link.on_received(i, start + ms(20 * u64::from(i) + 3));
+        }
+    }
+
+    let stats = link.stats(start + ms(20 * 250) + LOSS_TIMEOUT);
+    assert_eq!(stats.lost, 50);
+    assert_eq!(stats.packet_loss, 0.2);
+    assert_eq!(stats.average_trip_time, Some(ms(3)));
+}
+
+#[test]
+fn detects_replies_out_of_order() {
+    let start = Instant::now();
+    let mut link = LinkMonitor::new();
+    for (i, sequence_num) in [0xfffe, 0xffff, 0x0000, 0x0001].iter().enumerate() {
+        link.on_sent(*sequence_num, start + ms(20 * i as u64));
+    }
+    link.on_received(0xfffe, start + ms(70));
+    // Sequence numbers wrap, so 0x0000 comes after 0xffff.
+    link.on_received(0x0000, start + ms(71));
+    link.on_received(0xffff, start + ms(72));
+    link.on_received(0x0001, start + ms(73));
+
+    let stats = link.stats(start + ms(80));
+    assert_eq!(stats.out_of_order, 1);
+    assert_eq!(stats.trip_time, Some(ms(13)));
+}
+
+#[test]
+fn driver_station_reports_link_stats() {
+    let (_rio, ds) = common::start();
+
+    thread::sleep(ms(500));
+    let stats = ds.link_stats();
+    assert!(stats.sent >= 10, "{:?}", stats);
+    assert!(stats.received >= 10, "{:?}", stats);
+    assert!(stats.trip_time.unwrap() < ms(100));
+    assert!(stats.packet_loss < 0.1, "{:?}", stats);
+}
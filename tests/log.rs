@@ -0,0 +1,329 @@
+extern crate libds;
+
+mod common;
+
+use libds::{
+    log::{DsEvents, DsEventsWriter, DsLog, DsLogRecord, DsLogWriter, Logger},
+    messages::ds::udp::Control,
+    messages::rio::{RioTcpPacket, RioUdpPacket, Status, Trace},
+    messages::{ReadError, ReadErrorKind},
+    pdp::{PdhLog, PdpLog},
+};
+
+use common::at;
+
+/// The header both files start with: version 3, then 2024-03-09 13:45:30.5, or `at(25)`, as a
+/// LabVIEW timestamp.
+const HEADER: [u8; 20] = [
+    0x00, 0x00, 0x00, 0x03, // version
+    0x00, 0x00, 0x00, 0x00, 0xe2, 0x12, 0x18, 0x7a, // seconds since 1904
+    0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // half a second
+];
+
+fn record() -> DsLogRecord {
+    let mut currents = [0.0; 16];
+    currents[0] = 10.0;
+    currents[1] = 0.125;
+    currents[6] = 1.0;
+    DsLogRecord {
+        trip_time_ms: 5.5,
+        packet_loss: 0.08,
+        battery_voltage: 12.5,
+        rio_cpu: 0.25,
+        control: Control::ENABLED | Control::TELEOP,
+        status: Status::ENABLED,
+        trace: Trace::ROBOT_CODE | Trace::TELEOP_MODE,
+        watchdog: false,
+        can_utilization: 0.5,
+        wifi_db: 0.0,
+        bandwidth_mb: 1.5,
+        pdp: PdpLog {
+            id: 1,
+            currents,
+            resistance: 0x10,
+            voltage: 0x20,
+            temperature: 0x30,
+        },
+        pdh: None,
+    }
+}
+
+#[test]
+fn dslog_records() {
+    let mut writer = DsLogWriter::new(Vec::new(), at(25)).unwrap();
+    writer.write_record(&record()).unwrap();
+
+    let bytes = writer.into_inner();
+    assert_eq!(bytes[..20], HEADER);
+    let mut pdp = [0u8; 25];
+    pdp[0] = 1;
+    pdp[1] = 0x14; // channel 0, 80 eighths starting at bit 8
+    pdp[3] = 0x10; // channel 1, 1 eighth ending at bit 27
+    pdp[9] = 0x02; // channel 6, 8 eighths starting at bit 72
+    pdp[22] = 0x10;
+    pdp[23] = 0x20;
+    pdp[24] = 0x30;
+    let mut expected = vec![
+        11, // trip time in half milliseconds
+        2,  // packet loss in 4%
+        0x0c, 0x80, // 12.5V in 256ths
+        50,   // cpu in half percent
+        0xdb, // ds teleop and robot teleop, inverted
+        100,  // can in half percent
+        0,    // wifi
+        0x01, 0x80, // 1.5Mb in 256ths
+    ];
+    expected.extend_from_slice(&pdp);
+    assert_eq!(bytes[20..], expected[..]);
+}
+
+#[test]
+fn dslog_status_flags() {
+    let mut record = record();
+    record.control = Control::AUTO;
+    record.status = Status::BROWNOUT;
+    record.trace = Trace::DISABLED;
+    record.watchdog = true;
+
+    let mut writer = DsLogWriter::new(Vec::new(), at(25)).unwrap();
+    writer.write_record(&record).unwrap();
+    // Brownout, watchdog, ds auto, ds disabled and robot disabled, inverted.
+    assert_eq!(writer.into_inner()[20 + 5], !0b1101_1001);
+}
+
+#[test]
+fn dsevents_messages() {
+    let mut writer = DsEventsWriter::new(Vec::new(), at(25)).unwrap();
+    let stdout = RioTcpPacket::StandardOutput {
+        timestamp: 12.25,
+        sequence_number: 1,
+        message: "hello".to_owned(),
+    };
+    writer.write_message(at(25), &stdout).unwrap();
+    // Not shown in the log viewer, so skipped.
+    let faults = RioTcpPacket::DisableFaults {
+        comms: 1,
+        twelve_v: 2,
+    };
+    writer.write_message(at(25), &faults).unwrap();
+
+    let bytes = writer.into_inner();
+    assert_eq!(bytes[..20], HEADER);
+    let text = b"<TagVersion>1 <time> 12.250 <message> hello ";
+    assert_eq!(bytes[20..36], HEADER[4..]);
+    assert_eq!(bytes[36..40], (text.len() as i32).to_be_bytes());
+    assert_eq!(bytes[40..], text[..]);
+}
+
+#[test]
+fn error_message_text() {
+    let error = RioTcpPacket::ErrorMessage {
+        timestamp: 3.5,
+        sequence_number: 2,
+        print_msg: true,
+        error_code: 44004,
+        is_error: true,
+        details: "Joystick unplugged".to_owned(),
+        location: "Robot.java".to_owned(),
+        call_stack: "main".to_owned(),
+    };
+    assert_eq!(
+        libds::log::event_text(&error).unwrap(),
+        "<TagVersion>1 <time> 3.500 <count> 1 <flags> 1 <Code> 44004 \
+         <details> Joystick unplugged <location> Robot.java <stack> main "
+    );
+}
+
+#[test]
+fn status_tag_usage() {
+    let mut cpu = vec![2];
+    for percent in &[10.0f32, 5.0, 20.0, 5.0, 0.0, 0.0, 10.0, 0.0] {
+        cpu.extend_from_slice(&percent.to_be_bytes());
+    }
+    let mut can = 30.0f32.to_be_bytes().to_vec();
+    can.extend_from_slice(&[0; 10]);
+    let packet = RioUdpPacket {
+        sequence_num: 0,
+        comm_version: 0x01,
+        status: Status::empty(),
+        trace: Trace::empty(),
+        battery_voltage: 12.0,
+        request_date: false,
+        tags: vec![(0x05, &cpu[..]), (0x0e, &can[..])]
+            .into_iter()
+            .collect(),
+    };
+    assert_eq!(packet.cpu_usage(), Ok(Some(0.25)));
+    assert_eq!(packet.can_utilization(), Some(0.3));
+    assert_eq!(packet.tag(0x04), None);
+}
+
+#[test]
+fn dslog_reads_back() {
+    let mut auto = record();
+    auto.control = Control::AUTO;
+    auto.status = Status::BROWNOUT;
+    auto.trace = Trace::DISABLED;
+    auto.watchdog = true;
+
+    let mut writer = DsLogWriter::new(Vec::new(), at(25)).unwrap();
+    writer.write_record(&record()).unwrap();
+    writer.write_record(&auto).unwrap();
+    let mut bytes = writer.into_inner();
+    // A record cut off part way through, as left by a crash.
+    bytes.extend_from_slice(&[0x01, 0x02, 0x03]);
+
+    let log = DsLog::from_bytes(&bytes).unwrap();
+    assert_eq!(log.start, at(25));
+    // Only brownouts and the mode echoes of the trace are recorded.
+    let mut expected = record();
+    expected.status = Status::empty();
+    expected.trace = Trace::TELEOP_MODE;
+    assert_eq!(log.records, vec![expected, auto]);
+    assert_eq!(log.time_of(1), at(26));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = HEADER.to_vec();
+    bytes[3] = 0x05;
+    let error = ReadError {
+        offset: 0,
+        kind: ReadErrorKind::Invalid("log version"),
+    };
+    assert_eq!(DsLog::from_bytes(&bytes), Err(error.clone()));
+    assert_eq!(DsEvents::from_bytes(&bytes), Err(error.clone()));
+
+    bytes[3] = 0x02;
+    assert_eq!(DsLog::from_bytes(&bytes), Err(error));
+}
+
+#[test]
+fn dslog_version_4_power_distribution() {
+    let mut writer = DsLogWriter::new(Vec::new(), at(25)).unwrap();
+    writer.write_record(&record()).unwrap();
+    let version_3 = writer.into_inner();
+    // The parts of a record before and in its version 3 power distribution section.
+    let common = &version_3[20..30];
+    let pdp = &version_3[31..55];
+
+    let mut bytes = HEADER.to_vec();
+    bytes[3] = 0x04;
+    // A CTRE panel, recorded like version 3 without the id.
+    bytes.extend_from_slice(common);
+    bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x19]);
+    bytes.extend_from_slice(pdp);
+    // A REV hub with 10A on channel 0, 1A on channel 3 and 2A on channel 20.
+    bytes.extend_from_slice(common);
+    bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x21]);
+    let mut rev = [0u8; 32];
+    rev[0] = 0x14; // 80 eighths in the first 10 bits
+    rev[4] = 0x02; // 8 eighths in the first 10 bits of the second block
+    rev[27] = 32; // 32 sixteenths
+    bytes.extend_from_slice(&rev);
+    // No power distribution.
+    bytes.extend_from_slice(common);
+    bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    // A REV record cut off part way through.
+    bytes.extend_from_slice(common);
+    bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x21, 0x14]);
+
+    let log = DsLog::from_bytes(&bytes).unwrap();
+    assert_eq!(log.start, at(25));
+    assert_eq!(log.records.len(), 3);
+
+    let mut ctre = record();
+    ctre.status = Status::empty();
+    ctre.trace = Trace::TELEOP_MODE;
+    ctre.pdp.id = 0;
+    assert_eq!(log.records[0], ctre);
+
+    let mut currents = [0.0; 24];
+    currents[0] = 10.0;
+    currents[3] = 1.0;
+    currents[20] = 2.0;
+    assert_eq!(log.records[1].pdp, PdpLog::default());
+    assert_eq!(log.records[1].pdh, Some(PdhLog { currents }));
+    assert_eq!(log.records[1].total_current(), 13.0);
+    assert_eq!(log.records[1].battery_voltage, ctre.battery_voltage);
+
+    assert_eq!(log.records[2].pdp, PdpLog::default());
+    assert_eq!(log.records[2].pdh, None);
+
+    // The events file didn't change.
+    let mut events = HEADER.to_vec();
+    events[3] = 0x04;
+    assert_eq!(DsEvents::from_bytes(&events).unwrap().start, at(25));
+}
+
+#[test]
+fn dsevents_read_back() {
+    let messages = vec![
+        RioTcpPacket::StandardOutput {
+            timestamp: 12.25,
+            sequence_number: 0,
+            message: "hello <world>".to_owned(),
+        },
+        RioTcpPacket::ErrorMessage {
+            timestamp: 3.5,
+            sequence_number: 0,
+            print_msg: true,
+            error_code: 1,
+            is_error: false,
+            details: "Loop time overrun".to_owned(),
+            location: "".to_owned(),
+            call_stack: "at Robot.<init>(Robot.java:12)".to_owned(),
+        },
+        RioTcpPacket::RadioEvent("Radio connected".to_owned()),
+    ];
+
+    let mut writer = DsEventsWriter::new(Vec::new(), at(25)).unwrap();
+    for message in &messages {
+        writer.write_message(at(25), message).unwrap();
+    }
+    writer.write_event(at(25), "Some note").unwrap();
+
+    let events = DsEvents::from_bytes(&writer.into_inner()).unwrap();
+    assert_eq!(events.start, at(25));
+    assert_eq!(events.events.len(), 4);
+    assert!(events.events.iter().all(|event| event.time == at(25)));
+    let parsed: Vec<_> = events.events.iter().filter_map(|e| e.message()).collect();
+    assert_eq!(parsed, messages);
+}
+
+#[test]
+fn dsevents_truncated_entry() {
+    let mut writer = DsEventsWriter::new(Vec::new(), at(25)).unwrap();
+    writer.write_event(at(25), "cut off").unwrap();
+    let mut bytes = writer.into_inner();
+    bytes.truncate(bytes.len() - 1);
+    assert_eq!(
+        DsEvents::from_bytes(&bytes),
+        Err(ReadError {
+            offset: 40,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 7 },
+        })
+    );
+}
+
+#[test]
+fn logger_files_open() {
+    let dir = std::env::temp_dir().join(format!("libds-log-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut logger = Logger::create(&dir, at(25)).unwrap();
+    assert_eq!(
+        logger.dslog_path(),
+        dir.join("2024_03_09 13_45_30 Sat.dslog")
+    );
+    logger.log(&record()).unwrap();
+    logger.log_event(at(25), "Some note").unwrap();
+    logger.flush().unwrap();
+
+    assert_eq!(DsLog::open(logger.dslog_path()).unwrap().records.len(), 1);
+    assert_eq!(
+        DsEvents::open(logger.dsevents_path()).unwrap().events[0].text,
+        "Some note"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
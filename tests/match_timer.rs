@@ -0,0 +1,132 @@
+extern crate libds;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use libds::{
+    clock::Clock,
+    interlock::{EnableRefusal, Interlocks},
+    match_timer::{MatchConfig, MatchPhase, MatchTimer},
+    messages::ds::tcp::MatchInfo,
+    states::{MatchType, RobotMode},
+    DriverStation,
+};
+
+#[derive(Clone)]
+struct ManualClock(Rc<Cell<Instant>>);
+
+impl ManualClock {
+    fn new() -> Self {
+        ManualClock(Rc::new(Cell::new(Instant::now())))
+    }
+
+    fn advance(&self, secs: f32) {
+        self.0.set(self.0.get() + Duration::from_secs_f32(secs));
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
+
+fn config() -> MatchConfig {
+    MatchConfig {
+        match_info: Some(MatchInfo {
+            competition: "practice".to_owned(),
+            match_type: MatchType::Practice,
+            match_number: 12,
+            replay_number: 1,
+        }),
+        game_data: Some("LRL".to_owned()),
+        ..MatchConfig::default()
+    }
+}
+
+#[test]
+fn runs_through_every_phase() {
+    let ds = DriverStation::new();
+    let clock = ManualClock::new();
+    let mut timer = MatchTimer::with_clock(config(), clock.clone());
+
+    timer.start(&ds);
+    assert_eq!(timer.phase(), MatchPhase::Countdown);
+    assert_eq!(timer.remaining(), Duration::from_secs(3));
+    assert_eq!(ds.match_info().competition, "practice");
+    assert!(!ds.is_enabled());
+
+    clock.advance(3.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Auto));
+    assert_eq!(ds.mode(), RobotMode::Auto);
+    assert_eq!(ds.game_data(), "LRL");
+    assert!(ds.is_enabled());
+
+    clock.advance(10.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Auto));
+    assert_eq!(timer.remaining(), Duration::from_secs(5));
+
+    clock.advance(5.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Pause));
+    assert!(!ds.is_enabled());
+
+    clock.advance(3.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Teleop));
+    assert_eq!(ds.mode(), RobotMode::Teleop);
+    assert!(ds.is_enabled());
+    assert_eq!(timer.remaining(), Duration::from_secs(135));
+
+    clock.advance(135.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Finished));
+    assert!(!ds.is_enabled());
+    assert_eq!(timer.remaining(), Duration::from_secs(0));
+}
+
+#[test]
+fn catches_up_after_a_long_gap() {
+    let ds = DriverStation::new();
+    let clock = ManualClock::new();
+    let mut timer = MatchTimer::with_clock(config(), clock.clone());
+
+    timer.start(&ds);
+    clock.advance(30.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Teleop));
+    assert_eq!(ds.game_data(), "LRL");
+    assert!(ds.is_enabled());
+}
+
+#[test]
+fn stop_disables_the_robot() {
+    let ds = DriverStation::new();
+    let clock = ManualClock::new();
+    let mut timer = MatchTimer::with_clock(config(), clock.clone());
+
+    timer.start(&ds);
+    clock.advance(5.0);
+    timer.update(&ds).unwrap();
+    timer.stop(&ds);
+    assert_eq!(timer.phase(), MatchPhase::Finished);
+    assert!(!ds.is_enabled());
+
+    clock.advance(20.0);
+    assert_eq!(timer.update(&ds), Ok(MatchPhase::Finished));
+    assert!(!ds.is_enabled());
+}
+
+#[test]
+fn reports_interlock_refusals() {
+    let ds = DriverStation::new();
+    ds.set_interlocks(Interlocks {
+        comms_timeout: Some(Duration::from_secs(1)),
+        ..Interlocks::default()
+    });
+    let clock = ManualClock::new();
+    let mut timer = MatchTimer::with_clock(config(), clock.clone());
+
+    timer.start(&ds);
+    clock.advance(3.0);
+    assert_eq!(timer.update(&ds), Err(EnableRefusal::NoComms));
+    assert_eq!(timer.phase(), MatchPhase::Auto);
+    assert!(!ds.is_enabled());
+}
@@ -0,0 +1,397 @@
+extern crate chrono;
+extern crate libds;
+
+use chrono::{TimeZone, Utc};
+
+use libds::{
+    joystick::{AxisType, Joystick, JoystickType, OutOfRange},
+    messages::ds::tcp::{JoystickDescriptor, MatchInfo, Tag, TcpTag},
+    messages::ds::udp::{Control, DsUdpPacket, Request},
+    messages::fms::FmsControlPacket,
+    messages::rio::{RioTcpPacket, RioUdpPacket, StatusTags},
+    messages::{ReadError, ReadErrorKind},
+    states::{Alliance, MatchType, RobotMode},
+    version::ProtocolVersion,
+};
+
+#[test]
+fn match_info_packet() {
+    let info = MatchInfo {
+        competition: "NYRO".to_owned(),
+        match_type: MatchType::Qualification,
+        match_number: 258,
+        replay_number: 2,
+    };
+    assert_eq!(
+        info.to_packet(),
+        vec![0x00, 0x0a, 0x07, 0x04, b'N', b'Y', b'R', b'O', 0x02, 0x01, 0x02, 0x02]
+    );
+}
+
+#[test]
+fn ds_udp_packet() {
+    let mut stick = Joystick::new(3, 1, 1);
+    stick.set_button(0, true).unwrap();
+    stick.set_axis(0, -1).unwrap();
+    stick.set_pov(0, 90).unwrap();
+    assert_eq!(
+        stick.set_button(3, true),
+        Err(OutOfRange { index: 3, count: 3 })
+    );
+
+    let packet = DsUdpPacket {
+        sequence_num: 0x0102,
+        comm_version: 0x01,
+        control: Control::ENABLED | Control::AUTO,
+        request: Request::empty(),
+        alliance: Alliance::Blue(2),
+        joysticks: vec![Some(stick), None],
+        countdown: Some(15.0),
+        date: None,
+        timezone: None,
+        tags: Vec::new(),
+    };
+    let bytes = vec![
+        0x01, 0x02, 0x01, 0x06, 0x00, 0x04, // header
+        0x08, 0x0c, 0x01, 0xff, 0x03, 0x80, 0x01, 0x00, 0x5a, // joystick
+        0x01, 0x0c, // empty joystick
+        0x05, 0x07, 0x41, 0x70, 0x00, 0x00, // countdown
+    ];
+    assert_eq!(packet.encode(), bytes);
+
+    let mut buf = vec![0xaa; 64];
+    packet.encode_into(&mut buf);
+    assert_eq!(buf, bytes);
+
+    let decoded = DsUdpPacket::decode(&bytes).unwrap();
+    assert_eq!(decoded.control.robot_mode(), Some(RobotMode::Auto));
+    assert_eq!(decoded, packet);
+}
+
+#[test]
+fn date_and_timezone_tags() {
+    let packet = DsUdpPacket {
+        sequence_num: 0,
+        comm_version: 0x01,
+        control: Control::empty(),
+        request: Request::empty(),
+        alliance: Alliance::Red(1),
+        joysticks: Vec::new(),
+        countdown: None,
+        date: Some(Utc.ymd(2024, 3, 9).and_hms_micro(13, 45, 30, 123_456)),
+        timezone: Some("America/New_York".to_owned()),
+        tags: Vec::new(),
+    };
+
+    let mut bytes = vec![0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    bytes.extend(&[0x11, 0x10]);
+    bytes.extend(b"America/New_York");
+    bytes.extend(&[
+        0x0b, 0x0f, // date tag
+        0x00, 0x01, 0xe2, 0x40, // microseconds
+        30, 45, 13, // seconds, minutes, hours
+        9, 2, 124, // day of the month, month from 0, years since 1900
+    ]);
+    assert_eq!(packet.encode(), bytes);
+    assert_eq!(DsUdpPacket::decode(&bytes), Ok(packet));
+}
+
+#[test]
+fn date_tag_at_year_boundaries() {
+    let mut packet = DsUdpPacket::decode(&[0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).unwrap();
+
+    packet.date = Some(Utc.ymd(1999, 12, 31).and_hms(23, 59, 59));
+    assert_eq!(
+        &packet.encode()[6..],
+        &[0x0b, 0x0f, 0, 0, 0, 0, 59, 59, 23, 31, 11, 99]
+    );
+
+    packet.date = Some(Utc.ymd(2000, 1, 1).and_hms(0, 0, 0));
+    assert_eq!(
+        &packet.encode()[6..],
+        &[0x0b, 0x0f, 0, 0, 0, 0, 0, 0, 0, 1, 0, 100]
+    );
+}
+
+#[test]
+fn fms_control_packet_date() {
+    let packet = FmsControlPacket {
+        sequence_num: 7,
+        estop: false,
+        enabled: true,
+        mode: RobotMode::Teleop,
+        alliance: Alliance::Blue(3),
+        match_type: MatchType::Elimination,
+        match_number: 4,
+        replay_number: 1,
+        time: Utc.ymd(2024, 1, 31).and_hms_micro(9, 8, 7, 6),
+        remaining_seconds: 135,
+    };
+    let bytes = packet.to_bytes();
+    assert_eq!(
+        &bytes[10..],
+        &[
+            0, 0, 0, 6, // microseconds
+            7, 8, 9, // seconds, minutes, hours
+            31, 0, 124, // day of the month, month from 0, years since 1900
+            0, 135, // seconds remaining
+        ]
+    );
+    assert_eq!(FmsControlPacket::from_bytes(&bytes), Ok(packet));
+}
+
+#[test]
+fn decode_errors_report_offsets() {
+    assert_eq!(
+        DsUdpPacket::decode(&[0x00, 0x00, 0x01]),
+        Err(ReadError {
+            offset: 3,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 1 },
+        })
+    );
+
+    // A countdown tag one byte short.
+    let bytes = [
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x07, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(
+        DsUdpPacket::decode(&bytes),
+        Err(ReadError {
+            offset: 8,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 4 },
+        })
+    );
+
+    // An error message whose details claim more bytes than the tag has.
+    let mut bytes = vec![0x0b, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend(&[0x00, 0x10, b'h', b'i']);
+    assert_eq!(
+        RioTcpPacket::from_bytes(&bytes),
+        Err(ReadError {
+            offset: 13,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 16 },
+        })
+    );
+
+    assert_eq!(
+        RioTcpPacket::from_bytes(&[0x0a, 0x03]),
+        Err(ReadError {
+            offset: 1,
+            kind: ReadErrorKind::Invalid("device type"),
+        })
+    );
+}
+
+#[test]
+fn decode_rejects_trailing_tag_bytes() {
+    let header = [0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    let tags: [&[u8]; 3] = [
+        // A countdown with a byte after the float.
+        &[0x06, 0x07, 0x41, 0x20, 0x00, 0x00, 0xff],
+        // A joystick with no axes, buttons or POVs and then a stray byte.
+        &[0x05, 0x0c, 0x00, 0x00, 0x00, 0xff],
+        // 2024-03-09 13:45:30 with a byte after the year.
+        &[0x0c, 0x0f, 0, 0, 0, 0, 30, 45, 13, 9, 2, 124, 0xff],
+    ];
+    for tag in &tags {
+        let mut bytes = header.to_vec();
+        bytes.extend_from_slice(tag);
+        assert_eq!(
+            DsUdpPacket::decode(&bytes),
+            Err(ReadError {
+                offset: bytes.len() - 1,
+                kind: ReadErrorKind::TrailingBytes(1),
+            })
+        );
+    }
+
+    assert_eq!(
+        Joystick::from_udp_tag(&[0x00, 0x00, 0x00, 0xff]),
+        Err(ReadError {
+            offset: 3,
+            kind: ReadErrorKind::TrailingBytes(1),
+        })
+    );
+
+    // TCP tags are the rest of their frame, so anything after the last field is left over.
+    assert_eq!(
+        RioTcpPacket::from_bytes(&[0x04, 0x00, 0x01, 0x00, 0x02, 0xff]),
+        Err(ReadError {
+            offset: 5,
+            kind: ReadErrorKind::TrailingBytes(1),
+        })
+    );
+    assert_eq!(
+        TcpTag::from_bytes(&[0x07, 0x00, 0x02, 0x01, 0x02, 0x02, 0xff, 0xff]),
+        Err(ReadError {
+            offset: 6,
+            kind: ReadErrorKind::TrailingBytes(2),
+        })
+    );
+}
+
+#[test]
+fn encoding_cuts_off_what_the_lengths_cannot_hold() {
+    let mut packet = DsUdpPacket::decode(&[0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).unwrap();
+    packet.tags.push((0x20, vec![0xaa; 300]));
+    let bytes = packet.encode();
+    assert_eq!(&bytes[6..8], &[0xff, 0x20]);
+    assert_eq!(bytes.len(), 6 + 256);
+
+    let output = RioTcpPacket::StandardOutput {
+        timestamp: 0.0,
+        sequence_number: 0,
+        message: "x".repeat(70_000),
+    };
+    let bytes = output.to_packet();
+    assert_eq!(&bytes[..2], &[0xff, 0xff]);
+    assert_eq!(bytes.len(), 2 + 65535);
+
+    let error = RioTcpPacket::ErrorMessage {
+        timestamp: 0.0,
+        sequence_number: 0,
+        print_msg: false,
+        error_code: 1,
+        is_error: true,
+        details: "d".repeat(40_000),
+        location: "l".repeat(40_000),
+        call_stack: "c".repeat(10),
+    };
+    let bytes = error.to_packet();
+    assert_eq!(bytes.len(), 2 + 65535);
+    match RioTcpPacket::from_bytes(&bytes[2..]) {
+        Ok(RioTcpPacket::ErrorMessage {
+            details,
+            location,
+            call_stack,
+            ..
+        }) => {
+            assert_eq!(details.len(), 40_000);
+            assert_eq!(location.len(), 65535 - 17 - 40_000);
+            assert!(call_stack.is_empty());
+        }
+        other => panic!("expected an error message, got {:?}", other),
+    }
+
+    let descriptor = JoystickDescriptor {
+        index: 0,
+        is_xbox: false,
+        stick_type: JoystickType::Unknown,
+        name: String::new(),
+        axis_types: vec![AxisType::X; 300],
+        button_count: 0,
+        pov_count: 0,
+    };
+    match TcpTag::from_bytes(&descriptor.to_packet()[2..]) {
+        Ok(TcpTag::JoystickDescriptor(decoded)) => assert_eq!(decoded.axis_types.len(), 255),
+        other => panic!("expected a joystick descriptor, got {:?}", other),
+    }
+}
+
+#[test]
+fn status_tags_borrow_the_packet() {
+    let bytes = [
+        0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x00, 0x00, 0x02, 0x05, 0xaa, 0x01, 0x0e,
+    ];
+    let packet = RioUdpPacket::from_bytes(&bytes).unwrap();
+    assert_eq!(packet.tags.as_bytes().as_ptr(), bytes[8..].as_ptr());
+    assert_eq!(
+        packet.tags.iter().collect::<Vec<_>>(),
+        vec![(0x05, &[0xaa][..]), (0x0e, &[][..])]
+    );
+    assert_eq!(packet.tag(0x0e), Some(&[][..]));
+    assert_eq!(packet.tag(0x08), None);
+
+    let mut tags = StatusTags::new();
+    tags.push(0x05, &[0xaa]);
+    tags.push(0x0e, &[]);
+    assert_eq!(packet.tags, tags);
+
+    // The last tag claims a byte the packet doesn't have.
+    assert_eq!(
+        RioUdpPacket::from_bytes(&bytes[..12]),
+        Err(ReadError {
+            offset: 12,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 1 },
+        })
+    );
+}
+
+#[test]
+fn cpu_tag_with_many_cores() {
+    let mut bytes = vec![0x00, 0x01, 0x01, 0x00, 0x30, 0x0c, 0x00, 0x00];
+    let mut tag = vec![0x05, 64];
+    for _ in 0..4 {
+        tag.extend(&12.5f32.to_be_bytes());
+    }
+    bytes.push(tag.len() as u8);
+    bytes.extend(&tag);
+
+    // 64 cores need 1024 bytes of percentages, more than any tag can hold.
+    let packet = RioUdpPacket::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        packet.cpu_usage(),
+        Err(ReadError {
+            offset: 17,
+            kind: ReadErrorKind::UnexpectedEnd { needed: 4 },
+        })
+    );
+
+    bytes[10] = 1;
+    let packet = RioUdpPacket::from_bytes(&bytes).unwrap();
+    assert_eq!(packet.cpu_usage(), Ok(Some(0.5)));
+}
+
+#[test]
+fn tcp_tags_in_each_protocol_version() {
+    let info = MatchInfo {
+        competition: "NYRO".to_owned(),
+        match_type: MatchType::Qualification,
+        match_number: 258,
+        replay_number: 2,
+    };
+    assert_eq!(
+        info.to_packet_for(ProtocolVersion::Frc2018),
+        vec![0x00, 0x07, 0x07, 0x04, b'N', b'Y', b'R', b'O', 0x02]
+    );
+    assert_eq!(
+        info.to_packet_for(ProtocolVersion::Frc2019),
+        info.to_packet()
+    );
+
+    let descriptor = JoystickDescriptor {
+        index: 1,
+        is_xbox: true,
+        stick_type: JoystickType::XInputGamepad,
+        name: "pad".to_owned(),
+        axis_types: vec![AxisType::X, AxisType::Y],
+        button_count: 10,
+        pov_count: 1,
+    };
+    let old = descriptor.to_packet_for(ProtocolVersion::Frc2018);
+    assert_eq!(old.len() + 1, descriptor.to_packet().len());
+    assert_eq!(
+        TcpTag::from_bytes_for(&old[2..], ProtocolVersion::Frc2018),
+        Ok(TcpTag::JoystickDescriptor(descriptor))
+    );
+}
+
+#[test]
+fn protocol_version_from_comm_version() {
+    for version in ProtocolVersion::ALL.iter().filter(|v| v.is_detected()) {
+        assert_eq!(
+            ProtocolVersion::from_comm_version(version.comm_version()),
+            *version
+        );
+    }
+    // The 2018 layout has to be forced, whatever the robot reports.
+    assert_eq!(
+        ProtocolVersion::from_comm_version(ProtocolVersion::Frc2018.comm_version()),
+        ProtocolVersion::CURRENT
+    );
+    assert_eq!(
+        ProtocolVersion::from_comm_version(0xff),
+        ProtocolVersion::CURRENT
+    ); // What the driver station has always sent.
+    assert_eq!(ProtocolVersion::CURRENT.comm_version(), 0x01);
+}
@@ -0,0 +1,215 @@
+extern crate chrono;
+extern crate libds;
+
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use libds::{
+    clock::WallClock,
+    interlock::{EnableRefusal, Interlocks},
+    joystick::{Joystick, JoystickType},
+    messages::ds::tcp::TcpTag,
+    messages::ds::udp::Control,
+    messages::rio::{Status, Trace},
+    mock::RobotStatus,
+    states::{Alliance, RobotMode},
+    version::ProtocolVersion,
+    ConnectionConfig, DriverStation,
+};
+
+use common::{local_config, start, start_mock, wait_for};
+
+#[test]
+fn mock_rio_records_control_packets() {
+    let (rio, ds) = start();
+
+    let mut stick = Joystick::new(10, 2, 1);
+    stick.set_button(0, true).unwrap();
+    stick.set_button(9, true).unwrap();
+    stick.set_axis(1, -100).unwrap();
+    stick.set_pov(0, 270).unwrap();
+    ds.set_joystick(1, Some(stick.clone()));
+    assert_eq!(ds.joysticks()[1], Some(stick.clone()));
+    ds.set_mode(RobotMode::Auto);
+    ds.set_alliance(Alliance::Blue(2));
+    ds.set_enabled(true).unwrap();
+
+    assert!(wait_for(|| rio.last_control_packet().is_some_and(
+        |packet| packet.control.contains(Control::ENABLED)
+    )));
+
+    let packet = rio.last_control_packet().unwrap();
+    assert_eq!(packet.control.robot_mode(), Some(RobotMode::Auto));
+    assert_eq!(packet.alliance, Alliance::Blue(2));
+    assert!(!packet.control.contains(Control::ESTOP));
+    assert!(!packet.control.contains(Control::FMS_CONNECTED));
+    assert_eq!(packet.joysticks.len(), 6);
+    assert_eq!(packet.joysticks[0], None);
+    assert_eq!(packet.joysticks[1], Some(stick));
+    assert_eq!(
+        packet.joysticks[1].as_ref().unwrap().stick_type(),
+        JoystickType::Unknown
+    );
+
+    let packets = rio.control_packets();
+    for pair in packets.windows(2) {
+        assert_eq!(pair[1].sequence_num, pair[0].sequence_num.wrapping_add(1));
+    }
+}
+
+#[test]
+fn mock_rio_records_tcp_tags() {
+    let (rio, ds) = start();
+
+    // Game data and match info are sent as soon as the connection is made.
+    assert!(wait_for(|| rio.tcp_tags().len() >= 2));
+    assert_eq!(rio.tcp_tags()[0][0], 0x0e);
+    assert_eq!(rio.tcp_tags()[1][0], 0x07);
+
+    ds.set_game_data("LLL".to_string());
+    assert!(wait_for(|| rio.tcp_tags().len() >= 3));
+    assert_eq!(rio.tcp_tags()[2], b"\x0eLLL".to_vec());
+}
+
+#[test]
+fn scripted_status_reaches_driver_station() {
+    let (rio, ds) = start();
+    ds.set_interlocks(Interlocks {
+        require_robot_code: true,
+        comms_timeout: Some(Duration::from_millis(500)),
+        ..Interlocks::default()
+    });
+
+    rio.set_status(RobotStatus {
+        status: Status::empty(),
+        trace: Trace::IS_ROBORIO,
+        battery_voltage: 11.5,
+        ..RobotStatus::default()
+    });
+    assert!(wait_for(
+        || ds.has_robot_comms() && ds.can_enable() == Err(EnableRefusal::NoRobotCode)
+    ));
+    assert!(!ds.has_robot_code());
+
+    rio.set_status(RobotStatus {
+        battery_voltage: 12.25,
+        ..RobotStatus::default()
+    });
+    assert!(wait_for(|| ds.can_enable().is_ok()));
+    assert!((ds.battery_voltage() - 12.25).abs() < 0.01);
+    assert!(ds.has_robot_code());
+}
+
+#[test]
+fn estop_disables_and_refuses_enable() {
+    let (rio, ds) = start();
+    assert!(wait_for(|| ds.has_robot_comms()));
+    ds.set_enabled(true).unwrap();
+
+    ds.estop();
+    assert!(!ds.is_enabled());
+    assert!(ds.is_estopped());
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::EStopped));
+    let stopped = Control::ESTOP | Control::ENABLED;
+    assert!(wait_for(|| rio.control_packets().last().is_some_and(
+        |packet| packet.control & stopped == Control::ESTOP
+    )));
+}
+
+#[test]
+fn reconnecting_reuses_the_port() {
+    let rio = start_mock();
+    let mut config = local_config(rio.udp_port(), rio.tcp_port());
+    config.ds_udp_port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut ds = DriverStation::new();
+    for _ in 0..5 {
+        ds.connect_with(config.clone()).unwrap();
+    }
+    assert!(wait_for(|| ds.has_robot_comms()));
+}
+
+#[test]
+fn team_number_addresses() {
+    let address = |team| ConnectionConfig::for_team(team).address.to_string();
+    assert_eq!(address(254), "10.2.54.2");
+    assert_eq!(address(1678), "10.16.78.2");
+    assert_eq!(address(9), "10.0.9.2");
+}
+
+struct FixedClock(DateTime<Utc>);
+
+impl WallClock for FixedClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[test]
+fn date_is_sent_when_requested() {
+    let (rio, ds) = start();
+    let date = Utc.ymd(2025, 4, 17).and_hms_micro(18, 2, 3, 500);
+    ds.set_wall_clock(FixedClock(date));
+    ds.set_timezone("Europe/Berlin".to_owned());
+
+    assert!(wait_for(|| !rio.control_packets().is_empty()));
+    assert_eq!(rio.last_control_packet().unwrap().date, None);
+
+    rio.set_status(RobotStatus {
+        request_date: true,
+        ..RobotStatus::default()
+    });
+    assert!(wait_for(|| rio
+        .last_control_packet()
+        .is_some_and(|packet| packet.date.is_some())));
+
+    let packet = rio.last_control_packet().unwrap();
+    assert_eq!(packet.date, Some(date));
+    assert_eq!(packet.timezone, Some("Europe/Berlin".to_owned()));
+}
+
+#[test]
+fn follows_the_robots_protocol_version() {
+    let (rio, ds) = start();
+    assert!(wait_for(|| rio.tcp_tags().len() >= 2));
+    assert_eq!(ds.protocol_version(), ProtocolVersion::CURRENT);
+
+    // A robot reporting the comm version assumed for 2018 still gets the current layouts.
+    rio.set_status(RobotStatus {
+        comm_version: ProtocolVersion::Frc2018.comm_version(),
+        ..RobotStatus::default()
+    });
+    let sent = rio.control_packets().len();
+    assert!(wait_for(|| rio.control_packets().len() >= sent + 5));
+    assert_eq!(ds.protocol_version(), ProtocolVersion::CURRENT);
+    assert_eq!(rio.last_control_packet().unwrap().comm_version, 0x01);
+
+    ds.set_protocol_version(Some(ProtocolVersion::Frc2018));
+    assert!(wait_for(|| rio
+        .last_control_packet()
+        .is_some_and(|packet| packet.comm_version == 0x00)));
+    assert_eq!(ds.protocol_version(), ProtocolVersion::Frc2018);
+
+    // Match info is sent again without the match and replay numbers.
+    assert!(wait_for(|| rio.tcp_tags().len() >= 3));
+    let tag = &rio.tcp_tags()[2];
+    assert_eq!(
+        TcpTag::from_bytes_for(tag, ProtocolVersion::Frc2018),
+        Ok(TcpTag::MatchInfo(ds.match_info()))
+    );
+    assert_eq!(tag.len(), 2 + "unknown".len() + 1);
+
+    ds.set_protocol_version(None);
+    assert!(wait_for(|| rio
+        .last_control_packet()
+        .is_some_and(|packet| packet.comm_version == 0x01)));
+    assert_eq!(ds.protocol_version(), ProtocolVersion::Frc2019);
+}
@@ -0,0 +1,120 @@
+extern crate libds;
+
+mod common;
+
+use std::time::Duration;
+
+use libds::{
+    log::{
+        export::{Column, ExportConfig, Format, TelemetryExporter},
+        DsLogRecord,
+    },
+    messages::ds::udp::Control,
+    messages::rio::{RioUdpPacket, Status, Trace},
+    mock::RobotStatus,
+    pdp::{PdpHistory, PdpLog, PdpSample},
+    DriverStation,
+};
+
+use common::at;
+
+fn pdp() -> PdpLog {
+    let mut currents = [0.0; 16];
+    for (channel, current) in currents.iter_mut().enumerate() {
+        *current = channel as f32 * 2.5;
+    }
+    PdpLog {
+        id: 0,
+        currents,
+        resistance: 0,
+        voltage: 160,
+        temperature: 90,
+    }
+}
+
+#[test]
+fn decodes_status_tag() {
+    let packet = RioUdpPacket {
+        sequence_num: 1,
+        comm_version: 1,
+        status: Status::empty(),
+        trace: Trace::empty(),
+        battery_voltage: 12.0,
+        request_date: false,
+        tags: vec![(0x08, &pdp().to_tag()[..])].into_iter().collect(),
+    };
+    let decoded = RioUdpPacket::from_bytes(&packet.to_bytes())
+        .unwrap()
+        .pdp()
+        .unwrap();
+    assert_eq!(decoded, pdp());
+    assert_eq!(decoded.total_current(), 300.0);
+    assert_eq!(decoded.bus_voltage(), 12.0);
+    assert!((decoded.temperature() - 25.07).abs() < 0.01);
+
+    assert!(PdpLog::from_tag(&[0; 3]).is_err());
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut history = PdpHistory::new(3);
+    for n in 0..5 {
+        let mut pdp = PdpLog::default();
+        pdp.currents[4] = n as f32;
+        history.push(PdpSample { time: at(n), pdp });
+    }
+    assert_eq!(history.len(), 3);
+    assert_eq!(
+        history.channel(4),
+        vec![(at(2), 2.0), (at(3), 3.0), (at(4), 4.0)]
+    );
+    assert_eq!(history.peak_currents()[4], 4.0);
+    assert_eq!(history.latest().unwrap().time, at(4));
+}
+
+#[test]
+fn exports_total_current_and_temperature() {
+    let record = DsLogRecord {
+        trip_time_ms: 0.0,
+        packet_loss: 0.0,
+        battery_voltage: 12.0,
+        rio_cpu: 0.0,
+        control: Control::empty(),
+        status: Status::empty(),
+        trace: Trace::empty(),
+        watchdog: false,
+        can_utilization: 0.0,
+        wifi_db: 0.0,
+        bandwidth_mb: 0.0,
+        pdp: pdp(),
+        pdh: None,
+    };
+    let config = ExportConfig {
+        format: Format::Csv,
+        columns: vec![Column::PdpCurrent(2), Column::TotalCurrent],
+        ..Default::default()
+    };
+    let mut exporter = TelemetryExporter::new(Vec::new(), config).unwrap();
+    exporter.write_record(at(0), &record).unwrap();
+    let csv = String::from_utf8(exporter.into_inner()).unwrap();
+    assert_eq!(csv, "pdp_current_2,total_current\n5,300\n");
+}
+
+#[test]
+fn driver_station_keeps_samples() {
+    let rio = common::start_mock();
+    rio.set_status(RobotStatus {
+        tags: vec![(0x08, pdp().to_tag())],
+        ..RobotStatus::default()
+    });
+
+    let mut ds = DriverStation::new();
+    let samples = ds.pdp_samples();
+    common::connect(&rio, &mut ds);
+
+    let sample = samples.recv_timeout(Duration::from_secs(3)).unwrap();
+    assert_eq!(sample.pdp, pdp());
+    assert_eq!(ds.pdp().unwrap().pdp, pdp());
+    assert!(!ds.pdp_history().is_empty());
+    assert_eq!(ds.telemetry().pdp, pdp());
+}
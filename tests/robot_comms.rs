@@ -0,0 +1,189 @@
+extern crate libds;
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::time::Duration;
+
+use libds::{
+    interlock::{EnableRefusal, Interlocks},
+    joystick::Joystick,
+    messages::ds::tcp::{MatchInfo, Tag},
+    messages::rio::{RioTcpPacket, RioUdpPacket, Status, Trace},
+    robot::{RobotComms, RobotConfig},
+    states::{Alliance, MatchType, RobotMode},
+    DriverStation,
+};
+
+use common::{local_config, wait_for};
+
+fn start_robot() -> RobotComms {
+    RobotComms::start(RobotConfig {
+        udp_port: 0,
+        tcp_port: 0,
+        ds_port: None,
+        timeout: Duration::from_millis(200),
+        ..RobotConfig::default()
+    })
+    .unwrap()
+}
+
+fn connect(robot: &RobotComms) -> DriverStation {
+    let mut ds = DriverStation::new();
+    ds.connect_with(local_config(robot.udp_port(), robot.tcp_port()))
+        .unwrap();
+    ds
+}
+
+#[test]
+fn robot_follows_driver_station() {
+    let robot = start_robot();
+    let ds = connect(&robot);
+
+    let mut stick = Joystick::new(4, 2, 1);
+    stick.set_button(2, true).unwrap();
+    stick.set_axis(0, 64).unwrap();
+    ds.set_joystick(0, Some(stick.clone()));
+    ds.set_alliance(Alliance::Blue(3));
+    ds.set_mode(RobotMode::Auto);
+    ds.set_enabled(true).unwrap();
+
+    assert!(wait_for(|| robot.is_enabled()));
+    assert!(robot.is_ds_connected());
+    assert!(!robot.is_estopped());
+    assert!(!robot.is_fms_attached());
+    assert_eq!(robot.mode(), RobotMode::Auto);
+    assert_eq!(robot.alliance(), Some(Alliance::Blue(3)));
+    assert_eq!(robot.joystick(0), Some(stick));
+    assert_eq!(robot.joystick(1), None);
+
+    ds.set_game_data("RLR".to_string());
+    ds.set_match_info(MatchInfo {
+        competition: "Test".to_string(),
+        match_type: MatchType::Qualification,
+        match_number: 12,
+        replay_number: 1,
+    });
+    assert!(wait_for(|| robot.game_data() == "RLR"));
+    assert!(wait_for(|| robot
+        .match_info()
+        .is_some_and(|info| info.competition == "Test")));
+    assert_eq!(robot.match_info().unwrap().match_number, 12);
+
+    ds.set_enabled(false).unwrap();
+    assert!(wait_for(|| !robot.is_enabled()));
+}
+
+#[test]
+fn driver_station_sees_robot_status() {
+    let robot = start_robot();
+    robot.set_has_code(false);
+    robot.set_battery_voltage(12.5);
+
+    let ds = connect(&robot);
+    ds.set_interlocks(Interlocks {
+        require_robot_code: true,
+        ..Interlocks::default()
+    });
+
+    assert!(wait_for(|| ds.has_robot_comms()));
+    assert_eq!(ds.set_enabled(true), Err(EnableRefusal::NoRobotCode));
+
+    robot.set_has_code(true);
+    assert!(wait_for(|| ds.can_enable().is_ok()));
+    assert!((ds.battery_voltage() - 12.5).abs() < 0.01);
+}
+
+#[test]
+fn robot_disables_without_control_packets() {
+    let robot = start_robot();
+    let ds = UdpSocket::bind("127.0.0.1:0").unwrap();
+    ds.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    // Sequence 0x0001, comm version 1, enabled in teleop, no requests, red 1.
+    let control = [0x00, 0x01, 0x01, 0x04, 0x00, 0x00];
+    ds.send_to(&control, ("127.0.0.1", robot.udp_port()))
+        .unwrap();
+
+    let mut buf = [0u8; 64];
+    let (n, _) = ds.recv_from(&mut buf).unwrap();
+    let status = RioUdpPacket::from_bytes(&buf[..n]).unwrap();
+    assert_eq!(status.sequence_num, 1);
+    assert!(status.status.contains(Status::ENABLED));
+    assert!(status
+        .trace
+        .contains(Trace::ROBOT_CODE | Trace::TELEOP_MODE));
+    assert!(status.request_date);
+    assert!(robot.is_enabled());
+
+    assert!(wait_for(|| !robot.is_enabled()));
+    assert!(!robot.is_ds_connected());
+}
+
+#[test]
+fn robot_stays_estopped() {
+    let robot = start_robot();
+    let ds = UdpSocket::bind("127.0.0.1:0").unwrap();
+    ds.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let mut buf = [0u8; 64];
+
+    // Enabled and E-stopped in teleop, then enabled without the E-stop bit.
+    for (sequence, control) in [(1u8, 0x84u8), (2, 0x04)] {
+        ds.send_to(
+            &[0x00, sequence, 0x01, control, 0x00, 0x00],
+            ("127.0.0.1", robot.udp_port()),
+        )
+        .unwrap();
+        let (n, _) = ds.recv_from(&mut buf).unwrap();
+        let status = RioUdpPacket::from_bytes(&buf[..n]).unwrap();
+        assert_eq!(status.sequence_num, u16::from(sequence));
+        assert!(status.status.contains(Status::ESTOP));
+        assert!(!status.status.contains(Status::ENABLED));
+        assert!(status.trace.contains(Trace::DISABLED));
+    }
+
+    assert!(robot.is_ds_connected());
+    assert!(robot.is_estopped());
+    assert!(!robot.is_enabled());
+}
+
+#[test]
+fn robot_prints_to_console() {
+    let robot = start_robot();
+    let mut ds = TcpStream::connect(("127.0.0.1", robot.tcp_port())).unwrap();
+    ds.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    ds.write_all(
+        &MatchInfo {
+            competition: String::new(),
+            match_type: MatchType::None,
+            match_number: 0,
+            replay_number: 0,
+        }
+        .to_packet(),
+    )
+    .unwrap();
+
+    robot.print("hello");
+    robot.print("world");
+
+    let mut messages = Vec::new();
+    for _ in 0..2 {
+        let mut size = [0u8; 2];
+        ds.read_exact(&mut size).unwrap();
+        let mut frame = vec![0u8; u16::from_be_bytes(size) as usize];
+        ds.read_exact(&mut frame).unwrap();
+        match RioTcpPacket::from_bytes(&frame) {
+            Ok(RioTcpPacket::StandardOutput {
+                sequence_number,
+                message,
+                ..
+            }) => messages.push((sequence_number, message)),
+            _ => panic!("expected standard output"),
+        }
+    }
+    assert_eq!(
+        messages,
+        vec![(0, "hello".to_string()), (1, "world".to_string())]
+    );
+}
@@ -0,0 +1,331 @@
+extern crate chrono;
+extern crate libds;
+extern crate proptest;
+
+use chrono::{DateTime, TimeZone, Utc};
+use proptest::prelude::*;
+
+use libds::{
+    joystick::{AxisType, Joystick, JoystickType},
+    messages::ds::tcp::{GameData, JoystickDescriptor, MatchInfo, TcpTag},
+    messages::ds::udp::{Control, DsUdpPacket, Request},
+    messages::rio::{DeviceType, RioTcpPacket, RioUdpPacket, Status, Trace},
+    states::{Alliance, MatchType, RobotMode},
+};
+
+fn alliance() -> impl Strategy<Value = Alliance> {
+    prop_oneof![
+        (1..=3u8).prop_map(Alliance::Red),
+        (1..=3u8).prop_map(Alliance::Blue)
+    ]
+}
+
+fn mode() -> impl Strategy<Value = RobotMode> {
+    prop::sample::select(vec![RobotMode::Teleop, RobotMode::Test, RobotMode::Auto])
+}
+
+fn joystick() -> impl Strategy<Value = Joystick> {
+    (
+        prop::collection::vec(any::<bool>(), 0..32),
+        prop::collection::vec(any::<i8>(), 0..12),
+        prop::collection::vec(any::<i16>(), 0..4),
+    )
+        .prop_map(|(buttons, axes, povs)| {
+            let mut stick = Joystick::new(buttons.len() as u8, axes.len() as u8, povs.len() as u8);
+            for (i, pressed) in buttons.into_iter().enumerate() {
+                stick.set_button(i as u8, pressed).unwrap();
+            }
+            for (i, value) in axes.into_iter().enumerate() {
+                stick.set_axis(i as u8, value).unwrap();
+            }
+            for (i, value) in povs.into_iter().enumerate() {
+                stick.set_pov(i as u8, value).unwrap();
+            }
+            stick
+        })
+}
+
+fn tags(reserved: &'static [u8]) -> impl Strategy<Value = Vec<(u8, Vec<u8>)>> {
+    prop::collection::vec(
+        (
+            any::<u8>().prop_filter("reserved id", move |id| !reserved.contains(id)),
+            prop::collection::vec(any::<u8>(), 0..32),
+        ),
+        0..4,
+    )
+}
+
+fn text(max: usize) -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::char::range(' ', '~'), 0..max)
+        .prop_map(|chars| chars.into_iter().collect())
+}
+
+fn date() -> impl Strategy<Value = DateTime<Utc>> {
+    (
+        1900..2155i32,
+        1..=12u32,
+        1..=28u32,
+        0..24u32,
+        0..60u32,
+        0..60u32,
+        0..1_000_000u32,
+    )
+        .prop_map(|(year, month, day, hour, minute, second, micros)| {
+            Utc.ymd(year, month, day)
+                .and_hms_micro(hour, minute, second, micros)
+        })
+}
+
+fn ds_udp_packet() -> impl Strategy<Value = DsUdpPacket> {
+    (
+        (any::<u16>(), any::<u8>()),
+        (any::<bool>(), any::<bool>(), any::<bool>(), mode()),
+        any::<u8>().prop_map(Request::from_bits_truncate),
+        alliance(),
+        prop::collection::vec(prop::option::of(joystick()), 0..6),
+        prop::option::of(-1.0e3f32..1.0e3),
+        (prop::option::of(date()), prop::option::of(text(40))),
+        tags(&[0x07, 0x0c, 0x0f, 0x10]),
+    )
+        .prop_map(
+            |(
+                (sequence_num, comm_version),
+                (estop, fms_connected, enabled, mode),
+                request,
+                alliance,
+                joysticks,
+                countdown,
+                (date, timezone),
+                tags,
+            )| {
+                let mut control = Control::from_bits_truncate(mode as u8);
+                control.set(Control::ESTOP, estop);
+                control.set(Control::FMS_CONNECTED, fms_connected);
+                control.set(Control::ENABLED, enabled);
+                DsUdpPacket {
+                    sequence_num,
+                    comm_version,
+                    control,
+                    request,
+                    alliance,
+                    joysticks,
+                    countdown,
+                    date,
+                    timezone,
+                    tags,
+                }
+            },
+        )
+}
+
+fn ds_tcp_tag() -> impl Strategy<Value = TcpTag> {
+    let stick_types = vec![
+        JoystickType::Unknown,
+        JoystickType::XInputGamepad,
+        JoystickType::XInputFlightStick,
+        JoystickType::HIDJoystick,
+        JoystickType::HIDGamepad,
+    ];
+    let axis_types = vec![
+        AxisType::X,
+        AxisType::Y,
+        AxisType::Z,
+        AxisType::Twist,
+        AxisType::Throttle,
+    ];
+    let match_types = vec![
+        MatchType::None,
+        MatchType::Practice,
+        MatchType::Qualification,
+        MatchType::Elimination,
+    ];
+
+    prop_oneof![
+        (
+            0..6u8,
+            any::<bool>(),
+            prop::sample::select(stick_types),
+            text(40),
+            prop::collection::vec(prop::sample::select(axis_types), 0..12),
+            any::<u8>(),
+            any::<u8>(),
+        )
+            .prop_map(
+                |(index, is_xbox, stick_type, name, axis_types, button_count, pov_count)| {
+                    TcpTag::JoystickDescriptor(JoystickDescriptor {
+                        index,
+                        is_xbox,
+                        stick_type,
+                        name,
+                        axis_types,
+                        button_count,
+                        pov_count,
+                    })
+                }
+            ),
+        (
+            text(40),
+            prop::sample::select(match_types),
+            any::<u16>(),
+            any::<u8>(),
+        )
+            .prop_map(|(competition, match_type, match_number, replay_number)| {
+                TcpTag::MatchInfo(MatchInfo {
+                    competition,
+                    match_type,
+                    match_number,
+                    replay_number,
+                })
+            }),
+        text(40).prop_map(|data| TcpTag::GameData(GameData::new(data))),
+    ]
+}
+
+fn rio_udp_packet() -> impl Strategy<Value = RioUdpPacket<'static>> {
+    (
+        any::<u16>(),
+        any::<u8>(),
+        any::<u8>().prop_map(Status::from_bits_truncate),
+        any::<u8>().prop_map(Trace::from_bits_truncate),
+        (any::<u8>(), any::<u8>()),
+        any::<bool>(),
+        tags(&[]),
+    )
+        .prop_map(
+            |(sequence_num, comm_version, status, trace, (volts, fraction), request_date, tags)| {
+                RioUdpPacket {
+                    sequence_num,
+                    comm_version,
+                    status,
+                    trace,
+                    battery_voltage: f32::from(volts) + f32::from(fraction) / 256.0,
+                    request_date,
+                    tags: tags
+                        .iter()
+                        .map(|(id, contents)| (*id, contents.as_slice()))
+                        .collect(),
+                }
+            },
+        )
+}
+
+fn rio_tcp_packet() -> impl Strategy<Value = RioTcpPacket> {
+    let device_types = vec![
+        DeviceType::Software,
+        DeviceType::CANTalon,
+        DeviceType::PDP,
+        DeviceType::PCM,
+    ];
+
+    prop_oneof![
+        text(80).prop_map(RioTcpPacket::RadioEvent),
+        (
+            any::<u16>(),
+            any::<u8>(),
+            prop::collection::vec(any::<u8>(), 0..32)
+        )
+            .prop_map(|(team_num, unknown, entries)| RioTcpPacket::UsageReport {
+                team_num,
+                unknown,
+                entries,
+            }),
+        (any::<u16>(), any::<u16>())
+            .prop_map(|(comms, twelve_v)| RioTcpPacket::DisableFaults { comms, twelve_v }),
+        (any::<u16>(), any::<u16>(), any::<u16>()).prop_map(
+            |(six_v, five_v, three_point_three_v)| RioTcpPacket::RailFaults {
+                six_v,
+                five_v,
+                three_point_three_v,
+            }
+        ),
+        (
+            prop::sample::select(device_types),
+            any::<u16>(),
+            any::<u8>(),
+            text(40),
+            text(40),
+        )
+            .prop_map(|(device_type, unknown, id, name, version)| {
+                RioTcpPacket::VersionInfo {
+                    device_type,
+                    unknown,
+                    id,
+                    name,
+                    version,
+                }
+            }),
+        (
+            -1.0e6f32..1.0e6,
+            any::<u16>(),
+            any::<bool>(),
+            any::<u16>(),
+            any::<bool>(),
+            (text(80), text(80), text(80)),
+        )
+            .prop_map(
+                |(
+                    timestamp,
+                    sequence_number,
+                    print_msg,
+                    error_code,
+                    is_error,
+                    (details, location, call_stack),
+                )| RioTcpPacket::ErrorMessage {
+                    timestamp,
+                    sequence_number,
+                    print_msg,
+                    error_code,
+                    is_error,
+                    details,
+                    location,
+                    call_stack,
+                }
+            ),
+        (-1.0e6f32..1.0e6, any::<u16>(), text(80)).prop_map(
+            |(timestamp, sequence_number, message)| RioTcpPacket::StandardOutput {
+                timestamp,
+                sequence_number,
+                message,
+            }
+        ),
+        prop::collection::vec(any::<u8>(), 0..32).prop_map(RioTcpPacket::Unknown),
+    ]
+}
+
+/// Removes the length prefix from an encoded TCP tag, checking that it's right.
+fn unframe(packet: &[u8]) -> &[u8] {
+    let size = u16::from_be_bytes([packet[0], packet[1]]) as usize;
+    assert_eq!(size, packet.len() - 2);
+    &packet[2..]
+}
+
+proptest! {
+    #[test]
+    fn ds_udp_packet_roundtrips(packet in ds_udp_packet()) {
+        prop_assert_eq!(DsUdpPacket::decode(&packet.encode()), Ok(packet));
+    }
+
+    #[test]
+    fn ds_tcp_tag_roundtrips(tag in ds_tcp_tag()) {
+        prop_assert_eq!(TcpTag::from_bytes(unframe(&tag.to_packet())), Ok(tag));
+    }
+
+    #[test]
+    fn rio_udp_packet_roundtrips(packet in rio_udp_packet()) {
+        let bytes = packet.to_bytes();
+        prop_assert_eq!(RioUdpPacket::from_bytes(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn rio_tcp_packet_roundtrips(packet in rio_tcp_packet()) {
+        prop_assert_eq!(RioTcpPacket::from_bytes(unframe(&packet.to_packet())), Ok(packet));
+    }
+
+    #[test]
+    fn decoding_garbage_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let _ = DsUdpPacket::decode(&bytes);
+        let _ = TcpTag::from_bytes(&bytes);
+        let _ = RioUdpPacket::from_bytes(&bytes);
+        let _ = RioTcpPacket::from_bytes(&bytes);
+    }
+}